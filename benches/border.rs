@@ -0,0 +1,47 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use gof_lib::ui::{border::Border, rect::Rect};
+
+/// Splits a `width`x`height` terminal into a `columns`x`rows` grid of windows,
+/// laid out the same way `UI::recalculate_ui` does (neighbours share an edge).
+fn grid(width: u16, height: u16, columns: u16, rows: u16) -> Vec<Rect> {
+    let (cell_w, cell_h) = ((width - 1) / columns, (height - 1) / rows);
+    let mut rects = Vec::new();
+
+    for row in 0..rows {
+        for column in 0..columns {
+            rects.push((column * cell_w, row * cell_h, cell_w, cell_h).into());
+        }
+    }
+
+    rects
+}
+
+fn build_border(rects: &[Rect]) -> Border {
+    let mut border = Border::new();
+
+    for rect in rects {
+        border.append(rect.border_points());
+    }
+
+    border
+}
+
+fn bench_border(c: &mut Criterion) {
+    let mut group = c.benchmark_group("border_400x120");
+
+    for (columns, rows) in [(1, 1), (4, 2), (8, 4), (16, 8), (40, 12)] {
+        let rects = grid(400, 120, columns, rows);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{columns}x{rows}")),
+            &rects,
+            |b, rects| b.iter(|| build_border(rects)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_border);
+criterion_main!(benches);
@@ -5,11 +5,15 @@ use std::{
         BufWriter, Write,
     }, 
     error::Error, 
+    sync::mpsc::{ self, Receiver, RecvTimeoutError },
+    thread,
+    time::{ Duration, Instant },
 };
 
 use crossterm::{
     execute, 
     cursor::MoveTo, 
+    event::{ read, EnableBracketedPaste, DisableBracketedPaste },
     terminal::{
         ClearType, 
         Clear, 
@@ -20,8 +24,10 @@ use crossterm::{
     }, 
 };
 
-use crate::ui::{ UI, window::Window };
+use crate::events::{ AppEvent, TimerId, Waker };
+use crate::ui::{ UI, bus::State, window::Window };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Event {
     Draw,
     RecalculateUI,
@@ -29,31 +35,106 @@ pub enum Event {
     Exit
 }
 
-pub struct Application<STATE> {
+impl Event {
+    // When several events are handled before one draw, the strongest wins
+    fn weight(&self) -> u8 {
+        match self {
+            Event::Sleep => 0,
+            Event::Draw => 1,
+            Event::RecalculateUI => 2,
+            Event::Exit => 3,
+        }
+    }
+
+    /// Whichever of the two is stronger.
+    pub fn max(self, other: Event) -> Event {
+        if other.weight() > self.weight() { other } else { self }
+    }
+}
+
+pub struct Application<STATE: State> {
     pub ui: UI<STATE>,
-    queue: BufWriter<Stdout>
+    queue: BufWriter<Stdout>,
+    events: Receiver<AppEvent>,
+    waker: Waker,
 }
 
-impl<STATE: Clone> Application<STATE> {
+impl<STATE: State> Application<STATE> {
     pub fn new(windows: Vec<Box<dyn Window<STATE>>>, state: STATE) -> Self {
-        let ui = UI::new(windows, state);
+        let (sender, events) = mpsc::channel();
+        let waker = Waker::new(sender);
+
+        let mut ui = UI::new(windows, state);
+        ui.set_waker(waker.clone());
 
         execute!(
             stdout(),
             EnterAlternateScreen,
+            EnableBracketedPaste,
             Clear(ClearType::All),
             MoveTo(0, 0)
         ).unwrap();
         
         Self {
             ui,
-            queue: BufWriter::new(stdout())
+            queue: BufWriter::new(stdout()),
+            events,
+            waker,
         }
     }
 
+    /// For waking the main loop from other threads.
+    pub fn waker(&self) -> Waker {
+        self.waker.clone()
+    }
+
+    /// Sends an `AppEvent::Timer` to the main loop once, after `delay`.
+    pub fn set_timeout(&mut self, delay: Duration) -> TimerId {
+        self.ui.timers.set_timeout(delay)
+    }
+
+    /// Sends an `AppEvent::Timer` to the main loop every `period`.
+    pub fn set_interval(&mut self, period: Duration) -> TimerId {
+        self.ui.timers.set_interval(period)
+    }
+
+    pub fn cancel_timer(&mut self, id: TimerId) {
+        self.ui.timers.cancel(id);
+    }
+
+    /// Blocks until there is something to do, then returns it along with
+    /// anything else that is already waiting.
+    fn wait(&mut self) -> Result<Vec<AppEvent>, Box<dyn Error>> {
+        let mut events = Vec::new();
+
+        let first = match self.ui.timers.next_deadline() {
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+
+                match self.events.recv_timeout(timeout) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return Err("Event source disconnected.".into()),
+                }
+            },
+            None => Some(self.events.recv()?),
+        };
+
+        events.extend(first);
+        events.extend(self.events.try_iter());
+
+        let timers = self.ui.timers.expired(Instant::now());
+        events.extend(timers.into_iter().map(AppEvent::Timer));
+
+        Ok(events)
+    }
+
+    /// Runs until `main_loop` returns `Event::Exit`. The loop sleeps until
+    /// there is input, a timer fires, or something in the background wakes
+    /// it, and only redraws when asked to.
     pub fn run<START, LOOP>(mut self, mut on_start: START, mut main_loop: LOOP) -> Result<(), Box<dyn Error>> 
     where START: FnMut(&mut UI<STATE>),
-          LOOP: FnMut(&mut UI<STATE>) -> Event,
+          LOOP: FnMut(&mut UI<STATE>, AppEvent) -> Event,
     {
         self.ui.recalculate_ui()?;
         self.ui.select_next_window()?;
@@ -63,15 +144,28 @@ impl<STATE: Clone> Application<STATE> {
 
         self.draw()?;
         self.ui.move_cursor_to_window_origin();
+        self.ui.move_cursor_to_selected();
         self.ui.draw_cursor(&mut self.queue)?;
 
         on_start(&mut self.ui);
         self.queue.flush()?;
 
+        spawn_input_thread(self.waker.clone());
+
         loop {
-            let loop_res = main_loop(&mut self.ui);
+            let mut loop_res = Event::Sleep;
 
-            self.ui.update_windows_state();
+            for event in self.wait()? {
+                loop_res = loop_res.max(main_loop(&mut self.ui, event));
+            }
+
+            let jobs_changed = self.ui.run_jobs();
+
+            if self.ui.tick_windows() || jobs_changed {
+                loop_res = loop_res.max(Event::Draw);
+                // Background work can move the cursor too, e.g. by jumping
+                self.ui.move_cursor_to_selected();
+            }
 
             match loop_res {
                 Event::Draw => {
@@ -105,6 +199,7 @@ impl<STATE: Clone> Application<STATE> {
             stdout(),
             Clear(ClearType::All),
             MoveTo(0, 0),
+            DisableBracketedPaste,
             LeaveAlternateScreen
         )?;
 
@@ -113,3 +208,17 @@ impl<STATE: Clone> Application<STATE> {
         Ok(())
     }
 }
+
+/// Forwards terminal input to the main loop. The thread blocks on `read`
+/// and lives until the process exits.
+fn spawn_input_thread(waker: Waker) {
+    thread::spawn(move || loop {
+        match read() {
+            Ok(event) => waker.send(AppEvent::Input(event)),
+            Err(e) => {
+                error!("Error reading input: {:?}", e);
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,166 @@
+//! A language server that knows nothing about any language, for testing the
+//! client. It reports a warning for every `TODO` and an error for every
+//! `FIXME`, hovers and goes to the definitions of words, taking a word's
+//! first appearance as its definition, and completes words from the file.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{ stdin, stdout, BufReader };
+
+use ropey::Rope;
+use serde_json::{ json, Value };
+
+use gof_lib::lsp::protocol::{ self, PositionEncoding };
+
+const ENCODING: PositionEncoding = PositionEncoding::Utf16;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(stdin().lock());
+    let mut writer = stdout().lock();
+    let mut documents: HashMap<String, Rope> = HashMap::new();
+
+    while let Some(message) = protocol::read_message(&mut reader)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "positionEncoding": ENCODING.name(),
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "completionProvider": { },
+                    "definitionProvider": true,
+                }
+            }),
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let text = match method {
+                    "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+                    _ => params["contentChanges"][0]["text"].as_str(),
+                };
+
+                let text = Rope::from_str(text.unwrap_or_default());
+                let diagnostics = diagnostics(&text);
+
+                documents.insert(uri.clone(), text);
+                protocol::write_message(&mut writer, &protocol::notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": diagnostics })
+                ))?;
+
+                continue;
+            },
+            "textDocument/hover" | "textDocument/completion" | "textDocument/definition" => {
+                let Some(text) = documents.get(&uri) else { continue };
+                let position = ENCODING.from_lsp(text, &params["position"]).unwrap_or_default();
+
+                match method {
+                    "textDocument/hover" => hover(text, position),
+                    "textDocument/completion" => completion(text, position),
+                    _ => definition(text, &uri, position),
+                }
+            },
+            "shutdown" => Value::Null,
+            "exit" => break,
+            _ => continue,
+        };
+
+        if let Some(id) = message.get("id") {
+            protocol::write_message(&mut writer, &protocol::response(id.clone(), result))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Every word in `line`, with the column it starts at.
+fn words(line: &str) -> Vec<(usize, String)> {
+    let mut words = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+
+    for (column, c) in line.chars().enumerate() {
+        if c.is_alphanumeric() || c == '_' {
+            current.get_or_insert_with(|| (column, String::new())).1.push(c);
+        } else if let Some(word) = current.take() {
+            words.push(word);
+        }
+    }
+
+    words.extend(current);
+    words
+}
+
+/// The word at `(column, line)`, and the column it starts at.
+fn word_at(text: &Rope, (column, line): (usize, usize)) -> Option<(usize, String)> {
+    words(&text.line(line).to_string()).into_iter()
+        .find(|(start, word)| (*start..=start + word.chars().count()).contains(&column))
+}
+
+fn range(text: &Rope, line: usize, start: usize, end: usize) -> Value {
+    json!({
+        "start": ENCODING.to_lsp(text, (start, line)),
+        "end": ENCODING.to_lsp(text, (end, line)),
+    })
+}
+
+fn diagnostics(text: &Rope) -> Vec<Value> {
+    let mut diagnostics = Vec::new();
+
+    for (line, content) in text.lines().enumerate() {
+        for (start, word) in words(&content.to_string()) {
+            let severity = match word.as_str() {
+                "FIXME" => 1,
+                "TODO" => 2,
+                _ => continue,
+            };
+
+            diagnostics.push(json!({
+                "range": range(text, line, start, start + word.chars().count()),
+                "severity": severity,
+                "source": "mock",
+                "message": format!("found {}", word),
+            }));
+        }
+    }
+
+    diagnostics
+}
+
+fn hover(text: &Rope, position: (usize, usize)) -> Value {
+    match word_at(text, position) {
+        Some((_, word)) => json!({
+            "contents": { "kind": "markdown", "value": format!("```\n{}\n```\nA word of {} letters", word, word.chars().count()) }
+        }),
+        None => Value::Null,
+    }
+}
+
+fn completion(text: &Rope, (column, line): (usize, usize)) -> Value {
+    let prefix = word_at(text, (column, line))
+        .map(|(start, word)| word.chars().take(column - start).collect::<String>())
+        .unwrap_or_default();
+
+    let mut labels: Vec<String> = text.lines()
+        .flat_map(|l| words(&l.to_string()))
+        .map(|(_, word)| word)
+        .filter(|word| word.starts_with(&prefix) && *word != prefix)
+        .collect();
+
+    labels.sort();
+    labels.dedup();
+
+    Value::Array(labels.into_iter().map(|label| json!({ "label": label, "detail": "word" })).collect())
+}
+
+fn definition(text: &Rope, uri: &str, position: (usize, usize)) -> Value {
+    let Some((_, word)) = word_at(text, position) else { return Value::Null };
+
+    for (line, content) in text.lines().enumerate() {
+        if let Some((start, _)) = words(&content.to_string()).into_iter().find(|(_, w)| *w == word) {
+            return json!({ "uri": uri, "range": range(text, line, start, start + word.chars().count()) });
+        }
+    }
+
+    Value::Null
+}
@@ -0,0 +1,132 @@
+//! Running `cargo check` and reading the compiler messages it prints as JSON.
+
+use std::error::Error;
+use std::io::{ BufRead, BufReader };
+use std::path::{ Path, PathBuf };
+use std::process::{ Command, Stdio };
+
+use serde_json::Value;
+
+use crate::diagnostics::{ Diagnostic, Severity };
+use crate::jobs::JobContext;
+use crate::ui::bus::State;
+
+/// Runs `cargo check` in `root` with `args` added, returning every problem
+/// the compiler found in the order it found them. Progress is counted in
+/// crates checked. Stops early, with what it has, if the job is cancelled.
+pub fn check<S: State>(root: &Path, args: &[String], context: &JobContext<S>)
+-> Result<Vec<(PathBuf, Diagnostic)>, Box<dyn Error>> {
+    let mut child = Command::new("cargo")
+        .args([ "check", "--message-format=json" ])
+        .args(args)
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let workspace = workspace_root(root).unwrap_or_else(|_| root.to_path_buf());
+    let stdout = child.stdout.take().ok_or("cargo has no stdout")?;
+    let mut found = Vec::new();
+    let mut crates = 0;
+    let mut success = None;
+
+    for line in BufReader::new(stdout).lines() {
+        if context.is_cancelled() {
+            let _ = child.kill();
+            break;
+        }
+
+        let Ok(message) = serde_json::from_str::<Value>(&line?) else { continue };
+
+        match message["reason"].as_str() {
+            Some("compiler-message") => {
+                if let Some(entry) = parse_compiler_message(&workspace, &message) {
+                    if !found.contains(&entry) {
+                        found.push(entry);
+                    }
+                }
+            },
+            Some("compiler-artifact") => {
+                crates += 1;
+                context.progress(crates, None);
+            },
+            Some("build-finished") => success = message["success"].as_bool(),
+            _ => { }
+        }
+    }
+
+    let status = child.wait()?;
+
+    // A failed build with nothing to show for it, like a broken Cargo.toml
+    if !status.success() && success.is_none() && found.is_empty() && !context.is_cancelled() {
+        return Err(format!("cargo check failed ({})", status).into());
+    }
+
+    Ok(found)
+}
+
+/// The root of the workspace `root` is in, where cargo runs the compiler
+/// for its members.
+pub fn workspace_root(root: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let output = Command::new("cargo")
+        .args([ "locate-project", "--workspace", "--message-format", "plain" ])
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!("cargo locate-project failed ({})", output.status).into());
+    }
+
+    let manifest = PathBuf::from(String::from_utf8(output.stdout)?.trim());
+
+    Ok(manifest.parent().ok_or("cargo gave no workspace")?.to_path_buf())
+}
+
+/// A line of `cargo check --message-format=json` as a diagnostic at its
+/// primary span, if it's a compiler message with one. Cargo runs rustc in
+/// the workspace root, so span paths are relative to `workspace`, except
+/// for packages outside it, whose paths are absolute.
+pub fn parse_compiler_message(workspace: &Path, line: &Value) -> Option<(PathBuf, Diagnostic)> {
+    if line["reason"].as_str()? != "compiler-message" {
+        return None;
+    }
+
+    let message = &line["message"];
+    let severity = match message["level"].as_str()? {
+        "error" | "error: internal compiler error" => Severity::Error,
+        "warning" => Severity::Warning,
+        "note" => Severity::Info,
+        "help" => Severity::Hint,
+        _ => return None,
+    };
+
+    let span = message["spans"].as_array()?
+        .iter()
+        .find(|s| s["is_primary"].as_bool() == Some(true))?;
+
+    // Lines and columns are 1-based, and columns count chars
+    let position = |line: &str, column: &str| -> Option<(usize, usize)> {
+        let line = span[line].as_u64()?.saturating_sub(1) as usize;
+        let column = span[column].as_u64()?.saturating_sub(1) as usize;
+
+        Some((column, line))
+    };
+
+    let text = match message["code"]["code"].as_str() {
+        Some(code) => format!("{} [{}]", message["message"].as_str()?, code),
+        None => message["message"].as_str()?.to_string(),
+    };
+
+    let diagnostic = Diagnostic {
+        start: position("line_start", "column_start")?,
+        end: position("line_end", "column_end")?,
+        severity,
+        message: text,
+        source: Some("rustc".to_string()),
+    };
+
+    Some((workspace.join(span["file_name"].as_str()?), diagnostic))
+}
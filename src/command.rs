@@ -0,0 +1,213 @@
+//! Parsing for the commands typed after `:` in the command gutter.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    /// `.`, the cursor's line
+    Current,
+    /// `$`, the last line
+    Last,
+    /// A 1-based line number
+    Line(usize),
+}
+
+impl Address {
+    /// The 0-based line this refers to.
+    pub fn resolve(self, current: usize, last: usize) -> usize {
+        match self {
+            Address::Current => current,
+            Address::Last => last,
+            Address::Line(n) => n.saturating_sub(1).min(last),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: Address,
+    pub end: Address,
+}
+
+impl LineRange {
+    pub const CURRENT: LineRange = LineRange { start: Address::Current, end: Address::Current };
+    pub const ALL: LineRange = LineRange { start: Address::Line(1), end: Address::Last };
+
+    /// The 0-based, inclusive lines this refers to.
+    pub fn resolve(self, current: usize, last: usize) -> (usize, usize) {
+        let start = self.start.resolve(current, last);
+        let end = self.end.resolve(current, last);
+
+        (start.min(end), start.max(end))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubstituteFlags {
+    /// `g`: replace every match on a line, not just the first
+    pub global: bool,
+    /// `c`: ask before each replacement
+    pub confirm: bool,
+    /// `i` / `I`: override smart-case
+    pub ignore_case: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// `:[range]s/pattern/replacement/[flags]`. An empty pattern reuses the
+    /// last search. `replacement` is already in `regex`'s expansion syntax.
+    Substitute {
+        range: LineRange,
+        pattern: String,
+        replacement: String,
+        flags: SubstituteFlags,
+    },
+    /// `:w`, saving the whole file
+    Write,
+    /// `:make [args]`, running `cargo check` with `args` added
+    Make(Vec<String>),
+    /// `:cn`, jumping to the next entry in the quickfix list
+    NextError,
+    /// `:cp` or `:cN`, jumping to the previous one
+    PreviousError,
+    /// `:hunk`, showing how the lines at the cursor differ from `HEAD`
+    PreviewHunk,
+}
+
+pub fn parse(input: &str) -> Result<Command, String> {
+    let input = input.trim();
+    let (range, rest) = parse_range(input)?;
+
+    let name_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+    let (name, args) = rest.split_at(name_len);
+
+    match name {
+        "s" | "substitute" => parse_substitute(range.unwrap_or(LineRange::CURRENT), args),
+        "w" | "write" if range.is_some() => Err("Writing part of a file isn't supported".to_string()),
+        "w" | "write" if !args.trim().is_empty() => Err(format!("Trailing characters: {}", args.trim())),
+        "w" | "write" => Ok(Command::Write),
+        _ if range.is_some() && matches!(name, "make" | "cn" | "cnext" | "cp" | "cprevious" | "cN" | "cNext" | "hunk") => {
+            Err("No range allowed".to_string())
+        },
+        "make" => Ok(Command::Make(args.split_whitespace().map(String::from).collect())),
+        "cn" | "cnext" => Ok(Command::NextError),
+        "cp" | "cprevious" | "cN" | "cNext" => Ok(Command::PreviousError),
+        "hunk" => Ok(Command::PreviewHunk),
+        "" => Err("Missing command".to_string()),
+        _ => Err(format!("Not an editor command: {}", rest)),
+    }
+}
+
+fn parse_address(input: &str) -> (Option<Address>, &str) {
+    if let Some(rest) = input.strip_prefix('.') {
+        return (Some(Address::Current), rest);
+    }
+
+    if let Some(rest) = input.strip_prefix('$') {
+        return (Some(Address::Last), rest);
+    }
+
+    let digits = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+
+    match input[..digits].parse() {
+        Ok(n) => (Some(Address::Line(n)), &input[digits..]),
+        Err(_) => (None, input),
+    }
+}
+
+fn parse_range(input: &str) -> Result<(Option<LineRange>, &str), String> {
+    if let Some(rest) = input.strip_prefix('%') {
+        return Ok((Some(LineRange::ALL), rest));
+    }
+
+    let (start, rest) = parse_address(input);
+    let Some(start) = start else {
+        return Ok((None, input));
+    };
+
+    match rest.strip_prefix(',') {
+        Some(rest) => match parse_address(rest) {
+            (Some(end), rest) => Ok((Some(LineRange { start, end }), rest)),
+            (None, _) => Err("Invalid range".to_string()),
+        },
+        None => Ok((Some(LineRange { start, end: start }), rest)),
+    }
+}
+
+/// Splits `input` at the first unescaped `delimiter`, removing the escapes
+/// from escaped delimiters.
+fn split_delimited(input: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut part = String::new();
+    let mut chars = input.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some((_, next)) if next == delimiter => part.push(next),
+                Some((_, next)) => {
+                    part.push(c);
+                    part.push(next);
+                },
+                None => part.push(c),
+            }
+        } else if c == delimiter {
+            return (part, Some(&input[i + c.len_utf8()..]));
+        } else {
+            part.push(c);
+        }
+    }
+
+    (part, None)
+}
+
+/// Converts a vim-style replacement (`&`, `\1`, `\n`) into `regex`'s
+/// expansion syntax (`${0}`, `${1}`, a literal newline).
+fn convert_replacement(replacement: &str) -> String {
+    let mut converted = String::new();
+    let mut chars = replacement.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => converted.push_str("${0}"),
+            '$' => converted.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(d @ '0'..='9') => converted.push_str(&format!("${{{}}}", d)),
+                Some('n') | Some('r') => converted.push('\n'),
+                Some('t') => converted.push('\t'),
+                Some('$') => converted.push_str("$$"),
+                Some(other) => converted.push(other),
+                None => converted.push('\\'),
+            },
+            _ => converted.push(c),
+        }
+    }
+
+    converted
+}
+
+fn parse_substitute(range: LineRange, args: &str) -> Result<Command, String> {
+    let mut chars = args.chars();
+    let delimiter = match chars.next() {
+        Some(c) if !c.is_alphanumeric() && c != '\\' && c != '"' && !c.is_whitespace() => c,
+        _ => return Err("E146: Regular expressions can't be delimited by letters".to_string()),
+    };
+
+    let (pattern, rest) = split_delimited(chars.as_str(), delimiter);
+    let (replacement, rest) = split_delimited(rest.unwrap_or_default(), delimiter);
+
+    let mut flags = SubstituteFlags::default();
+    for flag in rest.unwrap_or_default().trim().chars() {
+        match flag {
+            'g' => flags.global = true,
+            'c' => flags.confirm = true,
+            'i' => flags.ignore_case = Some(true),
+            'I' => flags.ignore_case = Some(false),
+            _ => return Err(format!("Trailing characters: {}", flag)),
+        }
+    }
+
+    Ok(Command::Substitute {
+        range,
+        pattern,
+        replacement: convert_replacement(&replacement),
+        flags,
+    })
+}
@@ -0,0 +1,122 @@
+//! Making one edit at several cursors. Cursors are edited at from the last
+//! in the text to the first, and every edit moves the other cursors with
+//! the text it changed. Positions here are char indices.
+
+use std::ops::Range;
+
+use ropey::Rope;
+
+use crate::history::Edit;
+use crate::motion::Span;
+
+/// Where `at` is after `edit`. Positions after what it replaced move with
+/// the text, and positions inside what it removed go to its start.
+pub fn map_position(at: usize, edit: &Edit) -> usize {
+    let start = edit.char_idx;
+    let end = start + edit.removed.chars().count();
+
+    if at < start {
+        at
+    } else if at >= end {
+        at - (end - start) + edit.inserted.chars().count()
+    } else {
+        start
+    }
+}
+
+/// The chars `span` covers, line breaks and all.
+pub fn span_range(content: &Rope, span: &Span) -> Range<usize> {
+    match span {
+        Span::Chars(range) => range.clone(),
+        Span::Lines(lines) => {
+            let end = (*lines.end() + 1).min(content.len_lines());
+            content.line_to_char(*lines.start())..content.line_to_char(end)
+        },
+    }
+}
+
+/// Merges spans that overlap, so nothing is acted on twice. Each merged
+/// span comes with the indices of the spans it was made from, in the order
+/// they are in the text. Lines stay lines, unless they're merged with chars.
+pub fn merge_spans(content: &Rope, spans: &[Span]) -> Vec<(Span, Vec<usize>)> {
+    let mut order: Vec<usize> = (0..spans.len()).collect();
+    order.sort_by_key(|i| span_range(content, &spans[*i]).start);
+
+    let mut merged: Vec<(Span, Vec<usize>)> = Vec::new();
+
+    for i in order {
+        let span = &spans[i];
+        let range = span_range(content, span);
+
+        let Some((last, members)) = merged.last_mut() else {
+            merged.push((span.clone(), vec![ i ]));
+            continue;
+        };
+
+        let last_range = span_range(content, last);
+
+        if range.start >= last_range.end {
+            merged.push((span.clone(), vec![ i ]));
+            continue;
+        }
+
+        *last = match (&*last, span) {
+            (Span::Lines(a), Span::Lines(b)) => Span::Lines(*a.start()..=*a.end().max(b.end())),
+            _ => Span::Chars(last_range.start..last_range.end.max(range.end)),
+        };
+        members.push(i);
+    }
+
+    merged
+}
+
+/// Cursors an edit is being made at, one after another.
+#[derive(Debug)]
+pub struct CursorEdits {
+    positions: Vec<usize>,
+    // Indices into `positions` in the order they're edited at, and how many
+    // have been
+    order: Vec<usize>,
+    visited: usize,
+}
+
+impl CursorEdits {
+    pub fn new(positions: Vec<usize>) -> Self {
+        let mut order: Vec<usize> = (0..positions.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse(positions[*i]));
+
+        Self { positions, order, visited: 0 }
+    }
+
+    /// Moves every cursor but the one `next` last returned through `edits`,
+    /// made in order at that one, and puts that one at `cursor`.
+    pub fn edited(&mut self, edits: &[Edit], cursor: usize) {
+        let Some(current) = self.visited.checked_sub(1).map(|i| self.order[i]) else { return };
+
+        for (i, at) in self.positions.iter_mut().enumerate() {
+            if i != current {
+                *at = edits.iter().fold(*at, map_position);
+            }
+        }
+
+        self.positions[current] = cursor;
+    }
+
+    /// Where every cursor is now, in the order given to `new`.
+    pub fn positions(&self) -> &[usize] {
+        &self.positions
+    }
+}
+
+impl Iterator for CursorEdits {
+    /// The next cursor to edit at: its index among the positions given to
+    /// `new`, and where it is now
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let i = *self.order.get(self.visited)?;
+        self.visited += 1;
+
+        Some((i, self.positions[i]))
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::fmt::{ Debug, Display };
+use std::path::{ Path, PathBuf };
+
+use crossterm::style::Color;
+
+use crate::events::Waker;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl Severity {
+    /// What diagnostics of this severity are drawn in.
+    pub fn color(self) -> Color {
+        match self {
+            Severity::Error => Color::Red,
+            Severity::Warning => Color::Yellow,
+            Severity::Info => Color::Blue,
+            Severity::Hint => Color::Grey,
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Info => write!(f, "info"),
+            Severity::Hint => write!(f, "hint"),
+        }
+    }
+}
+
+/// A problem with a file, from a language server, compiler or linter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    // (column, line) in chars, `end` exclusive
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub severity: Severity,
+    pub message: String,
+    /// What reported it, e.g. `rustc`
+    pub source: Option<String>,
+}
+
+impl Diagnostic {
+    /// Whether the diagnostic covers any of `line`.
+    pub fn on_line(&self, line: usize) -> bool {
+        (self.start.1..=self.end.1).contains(&line)
+    }
+}
+
+/// Everything one provider has to say about a file, replacing whatever it
+/// said before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// Which provider it's from, e.g. `lsp`
+    pub source: String,
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Something that finds problems with files: a language server, a compiler,
+/// a linter. Polled on the main thread, so it should do its work elsewhere.
+pub trait DiagnosticProvider: Debug {
+    /// Every report since the last call, without blocking.
+    fn poll_diagnostics(&mut self) -> Vec<Report>;
+
+    /// Lets the provider wake the main loop when it has something new.
+    fn set_waker(&mut self, _waker: Waker) { }
+}
+
+/// The diagnostics for every file, from every provider.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiagnosticSet {
+    // By path, then by source
+    files: BTreeMap<PathBuf, BTreeMap<String, Vec<Diagnostic>>>,
+}
+
+impl DiagnosticSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, report: &Report) {
+        let sources = self.files.entry(report.path.clone()).or_default();
+
+        if report.diagnostics.is_empty() {
+            sources.remove(&report.source);
+        } else {
+            sources.insert(report.source.clone(), report.diagnostics.clone());
+        }
+
+        if sources.is_empty() {
+            self.files.remove(&report.path);
+        }
+    }
+
+    /// Forgets everything `source` said, about every file.
+    pub fn clear_source(&mut self, source: &str) {
+        for sources in self.files.values_mut() {
+            sources.remove(source);
+        }
+
+        self.files.retain(|_, sources| !sources.is_empty());
+    }
+
+    /// Every diagnostic for `path`, in the order they appear in it.
+    pub fn for_path(&self, path: &Path) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self.files.get(path)
+            .into_iter()
+            .flat_map(|sources| sources.values().flatten().cloned())
+            .collect();
+
+        diagnostics.sort_by_key(|d| (d.start.1, d.start.0, d.severity));
+        diagnostics
+    }
+
+    /// Every diagnostic, by path and then in the order they appear.
+    pub fn all(&self) -> Vec<(&Path, &Diagnostic)> {
+        let mut all: Vec<(&Path, &Diagnostic)> = self.files.iter()
+            .flat_map(|(path, sources)| sources.values().flatten().map(move |d| (path.as_path(), d)))
+            .collect();
+
+        all.sort_by_key(|(path, d)| (*path, d.start.1, d.start.0, d.severity));
+        all
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.files.values()
+            .flat_map(|sources| sources.values().flatten())
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
@@ -0,0 +1,110 @@
+/// Edit distances past this are given up on, and the lines in between
+/// reported as all deleted, then all inserted.
+const MAX_EDITS: usize = 1000;
+
+/// One line of the difference between two texts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// The shortest list of line deletions and insertions turning `old` into
+/// `new`, interleaved with the lines they share, in order.
+pub fn diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Change<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut changes: Vec<Change> = old[..prefix].iter().map(|l| Change::Equal(l)).collect();
+
+    match myers(a, b) {
+        Some(middle) => changes.extend(middle),
+        None => {
+            changes.extend(a.iter().map(|l| Change::Delete(l)));
+            changes.extend(b.iter().map(|l| Change::Insert(l)));
+        }
+    }
+
+    changes.extend(old[old.len() - suffix..].iter().map(|l| Change::Equal(l)));
+    changes
+}
+
+/// Myers' O(ND) diff. Returns `None` if it would take more than `MAX_EDITS`.
+fn myers<'a>(a: &[&'a str], b: &[&'a str]) -> Option<Vec<Change<'a>>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_EDITS) as isize;
+
+    // `v[offset + k]` is the furthest `x` reached on diagonal `k = x - y`
+    let offset = max + 1;
+    let mut v = vec![ 0isize; 2 * offset as usize + 1 ];
+    // The part of `v` each round started from, diagonals `-d - 1..=d + 1`
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut end = None;
+
+    'rounds: for d in 0..=max {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            v[i] = x;
+
+            if x >= n && y >= m {
+                end = Some(d);
+                break 'rounds;
+            }
+        }
+    }
+
+    let end = end?;
+    let mut changes = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for d in (0..=end).rev() {
+        let v = &trace[d as usize];
+        let at = |k: isize| v[(k + d + 1) as usize];
+
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            changes.push(Change::Equal(a[x as usize - 1]));
+            x -= 1;
+            y -= 1;
+        }
+
+        if d > 0 {
+            if x == prev_x {
+                changes.push(Change::Insert(b[y as usize - 1]));
+            } else {
+                changes.push(Change::Delete(a[x as usize - 1]));
+            }
+        }
+
+        (x, y) = (prev_x, prev_y);
+    }
+
+    changes.reverse();
+    Some(changes)
+}
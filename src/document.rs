@@ -0,0 +1,289 @@
+use std::{path::{Path, PathBuf}, error::Error, fs, time::SystemTime};
+
+use ropey::Rope;
+
+use crate::diagnostics::Diagnostic;
+use crate::events::Waker;
+use crate::history::{ History, Transaction };
+use crate::loader::{ FileLoader, LoadEvent };
+use crate::text::{ self, Encoding, LineEnding };
+use crate::FileStatus;
+
+/// Files this big are loaded in the background and shown without the
+/// extras, like search highlighting, that would make drawing them slow.
+pub const LARGE_FILE_SIZE: u64 = 16 << 20;
+/// Files bigger than this aren't opened at all.
+pub const MAX_FILE_SIZE: u64 = 1 << 30;
+
+#[derive(Debug, Default)]
+enum Loading {
+    #[default]
+    Done,
+    /// Waiting for `start_loading`
+    Pending,
+    Running(FileLoader),
+}
+
+/// A file open in a `Buffer`, along with everything that should survive
+/// switching away from it and back.
+#[derive(Debug, Default)]
+pub struct Document {
+    pub path: PathBuf,
+    pub content: Rope,
+    pub history: History,
+    pub line_ending: LineEnding,
+    pub encoding: Encoding,
+    // (column, line) within `content`
+    pub cursor: (usize, usize),
+    pub scroll: usize,
+    /// The file has changed on disk since it was loaded, and the document
+    /// had changes of its own to lose.
+    pub changed_on_disk: bool,
+    /// Loaded in the background, because the file is at least
+    /// `LARGE_FILE_SIZE`.
+    pub large: bool,
+    loading: Loading,
+    // The file's modification time and size when it was last read or saved
+    disk_stamp: Option<(SystemTime, u64)>,
+    /// Kept up to date from `AppState::diagnostics` while it's visible.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The revision of `history` language servers last heard about.
+    pub synced_revision: Option<u64>,
+    /// The revision of `history` last compared with `HEAD`.
+    pub diffed_revision: Option<u64>,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        debug!("Loading contents of file {}...", path.display());
+
+        match read(path) {
+            Ok((content, encoding)) => {
+                debug!("...Success! ({})", encoding);
+
+                Ok(Self {
+                    path: path.to_path_buf(),
+                    disk_stamp: disk_stamp(path),
+                    line_ending: LineEnding::detect(&content),
+                    encoding,
+                    content,
+                    ..Self::default()
+                })
+            },
+            Err(e) => {
+                error!("Error loading file: {:?}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Opens `path`, or, for large files, an empty document that fills in
+    /// once `start_loading` is called and `poll_loader` keeps being.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            // Let `open` say what's wrong
+            Err(_) => return Self::open(path),
+        };
+
+        if size > MAX_FILE_SIZE {
+            return Err(format!("{} is too large ({} MB)", path.display(), size >> 20).into());
+        }
+
+        if size < LARGE_FILE_SIZE {
+            return Self::open(path);
+        }
+
+        debug!("Loading {} ({} bytes) in the background", path.display(), size);
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            large: true,
+            loading: Loading::Pending,
+            ..Self::default()
+        })
+    }
+
+    /// Starts reading the file of a document made by `load`, if it hasn't
+    /// been started yet. `waker` is woken as the file comes in.
+    pub fn start_loading(&mut self, waker: Option<Waker>) {
+        if !matches!(self.loading, Loading::Pending) {
+            return;
+        }
+
+        self.disk_stamp = disk_stamp(&self.path);
+
+        let size = self.disk_stamp.map_or(0, |(_, size)| size);
+        self.loading = Loading::Running(FileLoader::spawn(self.path.clone(), size, waker));
+    }
+
+    /// Reads the file again in the background, the way `load` does large
+    /// files, once `start_loading` is called. Whatever is being loaded now is
+    /// dropped. The old content isn't kept around, so its history goes too.
+    pub fn reload_in_background(&mut self) {
+        self.loading = Loading::Pending;
+        self.history = History::new();
+        self.changed_on_disk = false;
+    }
+
+    /// Whether the file's modification time or size is different from when
+    /// it was last read or saved, which is much cheaper to find out than
+    /// whether its content is.
+    pub fn changed_since_read(&self) -> bool {
+        disk_stamp(&self.path) != self.disk_stamp
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !matches!(self.loading, Loading::Done)
+    }
+
+    /// How many bytes of how many have been loaded, while loading.
+    pub fn load_progress(&self) -> Option<(u64, u64)> {
+        match &self.loading {
+            Loading::Running(loader) => Some(loader.progress()),
+            _ => None,
+        }
+    }
+
+    /// Adds whatever has been loaded since the last call. Returns whether
+    /// anything changed.
+    pub fn poll_loader(&mut self) -> Result<bool, String> {
+        let loader = match &mut self.loading {
+            Loading::Running(loader) => loader,
+            _ => return Ok(false),
+        };
+
+        let events = loader.poll();
+        let done = loader.is_done();
+        let changed = !events.is_empty() || done;
+
+        if done {
+            self.loading = Loading::Done;
+        }
+
+        for event in events {
+            match event {
+                LoadEvent::Start(encoding) => {
+                    self.content = Rope::new();
+                    self.encoding = encoding;
+                },
+                LoadEvent::Text { text, .. } => {
+                    // Going by the first piece, rather than all of it
+                    if self.content.len_chars() == 0 {
+                        self.line_ending = LineEnding::detect(&Rope::from_str(&text));
+                    }
+
+                    let end = self.content.len_chars();
+                    self.content.insert(end, &text);
+                },
+                LoadEvent::Failed(e) => {
+                    self.loading = Loading::Done;
+                    return Err(e);
+                },
+            }
+        }
+
+        // The loader read up to whatever the end was by now. A reload may
+        // have come back shorter.
+        if done {
+            self.disk_stamp = disk_stamp(&self.path);
+            self.cursor.1 = self.cursor.1.min(self.last_line());
+        }
+
+        Ok(changed)
+    }
+
+    /// What the file holds now, and how it's encoded, leaving the document
+    /// as it is.
+    pub fn read_from_disk(&self) -> Result<(Rope, Encoding), Box<dyn Error>> {
+        read(&self.path)
+    }
+
+    /// Replaces the content with `content`, freshly read from disk. The
+    /// cursor stays where it was, as far as the new content allows, and the
+    /// reload can be undone like any other change.
+    pub fn reload(&mut self, content: Rope, encoding: Encoding) {
+        let mut transaction = Transaction::new(self.cursor);
+        let len = self.content.len_chars();
+
+        transaction.replace(&mut self.content, 0..len, &content.to_string());
+
+        self.cursor.1 = self.cursor.1.min(self.last_line());
+        transaction.set_cursor_after(self.cursor);
+
+        self.history.commit(transaction);
+        self.history.mark_saved();
+        self.line_ending = LineEnding::detect(&self.content);
+        self.encoding = encoding;
+        self.changed_on_disk = false;
+        self.disk_stamp = disk_stamp(&self.path);
+    }
+
+    /// Writes the content back to the file, in the encoding and with the
+    /// line endings it was loaded with. Returns how many bytes were written.
+    pub fn save(&mut self) -> Result<usize, Box<dyn Error>> {
+        if self.path.as_os_str().is_empty() {
+            return Err("No file name".into());
+        }
+
+        if self.is_loading() {
+            return Err("Still loading".into());
+        }
+
+        // All of it is encoded before the file is touched, so a char the
+        // encoding can't hold fails the save instead of cutting the file short
+        let mut bytes = self.encoding.bom().to_vec();
+
+        // Chunks never split a CRLF, so each can be normalized on its own
+        for chunk in self.content.chunks() {
+            bytes.extend(self.encoding.encode(&self.line_ending.normalize(chunk))?);
+        }
+
+        fs::write(&self.path, &bytes)?;
+        let written = bytes.len();
+
+        debug!("Wrote {} bytes to {}.", written, self.path.display());
+
+        self.history.mark_saved();
+        self.changed_on_disk = false;
+        self.disk_stamp = disk_stamp(&self.path);
+
+        Ok(written)
+    }
+
+    pub fn line_count(&self) -> usize {
+        text::line_count(&self.content)
+    }
+
+    pub fn last_line(&self) -> usize {
+        self.line_count().saturating_sub(1)
+    }
+
+    pub fn status(&self) -> FileStatus {
+        FileStatus {
+            modified: self.history.is_modified(),
+            line_count: self.line_count(),
+            line_ending: self.line_ending,
+            encoding: self.encoding,
+        }
+    }
+}
+
+fn disk_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn read(path: &Path) -> Result<(Rope, Encoding), Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    let encoding = Encoding::detect(&bytes);
+    let text = encoding.decode(&bytes)?;
+
+    Ok((Rope::from_str(&text), encoding))
+}
@@ -0,0 +1,122 @@
+use std::sync::mpsc::Sender;
+use std::time::{ Duration, Instant };
+
+use crossterm::event::Event as InputEvent;
+
+pub type TimerId = u64;
+
+/// Everything that can wake `Application::run` up.
+#[derive(Debug, Clone)]
+pub enum AppEvent {
+    Input(InputEvent),
+    Timer(TimerId),
+    /// Something in the background, like a job or a search, has news
+    Wake,
+}
+
+/// Wakes the main loop from another thread.
+#[derive(Debug, Clone)]
+pub struct Waker {
+    sender: Sender<AppEvent>,
+}
+
+impl Waker {
+    pub fn new(sender: Sender<AppEvent>) -> Self {
+        Self { sender }
+    }
+
+    pub fn wake(&self) {
+        let _ = self.sender.send(AppEvent::Wake);
+    }
+
+    pub fn send(&self, event: AppEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+#[derive(Debug)]
+struct Timer {
+    id: TimerId,
+    deadline: Instant,
+    // Set for timers that repeat
+    period: Option<Duration>,
+}
+
+/// Timers the main loop sleeps until, each firing an `AppEvent::Timer`.
+#[derive(Debug, Default)]
+pub struct Timers {
+    timers: Vec<Timer>,
+    next_id: TimerId,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&mut self, deadline: Instant, period: Option<Duration>) -> TimerId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.timers.push(Timer { id, deadline, period });
+
+        id
+    }
+
+    /// Fires once, after `delay`.
+    pub fn set_timeout(&mut self, delay: Duration) -> TimerId {
+        self.set_deadline(Instant::now() + delay)
+    }
+
+    /// Fires once, at `deadline`.
+    pub fn set_deadline(&mut self, deadline: Instant) -> TimerId {
+        self.add(deadline, None)
+    }
+
+    /// Fires every `period` until cancelled.
+    pub fn set_interval(&mut self, period: Duration) -> TimerId {
+        self.set_interval_from(Instant::now(), period)
+    }
+
+    /// Fires every `period` after `start` until cancelled.
+    pub fn set_interval_from(&mut self, start: Instant, period: Duration) -> TimerId {
+        let period = period.max(Duration::from_millis(1));
+        self.add(start + period, Some(period))
+    }
+
+    pub fn cancel(&mut self, id: TimerId) {
+        self.timers.retain(|t| t.id != id);
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers.iter().map(|t| t.deadline).min()
+    }
+
+    /// Every timer due by `now`. One-shot timers are removed, and repeating
+    /// ones rescheduled.
+    pub fn expired(&mut self, now: Instant) -> Vec<TimerId> {
+        let mut expired = Vec::new();
+
+        self.timers.retain_mut(|timer| {
+            if timer.deadline > now {
+                return true;
+            }
+
+            expired.push(timer.id);
+
+            match timer.period {
+                Some(period) => {
+                    // Skip ticks that were missed rather than firing them all at once
+                    while timer.deadline <= now {
+                        timer.deadline += period;
+                    }
+
+                    true
+                },
+                None => false,
+            }
+        });
+
+        expired
+    }
+}
@@ -0,0 +1,239 @@
+use std::path::PathBuf;
+use std::sync::{
+    Arc,
+    atomic::{ AtomicBool, Ordering },
+    mpsc::{ self, Receiver },
+};
+use std::thread;
+
+use ignore::WalkBuilder;
+
+use crate::events::Waker;
+
+// Scores for the fuzzy matcher. A matched char is worth `MATCH`, plus
+// bonuses for where it falls; every skipped char between two matches costs
+// `GAP`.
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 24;
+const WORD_START: i64 = 20;
+const SEGMENT_START: i64 = 28;
+const FILE_NAME: i64 = 8;
+const GAP: i64 = 1;
+
+const NONE: i64 = i64::MIN / 2;
+
+/// Paths are sent back in batches of this many.
+const BATCH_SIZE: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Byte offsets of the matched chars in the candidate
+    pub indices: Vec<usize>,
+}
+
+/// Matches the chars of `pattern` in order against `candidate`, picking the
+/// placement with the best score. Matches at the start of a path segment or
+/// word, in the file name, or right after the previous match score higher.
+///
+/// Whitespace in `pattern` is ignored, and the match is case-insensitive
+/// unless `pattern` has an uppercase char.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if pattern.is_empty() {
+        return Some(FuzzyMatch { score: 0, indices: Vec::new() });
+    }
+
+    let case_sensitive = pattern.iter().any(|c| c.is_uppercase());
+    let eq = |p: char, c: char| {
+        if case_sensitive {
+            p == c
+        } else {
+            c.to_lowercase().eq(p.to_lowercase())
+        }
+    };
+
+    let chars: Vec<(usize, char)> = candidate.char_indices().collect();
+
+    // Cheap rejection before filling the table
+    let mut rest = chars.iter();
+    if !pattern.iter().all(|&p| rest.any(|&(_, c)| eq(p, c))) {
+        return None;
+    }
+
+    let (m, n) = (pattern.len(), chars.len());
+    let file_start = candidate.rfind(['/', '\\']).map_or(0, |i| i + 1);
+    let bonuses: Vec<i64> = (0..n).map(|j| bonus(&chars, j, file_start)).collect();
+
+    // `scores[i * n + j]` is the best score for `pattern[..=i]` with
+    // `pattern[i]` matched at `chars[j]`; `from` is where `pattern[i - 1]`
+    // was matched to get it.
+    let mut scores = vec![NONE; m * n];
+    let mut from = vec![0; m * n];
+
+    for (i, &p) in pattern.iter().enumerate() {
+        // Best score of a non-adjacent previous match, already charged for
+        // the gap up to `j - 1`
+        let mut best = NONE;
+        let mut best_from = 0;
+
+        for j in 0..n {
+            if i > 0 && j >= 2 {
+                best -= GAP;
+
+                let candidate = scores[(i - 1) * n + j - 2] - GAP;
+                if candidate > best {
+                    best = candidate;
+                    best_from = j - 2;
+                }
+            }
+
+            if !eq(p, chars[j].1) {
+                continue;
+            }
+
+            let score = MATCH + bonuses[j];
+
+            if i == 0 {
+                scores[j] = score;
+                continue;
+            }
+
+            if j == 0 {
+                continue;
+            }
+
+            let adjacent = scores[(i - 1) * n + j - 1];
+
+            if adjacent > NONE && adjacent + CONSECUTIVE >= best {
+                scores[i * n + j] = adjacent + CONSECUTIVE + score;
+                from[i * n + j] = j - 1;
+            } else if best > NONE {
+                scores[i * n + j] = best + score;
+                from[i * n + j] = best_from;
+            }
+        }
+    }
+
+    let last = (m - 1) * n;
+    let (mut j, score) = (0..n)
+        .map(|j| (j, scores[last + j]))
+        .max_by_key(|&(_, s)| s)?;
+
+    if score <= NONE {
+        return None;
+    }
+
+    let mut indices = vec![0; m];
+    for i in (0..m).rev() {
+        indices[i] = chars[j].0;
+        j = from[i * n + j];
+    }
+
+    Some(FuzzyMatch { score, indices })
+}
+
+fn bonus(chars: &[(usize, char)], j: usize, file_start: usize) -> i64 {
+    let (index, c) = chars[j];
+    let mut bonus = if index >= file_start { FILE_NAME } else { 0 };
+
+    bonus += match j.checked_sub(1).map(|k| chars[k].1) {
+        None | Some('/') | Some('\\') => SEGMENT_START,
+        Some('_') | Some('-') | Some('.') | Some(' ') => WORD_START,
+        Some(prev) if prev.is_lowercase() && c.is_uppercase() => WORD_START,
+        _ => 0,
+    };
+
+    bonus
+}
+
+/// Lists every file under a directory on a background thread, skipping
+/// anything ignored by `.gitignore` and friends. Dropping it stops the walk.
+#[derive(Debug)]
+pub struct FileList {
+    receiver: Receiver<Vec<PathBuf>>,
+    cancelled: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl FileList {
+    /// `waker` is woken after each batch of paths.
+    pub fn spawn(root: PathBuf, waker: Option<Waker>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancelled);
+
+        thread::spawn(move || {
+            let send = |batch: Vec<PathBuf>| {
+                let sent = sender.send(batch).is_ok();
+
+                if let Some(waker) = &waker {
+                    waker.wake();
+                }
+
+                sent
+            };
+
+            let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+            for entry in WalkBuilder::new(&root).build() {
+                if flag.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                match entry {
+                    Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) => {
+                        batch.push(entry.into_path());
+                    },
+                    Ok(_) => { },
+                    Err(e) => warn!("finder: {}", e),
+                }
+
+                if batch.len() == BATCH_SIZE && !send(std::mem::take(&mut batch)) {
+                    return;
+                }
+            }
+
+            let _ = sender.send(batch);
+
+            // The list is done once the sender is gone, so drop it before waking
+            drop(sender);
+
+            if let Some(waker) = &waker {
+                waker.wake();
+            }
+        });
+
+        Self { receiver, cancelled, done: false }
+    }
+
+    /// Every path found since the last call, without blocking.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(batch) => paths.extend(batch),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+
+        paths
+    }
+
+    /// Whether the walk has finished and everything has been polled.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+impl Drop for FileList {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,258 @@
+//! What git knows about files, asked of the `git` command in their
+//! directory. Nothing here touches a remote.
+
+use std::collections::BTreeMap;
+use std::path::{ Path, PathBuf };
+use std::process::{ Command, Stdio };
+
+use crossterm::style::Color;
+
+use crate::diff::{ self, Change };
+use crate::text::Encoding;
+
+/// The bytes `git` printed, or `None` if it failed, like it does outside a
+/// repository.
+fn git(dir: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    output.status.success().then_some(output.stdout)
+}
+
+/// The file at `path` as it is in `HEAD`, decoded like the working copy.
+/// `None` if it isn't in a repository or isn't committed.
+pub fn head_text(path: &Path, encoding: Encoding) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let bytes = git(path.parent()?, &[ "show", &format!("HEAD:./{}", name) ])?;
+
+    encoding.decode(&bytes).ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// Lines that differ between `HEAD` and the working copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub kind: HunkKind,
+    /// The first line of the working copy it covers, or for deletions the
+    /// line after what was deleted
+    pub start: usize,
+    /// How many lines of the working copy it covers, none for deletions
+    pub len: usize,
+    /// What was there in `HEAD`
+    pub old: Vec<String>,
+}
+
+impl Hunk {
+    /// Whether the hunk is on `line`. Deletions are on the lines either side.
+    pub fn on_line(&self, line: usize) -> bool {
+        match self.kind {
+            HunkKind::Deleted => line + 1 == self.start || line == self.start,
+            _ => (self.start..self.start + self.len).contains(&line),
+        }
+    }
+}
+
+/// The hunks turning `old` into `new`, both split into lines.
+pub fn hunks(old: &[&str], new: &[&str]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut line = 0;
+    // Whether the last change extended the last hunk
+    let mut in_hunk = false;
+
+    for change in diff::diff(old, new) {
+        if let Change::Equal(_) = change {
+            line += 1;
+            in_hunk = false;
+            continue;
+        }
+
+        if !in_hunk {
+            hunks.push(Hunk { kind: HunkKind::Deleted, start: line, len: 0, old: Vec::new() });
+            in_hunk = true;
+        }
+
+        let hunk = hunks.last_mut().unwrap();
+
+        match change {
+            Change::Delete(text) => hunk.old.push(text.to_string()),
+            Change::Insert(_) => {
+                hunk.len += 1;
+                line += 1;
+            },
+            Change::Equal(_) => { },
+        }
+
+        hunk.kind = match (hunk.old.is_empty(), hunk.len) {
+            (true, _) => HunkKind::Added,
+            (false, 0) => HunkKind::Deleted,
+            (false, _) => HunkKind::Modified,
+        };
+    }
+
+    hunks
+}
+
+/// What git thinks of a file in the working copy, least notable first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ignored,
+    Untracked,
+    /// Changed, with every change staged
+    Staged,
+    /// Changed since it was last staged
+    Modified,
+    /// Left with conflicts by a merge
+    Conflicted,
+}
+
+impl Status {
+    /// The letter shown beside files with this status.
+    pub fn marker(&self) -> char {
+        match self {
+            Status::Ignored => '!',
+            Status::Untracked => '?',
+            Status::Staged => 'S',
+            Status::Modified => 'M',
+            Status::Conflicted => 'C',
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Status::Ignored => Color::DarkGrey,
+            Status::Untracked => Color::Cyan,
+            Status::Staged => Color::Green,
+            Status::Modified => Color::Yellow,
+            Status::Conflicted => Color::Red,
+        }
+    }
+
+    /// The status of a `git status --porcelain` entry, from its two letters.
+    fn parse(index: u8, worktree: u8) -> Option<Self> {
+        Some(match (index, worktree) {
+            (b'!', b'!') => Status::Ignored,
+            (b'?', b'?') => Status::Untracked,
+            (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => Status::Conflicted,
+            (_, b' ') => Status::Staged,
+            (_, b'M' | b'D' | b'T') => Status::Modified,
+            _ => return None,
+        })
+    }
+}
+
+/// The status of every file in a repository that isn't simply committed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoStatus {
+    /// The top of the working copy, as an absolute path
+    pub root: PathBuf,
+    /// `None` outside a repository, or with a detached `HEAD`
+    pub branch: Option<String>,
+    /// Keyed by paths relative to `root`. Ignored and untracked directories
+    /// are listed once, rather than every file in them.
+    pub files: BTreeMap<PathBuf, Status>,
+}
+
+impl RepoStatus {
+    /// The status of the file or directory at `path`. Directories take the
+    /// most notable status of the files in them, not counting ignored ones.
+    pub fn get(&self, path: &Path) -> Option<Status> {
+        let path = absolute(path)?;
+        let relative = path.strip_prefix(&self.root).ok()?;
+
+        // Files in ignored or untracked directories
+        for ancestor in relative.ancestors() {
+            if let Some(status) = self.files.get(ancestor) {
+                return Some(*status);
+            }
+        }
+
+        self.files.range(relative.to_path_buf()..)
+            .take_while(|(p, _)| p.starts_with(relative))
+            .map(|(_, status)| *status)
+            .filter(|status| *status != Status::Ignored)
+            .max()
+    }
+}
+
+/// `path` made absolute with symlinks resolved. The file itself doesn't
+/// have to exist, so deleted files can still be looked up.
+fn absolute(path: &Path) -> Option<PathBuf> {
+    if let Ok(path) = path.canonicalize() {
+        return Some(path);
+    }
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    Some(dir.canonicalize().ok()?.join(path.file_name()?))
+}
+
+/// The status of the repository `dir` is in, or `None` if it isn't in one.
+pub fn status(dir: &Path) -> Option<RepoStatus> {
+    let root = git(dir, &[ "rev-parse", "--show-toplevel" ])?;
+    let root = PathBuf::from(String::from_utf8(root).ok()?.trim_end());
+    // Without optional locks, so watching the index for changes doesn't
+    // notice `git status` itself refreshing it
+    let output = git(dir, &[ "--no-optional-locks", "status", "--porcelain=v1", "-z", "--branch", "--ignored", "--untracked-files=normal" ])?;
+
+    Some(parse_status(root.canonicalize().unwrap_or(root), &output))
+}
+
+/// The output of `git status --porcelain=v1 -z --branch` for the repository
+/// at `root`.
+pub fn parse_status(root: PathBuf, output: &[u8]) -> RepoStatus {
+    let mut status = RepoStatus { root, ..RepoStatus::default() };
+    let mut entries = output.split(|b| *b == 0).filter(|e| !e.is_empty());
+
+    while let Some(entry) = entries.next() {
+        let entry = String::from_utf8_lossy(entry);
+
+        if let Some(branch) = entry.strip_prefix("## ") {
+            status.branch = parse_branch(branch);
+            continue;
+        }
+
+        let bytes = entry.as_bytes();
+        if bytes.len() < 4 {
+            continue;
+        }
+
+        // Renames and copies are followed by the path they came from
+        if matches!(bytes[0], b'R' | b'C') {
+            entries.next();
+        }
+
+        if let Some(file_state) = Status::parse(bytes[0], bytes[1]) {
+            let path = entry[3..].trim_end_matches('/');
+            status.files.insert(PathBuf::from(path), file_state);
+        }
+    }
+
+    status
+}
+
+/// The branch in the header `git status --branch` prints, which looks like
+/// `main...origin/main [ahead 1]`, `No commits yet on main` or
+/// `HEAD (no branch)`.
+fn parse_branch(header: &str) -> Option<String> {
+    let header = header.strip_prefix("No commits yet on ").unwrap_or(header);
+    let branch = header.split("...").next()?.split(' ').next()?;
+
+    (branch != "HEAD").then(|| branch.to_string())
+}
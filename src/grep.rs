@@ -0,0 +1,156 @@
+use std::fs::File;
+use std::io::{ BufRead, BufReader };
+use std::path::{ Path, PathBuf };
+use std::sync::{
+    Arc,
+    atomic::{ AtomicBool, Ordering },
+    mpsc::{ self, Receiver, Sender },
+};
+use std::thread;
+
+use ignore::WalkBuilder;
+use regex::Regex;
+
+use crate::events::Waker;
+
+/// Files with a NUL byte in this many leading bytes are treated as binary.
+const BINARY_CHECK_LEN: usize = 8192;
+
+#[derive(Debug, Clone)]
+pub struct GrepMatch {
+    pub path: PathBuf,
+    /// 0-based
+    pub line: usize,
+    /// 0-based, in chars
+    pub column: usize,
+    pub text: String,
+}
+
+#[derive(Debug)]
+pub enum GrepEvent {
+    Match(GrepMatch),
+    Done { files: usize },
+}
+
+/// A search running on a background thread. Dropping it stops the search.
+#[derive(Debug)]
+pub struct GrepSearch {
+    receiver: Receiver<GrepEvent>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl GrepSearch {
+    /// Starts searching every file under `root` for `regex`, skipping
+    /// anything ignored by `.gitignore` and friends. `waker` is woken after
+    /// each file with matches, and when the search is done.
+    pub fn spawn(root: PathBuf, regex: Regex, waker: Option<Waker>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancelled);
+
+        thread::spawn(move || {
+            let files = search_tree(&root, &regex, &sender, &flag, waker.as_ref());
+            let _ = sender.send(GrepEvent::Done { files });
+
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        });
+
+        Self { receiver, cancelled }
+    }
+
+    /// Everything found since the last call, without blocking.
+    pub fn poll(&self) -> Vec<GrepEvent> {
+        self.receiver.try_iter().collect()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for GrepSearch {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+fn search_tree(root: &Path, regex: &Regex, sender: &Sender<GrepEvent>, cancelled: &AtomicBool, waker: Option<&Waker>) -> usize {
+    let mut files = 0;
+
+    for entry in WalkBuilder::new(root).build() {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("grep: {}", e);
+                continue;
+            }
+        };
+
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+
+        files += 1;
+
+        match search_file(entry.path(), regex, sender, cancelled) {
+            Ok(true) => {
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            },
+            Ok(false) => { },
+            Err(e) => warn!("grep: {}: {}", entry.path().display(), e),
+        }
+    }
+
+    files
+}
+
+/// Returns whether anything in the file matched.
+fn search_file(path: &Path, regex: &Regex, sender: &Sender<GrepEvent>, cancelled: &AtomicBool) -> std::io::Result<bool> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    if reader.fill_buf()?.iter().take(BINARY_CHECK_LEN).any(|b| *b == 0) {
+        return Ok(false);
+    }
+
+    let mut bytes = Vec::new();
+    let mut line = 0;
+    let mut found_any = false;
+
+    while reader.read_until(b'\n', &mut bytes)? > 0 {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let text = String::from_utf8_lossy(&bytes);
+        let text = text.trim_end_matches(['\n', '\r']);
+
+        if let Some(m) = regex.find(text) {
+            let found = GrepMatch {
+                path: path.to_path_buf(),
+                line,
+                column: text[..m.start()].chars().count(),
+                text: text.to_string(),
+            };
+
+            // The search was dropped
+            if sender.send(GrepEvent::Match(found)).is_err() {
+                break;
+            }
+
+            found_any = true;
+        }
+
+        bytes.clear();
+        line += 1;
+    }
+
+    Ok(found_any)
+}
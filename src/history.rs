@@ -0,0 +1,196 @@
+use std::ops::Range;
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+use ropey::Rope;
+
+/// A single change to a rope: `removed` was replaced by `inserted`,
+/// starting at `char_idx`.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub char_idx: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+impl Edit {
+    pub fn apply(&self, text: &mut Rope) {
+        let end = self.char_idx + self.removed.chars().count();
+
+        text.remove(self.char_idx..end);
+        text.insert(self.char_idx, &self.inserted);
+    }
+
+    pub fn inverse(&self) -> Edit {
+        Edit {
+            char_idx: self.char_idx,
+            removed: self.inserted.clone(),
+            inserted: self.removed.clone(),
+        }
+    }
+}
+
+/// A group of edits that is undone and redone as one step.
+#[derive(Debug, Clone, Default)]
+pub struct Transaction {
+    id: u64,
+    edits: Vec<Edit>,
+    cursor_before: (usize, usize),
+    cursor_after: (usize, usize),
+}
+
+impl Transaction {
+    /// Starts a transaction; `cursor` is restored when it is undone.
+    pub fn new(cursor: (usize, usize)) -> Self {
+        Self {
+            cursor_before: cursor,
+            cursor_after: cursor,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// The edits made so far, in the order they were made.
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// Replaces the chars in `range` of `text` with `with`, recording the edit.
+    pub fn replace(&mut self, text: &mut Rope, range: Range<usize>, with: &str) {
+        let edit = Edit {
+            char_idx: range.start,
+            removed: text.slice(range).to_string(),
+            inserted: with.to_string(),
+        };
+
+        edit.apply(text);
+        self.edits.push(edit);
+    }
+
+    pub fn insert(&mut self, text: &mut Rope, char_idx: usize, inserted: &str) {
+        self.replace(text, char_idx..char_idx, inserted);
+    }
+
+    pub fn remove(&mut self, text: &mut Rope, range: Range<usize>) {
+        self.replace(text, range, "");
+    }
+
+    /// Where the cursor is restored to when the transaction is redone.
+    pub fn set_cursor_after(&mut self, cursor: (usize, usize)) {
+        self.cursor_after = cursor;
+    }
+
+    pub fn cursor_after(&self) -> (usize, usize) {
+        self.cursor_after
+    }
+}
+
+// Shared by every history, so no two revisions are ever the same
+static REVISIONS: AtomicU64 = AtomicU64::new(0);
+
+fn next_revision() -> u64 {
+    REVISIONS.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+#[derive(Debug)]
+pub struct History {
+    undo: Vec<Transaction>,
+    redo: Vec<Transaction>,
+    next_id: u64,
+    saved: Option<u64>,
+    revision: u64,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self { undo: Vec::new(), redo: Vec::new(), next_id: 0, saved: None, revision: next_revision() }
+    }
+
+    /// Records an already-applied transaction. Empty transactions are dropped.
+    pub fn commit(&mut self, mut transaction: Transaction) {
+        if transaction.is_empty() {
+            return;
+        }
+
+        self.next_id += 1;
+        transaction.id = self.next_id;
+
+        self.undo.push(transaction);
+        self.redo.clear();
+        self.revision = next_revision();
+    }
+
+    /// Adds an already-applied transaction to the last one, so they're undone
+    /// as one. Empty transactions are dropped.
+    pub fn join(&mut self, transaction: Transaction) {
+        if transaction.is_empty() {
+            return;
+        }
+
+        let Some(last) = self.undo.last_mut() else { return self.commit(transaction) };
+
+        // A new id, as it's no longer what was saved
+        self.next_id += 1;
+        last.id = self.next_id;
+        last.edits.extend(transaction.edits);
+        last.cursor_after = transaction.cursor_after;
+
+        self.redo.clear();
+        self.revision = next_revision();
+    }
+
+    /// Reverts the last transaction, returning where the cursor was before it.
+    pub fn undo(&mut self, text: &mut Rope) -> Option<(usize, usize)> {
+        let transaction = self.undo.pop()?;
+
+        for edit in transaction.edits.iter().rev() {
+            edit.inverse().apply(text);
+        }
+
+        let cursor = transaction.cursor_before;
+        self.redo.push(transaction);
+        self.revision = next_revision();
+
+        Some(cursor)
+    }
+
+    /// Reapplies the last undone transaction, returning where the cursor was
+    /// after it.
+    pub fn redo(&mut self, text: &mut Rope) -> Option<(usize, usize)> {
+        let transaction = self.redo.pop()?;
+
+        for edit in &transaction.edits {
+            edit.apply(text);
+        }
+
+        let cursor = transaction.cursor_after;
+        self.undo.push(transaction);
+        self.revision = next_revision();
+
+        Some(cursor)
+    }
+
+    /// Marks the current state as the one on disk.
+    pub fn mark_saved(&mut self) {
+        self.saved = self.undo.last().map(|t| t.id);
+    }
+
+    /// Goes up with every commit, undo and redo, so anything following the
+    /// text can tell when it has changed. Revisions are never shared between
+    /// histories, so a reopened file doesn't start over.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.undo.last().map(|t| t.id) != self.saved
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,321 @@
+use std::fmt::{ Debug, Display };
+use std::sync::{
+    Arc,
+    atomic::{ AtomicBool, Ordering },
+    mpsc::{ self, Receiver, Sender },
+};
+use std::thread;
+
+use crate::events::Waker;
+use crate::ui::bus::{ Bus, State };
+
+pub type JobId = u64;
+
+/// How far along a running job is, for showing in the status area.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobStatus {
+    pub id: JobId,
+    pub name: String,
+    pub done: usize,
+    /// `None` if the job can't tell how much work there is
+    pub total: Option<usize>,
+    pub message: String,
+}
+
+impl Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        match self.total {
+            Some(total) if total > 0 => write!(f, " {}%", self.done.min(total) * 100 / total)?,
+            _ if self.done > 0 => write!(f, " {}", self.done)?,
+            _ => write!(f, "...")?,
+        }
+
+        if !self.message.is_empty() {
+            write!(f, " ({})", self.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+enum JobMessage<M> {
+    Progress { id: JobId, done: usize, total: Option<usize> },
+    Message { id: JobId, message: String },
+    Send(M),
+    Redraw,
+    Finished(JobId),
+}
+
+/// Handed to a job's closure on its worker thread, to report back to the UI.
+pub struct JobContext<STATE: State> {
+    id: JobId,
+    sender: Sender<JobMessage<STATE::Message>>,
+    cancelled: Arc<AtomicBool>,
+    waker: Option<Waker>,
+}
+
+impl<STATE: State> JobContext<STATE> {
+    pub fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// Jobs should check this now and then, and return early once it's set.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn notify(&self, message: JobMessage<STATE::Message>) {
+        let _ = self.sender.send(message);
+
+        if let Some(waker) = &self.waker {
+            waker.wake();
+        }
+    }
+
+    pub fn progress(&self, done: usize, total: Option<usize>) {
+        self.notify(JobMessage::Progress { id: self.id, done, total });
+    }
+
+    /// Shown next to the job's progress.
+    pub fn message<S: Into<String>>(&self, message: S) {
+        self.notify(JobMessage::Message { id: self.id, message: message.into() });
+    }
+
+    /// Applies `message` to the application's state, on the main thread.
+    pub fn send(&self, message: STATE::Message) {
+        self.notify(JobMessage::Send(message));
+    }
+
+    pub fn redraw(&self) {
+        self.notify(JobMessage::Redraw);
+    }
+}
+
+impl<STATE: State> Drop for JobContext<STATE> {
+    // Sent even if the job panics, so it never looks like it's still running
+    fn drop(&mut self) {
+        self.notify(JobMessage::Finished(self.id));
+    }
+}
+
+type JobFn<STATE> = Box<dyn FnOnce(&JobContext<STATE>) + Send>;
+
+/// A job a window wants started. Windows can't reach `Jobs`, so `UI` spawns
+/// these for them.
+pub struct JobRequest<STATE: State> {
+    pub name: String,
+    job: JobFn<STATE>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<STATE: State> JobRequest<STATE> {
+    pub fn new<S, F>(name: S, job: F) -> Self
+    where S: Into<String>,
+          F: FnOnce(&JobContext<STATE>) + Send + 'static {
+        Self { name: name.into(), job: Box::new(job), cancelled: Arc::default() }
+    }
+
+    /// For cancelling the job from the window that asked for it, whether or
+    /// not it has started yet.
+    pub fn handle(&self) -> JobHandle {
+        JobHandle { cancelled: Arc::clone(&self.cancelled) }
+    }
+}
+
+/// Cancels a requested job, the way `Jobs::cancel` would.
+#[derive(Debug, Clone)]
+pub struct JobHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl<STATE: State> Debug for JobRequest<STATE> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobRequest").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+struct RunningJob {
+    status: JobStatus,
+    cancelled: Arc<AtomicBool>,
+}
+
+type StatusHook<M> = Box<dyn FnMut(&[JobStatus]) -> M>;
+
+/// Work running on background threads. Each job gets a `JobContext` to report
+/// progress, send messages, or ask for a redraw; none of it takes effect
+/// until `drain` is called on the main thread.
+pub struct Jobs<STATE: State> {
+    sender: Sender<JobMessage<STATE::Message>>,
+    receiver: Receiver<JobMessage<STATE::Message>>,
+    running: Vec<RunningJob>,
+    next_id: JobId,
+    waker: Option<Waker>,
+    // A job started since the last `drain`
+    statuses_changed: bool,
+    on_change: Option<StatusHook<STATE::Message>>,
+}
+
+impl<STATE: State> Jobs<STATE> {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            sender,
+            receiver,
+            running: Vec::new(),
+            next_id: 0,
+            waker: None,
+            statuses_changed: false,
+            on_change: None,
+        }
+    }
+
+    /// Runs `job` on a new worker thread.
+    pub fn spawn<S, F>(&mut self, name: S, job: F) -> JobId
+    where S: Into<String>,
+          F: FnOnce(&JobContext<STATE>) + Send + 'static {
+        self.spawn_with(name.into(), Box::new(job), Arc::default())
+    }
+
+    pub fn spawn_request(&mut self, request: JobRequest<STATE>) -> JobId {
+        self.spawn_with(request.name, request.job, request.cancelled)
+    }
+
+    fn spawn_with(&mut self, name: String, job: JobFn<STATE>, cancelled: Arc<AtomicBool>) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let context = JobContext {
+            id,
+            sender: self.sender.clone(),
+            cancelled: Arc::clone(&cancelled),
+            waker: self.waker.clone(),
+        };
+
+        debug!("Starting job {} ({}).", id, name);
+
+        thread::spawn(move || job(&context));
+
+        self.running.push(RunningJob {
+            status: JobStatus { id, name, done: 0, total: None, message: String::new() },
+            cancelled,
+        });
+        self.statuses_changed = true;
+
+        id
+    }
+
+    /// Jobs started after this wake the main loop whenever they send anything.
+    pub fn set_waker(&mut self, waker: Waker) {
+        self.waker = Some(waker);
+    }
+
+    /// Asks the job to stop. It is up to the job to notice.
+    pub fn cancel(&self, id: JobId) {
+        if let Some(job) = self.running.iter().find(|j| j.status.id == id) {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        for job in &self.running {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_running(&self, id: JobId) -> bool {
+        self.running.iter().any(|j| j.status.id == id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        self.running.iter().map(|j| j.status.clone()).collect()
+    }
+
+    /// Called from `drain` whenever a job starts, progresses or finishes, for
+    /// a message telling the windows about it.
+    pub fn on_change<F>(&mut self, hook: F)
+    where F: FnMut(&[JobStatus]) -> STATE::Message + 'static {
+        self.on_change = Some(Box::new(hook));
+    }
+
+    fn status_mut(&mut self, id: JobId) -> Option<&mut JobStatus> {
+        self.running.iter_mut()
+            .find(|j| j.status.id == id)
+            .map(|j| &mut j.status)
+    }
+
+    /// Collects everything the jobs have sent since the last call, without
+    /// blocking, passing their messages on to `bus`. Returns `true` if
+    /// anything needs to be redrawn.
+    pub fn drain(&mut self, bus: &mut Bus<STATE::Message>) -> bool {
+        let mut redraw = false;
+        let mut changed = std::mem::take(&mut self.statuses_changed);
+
+        for message in self.receiver.try_iter().collect::<Vec<_>>() {
+            match message {
+                JobMessage::Progress { id, done, total } => {
+                    if let Some(status) = self.status_mut(id) {
+                        status.done = done;
+                        status.total = total;
+                        changed = true;
+                    }
+                },
+                JobMessage::Message { id, message } => {
+                    if let Some(status) = self.status_mut(id) {
+                        status.message = message;
+                        changed = true;
+                    }
+                },
+                JobMessage::Send(message) => {
+                    bus.send(message);
+                    redraw = true;
+                },
+                JobMessage::Redraw => redraw = true,
+                JobMessage::Finished(id) => {
+                    debug!("Job {} finished.", id);
+
+                    self.running.retain(|j| j.status.id != id);
+                    changed = true;
+                },
+            }
+        }
+
+        if changed {
+            let statuses = self.statuses();
+
+            if let Some(hook) = &mut self.on_change {
+                bus.send(hook(&statuses));
+            }
+        }
+
+        redraw || changed
+    }
+}
+
+impl<STATE: State> Default for Jobs<STATE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<STATE: State> Drop for Jobs<STATE> {
+    fn drop(&mut self) {
+        self.cancel_all();
+    }
+}
@@ -0,0 +1,299 @@
+//! The keys of normal and visual mode, turned into commands. Counts,
+//! registers, operators and two-key prefixes are held here until the key
+//! that finishes a command comes, so the buffer only sees whole commands.
+
+use crossterm::event::{ KeyCode, KeyModifiers };
+
+use crate::motion::{ Motion, Operator, TextObject };
+use crate::registers::Registers;
+use crate::search::SearchDirection;
+use crate::selection::SelectionKind;
+
+/// What an operator acts on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Motion(Motion),
+    Object(TextObject),
+    /// Whole lines from the cursor's, for an operator typed twice like `dd`
+    Lines,
+}
+
+/// Something done to the selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisualAction {
+    /// Esc
+    Exit,
+    /// `o`, moving the cursor to the other end
+    SwapEnds,
+    /// `I`, a cursor on every line
+    AddCursors,
+    Yank,
+    Delete,
+    Change,
+    Indent,
+    Unindent,
+    ToggleCase,
+    Lowercase,
+    Uppercase,
+}
+
+/// Where typing starts, for the keys that start insert mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertAt {
+    /// `i`
+    Cursor,
+    /// `a`
+    AfterCursor,
+    /// `I`, at the line's first non-blank
+    LineStart,
+    /// `A`
+    LineEnd,
+    /// `o`, on a new line below
+    LineBelow,
+    /// `O`
+    LineAbove,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Moves the cursor
+    Motion(Motion),
+    Operate(Operator, Target),
+    /// Selects the text object around the cursor, from visual mode
+    SelectObject(TextObject),
+    /// Starts selecting, or changes the shape selected, or stops if it is
+    /// already that shape
+    Select(SelectionKind),
+    Visual(VisualAction),
+    Insert(InsertAt),
+    Undo,
+    Redo,
+    Hover,
+    Definition,
+    Completion,
+    /// `]c` and `[c`
+    JumpToHunk { forward: bool },
+    /// `gn`
+    AddCursorAtNextMatch,
+    /// `gj` and `gk`
+    AddCursorBeside { below: bool },
+    /// `Y`
+    YankLines,
+    /// `x`
+    DeleteChars,
+    /// `p` and `P`
+    Put { after: bool },
+    /// `/` and `?`
+    Search(SearchDirection),
+    /// `:`
+    CommandLine,
+    /// `n` and `N`
+    RepeatSearch { reverse: bool },
+    /// Esc with nothing selected
+    Escape,
+}
+
+/// An action, with the count and register typed for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Command {
+    pub action: Action,
+    /// A count typed before an operator is multiplied by one after it
+    pub count: Option<usize>,
+    pub register: Option<char>,
+}
+
+// What a key did to the command being typed
+enum Step {
+    Finished(Action),
+    Partial,
+    Nothing,
+}
+
+/// The command being typed.
+#[derive(Debug, Default)]
+pub struct Keys {
+    // A key waiting for the one that finishes the command, like the first
+    // `g` of `gg` or the `"` before a register name
+    pending: Option<char>,
+    register: Option<char>,
+    count: Option<usize>,
+    // The operator waiting for what to act on, with the count typed before it
+    operator: Option<(Operator, Option<usize>)>,
+}
+
+impl Keys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the next key, returning the command it finishes if it finishes
+    /// one. `visual` says whether anything is selected, which some keys act
+    /// on instead. Keys that make no sense drop what was typed before them.
+    pub fn handle(&mut self, code: KeyCode, modifiers: KeyModifiers, visual: bool) -> Option<Command> {
+        let step = match self.pending.take() {
+            Some(prefix) => self.after_prefix(prefix, code),
+            None => match visual_action(code, modifiers).filter(|_| visual) {
+                Some(action) => Step::Finished(action),
+                None => self.key(code, modifiers, visual),
+            },
+        };
+
+        let command = match step {
+            Step::Partial => return None,
+            Step::Finished(action) => Some(Command { action, count: self.take_count(), register: self.register }),
+            Step::Nothing => None,
+        };
+
+        *self = Self::default();
+        command
+    }
+
+    /// Whether part of a command has been typed.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some() || self.register.is_some() || self.count.is_some() || self.operator.is_some()
+    }
+
+    /// The count typed for the command, times the one typed before its
+    /// operator.
+    fn take_count(&mut self) -> Option<usize> {
+        let before = self.operator.and_then(|(_, count)| count);
+
+        match (before, self.count.take()) {
+            (Some(before), Some(count)) => Some(before.saturating_mul(count)),
+            (before, count) => before.or(count),
+        }
+    }
+
+    fn key(&mut self, code: KeyCode, modifiers: KeyModifiers, visual: bool) -> Step {
+        let motion = match code {
+            KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => Motion::from_key(c),
+            KeyCode::Left => Some(Motion::Left),
+            KeyCode::Right => Some(Motion::Right),
+            KeyCode::Up => Some(Motion::Up),
+            KeyCode::Down => Some(Motion::Down),
+            _ => None,
+        };
+
+        let action = match code {
+            KeyCode::Char('r') if modifiers == KeyModifiers::CONTROL => Action::Redo,
+            // Terminals send Ctrl-] as Ctrl-5
+            KeyCode::Char(']' | '5') if modifiers == KeyModifiers::CONTROL => Action::Definition,
+            KeyCode::Char(' ') if modifiers == KeyModifiers::CONTROL => Action::Completion,
+            KeyCode::Char('v') if modifiers == KeyModifiers::CONTROL => Action::Select(SelectionKind::Block),
+            KeyCode::Char(c @ '0'..='9') if c != '0' || self.count.is_some() => {
+                let digit = c.to_digit(10).unwrap_or_default() as usize;
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+
+                return Step::Partial;
+            },
+            _ if motion.is_some() => return self.motion(motion.unwrap()),
+            KeyCode::Char('u') => Action::Undo,
+            KeyCode::Char('K') => Action::Hover,
+            KeyCode::Char(c @ ('d' | 'c' | 'y' | '>' | '<')) => return self.operator(Operator::from_key(c).unwrap()),
+            KeyCode::Char(c @ ('i' | 'a')) if self.operator.is_some() || visual => {
+                self.pending = Some(c);
+                return Step::Partial;
+            },
+            KeyCode::Char('i') => Action::Insert(InsertAt::Cursor),
+            KeyCode::Char('a') => Action::Insert(InsertAt::AfterCursor),
+            KeyCode::Char('I') if !visual => Action::Insert(InsertAt::LineStart),
+            KeyCode::Char('A') if !visual => Action::Insert(InsertAt::LineEnd),
+            KeyCode::Char('o') if !visual => Action::Insert(InsertAt::LineBelow),
+            KeyCode::Char('O') if !visual => Action::Insert(InsertAt::LineAbove),
+            KeyCode::Char(c @ (']' | '[' | '"' | 'g' | 'f' | 'F' | 't' | 'T')) => {
+                self.pending = Some(c);
+                return Step::Partial;
+            },
+            KeyCode::Char('v') => Action::Select(SelectionKind::Char),
+            KeyCode::Char('V') => Action::Select(SelectionKind::Line),
+            KeyCode::Char('Y') => Action::YankLines,
+            KeyCode::Char('x') => Action::DeleteChars,
+            KeyCode::Char(c @ ('p' | 'P')) => Action::Put { after: c == 'p' },
+            KeyCode::Char('/') => Action::Search(SearchDirection::Forward),
+            KeyCode::Char('?') => Action::Search(SearchDirection::Backward),
+            KeyCode::Char(':') => Action::CommandLine,
+            KeyCode::Char(c @ ('n' | 'N')) => Action::RepeatSearch { reverse: c == 'N' },
+            KeyCode::Esc => Action::Escape,
+            _ => return Step::Nothing,
+        };
+
+        Step::Finished(action)
+    }
+
+    /// The key after a prefix like `g`, `f`, `"` or the `i` of `diw`.
+    fn after_prefix(&mut self, prefix: char, code: KeyCode) -> Step {
+        let KeyCode::Char(c) = code else { return Step::Nothing };
+
+        let action = match (prefix, c) {
+            (']', 'c') => Action::JumpToHunk { forward: true },
+            ('[', 'c') => Action::JumpToHunk { forward: false },
+            ('"', c) if Registers::is_valid(c) => {
+                self.register = Some(c);
+                return Step::Partial;
+            },
+            ('g', 'n') => Action::AddCursorAtNextMatch,
+            ('g', 'j') => Action::AddCursorBeside { below: true },
+            ('g', 'k') => Action::AddCursorBeside { below: false },
+            ('i' | 'a', c) => match TextObject::from_keys(prefix, c) {
+                Some(object) => match self.operator {
+                    Some((operator, _)) => Action::Operate(operator, Target::Object(object)),
+                    None => Action::SelectObject(object),
+                },
+                None => return Step::Nothing,
+            },
+            (_, c) => match Motion::from_keys(prefix, c) {
+                Some(motion) => return self.motion(motion),
+                None => return Step::Nothing,
+            },
+        };
+
+        Step::Finished(action)
+    }
+
+    /// Moves the cursor, or has the operator waiting act on what the motion
+    /// moves over.
+    fn motion(&mut self, motion: Motion) -> Step {
+        match self.operator {
+            Some((operator, _)) => Step::Finished(Action::Operate(operator, Target::Motion(motion))),
+            None => Step::Finished(Action::Motion(motion)),
+        }
+    }
+
+    /// Starts `operator`, or has it act on whole lines when it is typed
+    /// twice, as in `dd`.
+    fn operator(&mut self, operator: Operator) -> Step {
+        match self.operator {
+            Some((pending, _)) if pending == operator => Step::Finished(Action::Operate(operator, Target::Lines)),
+            // Some other operator was waiting, so neither makes sense
+            Some(_) => Step::Nothing,
+            None => {
+                self.operator = Some((operator, self.count.take()));
+                Step::Partial
+            },
+        }
+    }
+}
+
+/// The keys that act on the selection. Every other key does what it does
+/// in normal mode, moving the cursor and so the selection's end.
+fn visual_action(code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+    let action = match code {
+        KeyCode::Esc => VisualAction::Exit,
+        KeyCode::Char('v') if modifiers == KeyModifiers::CONTROL => return Some(Action::Select(SelectionKind::Block)),
+        KeyCode::Char('v') => return Some(Action::Select(SelectionKind::Char)),
+        KeyCode::Char('V') => return Some(Action::Select(SelectionKind::Line)),
+        KeyCode::Char('o') => VisualAction::SwapEnds,
+        KeyCode::Char('I') => VisualAction::AddCursors,
+        KeyCode::Char('y') => VisualAction::Yank,
+        KeyCode::Char('d' | 'x') => VisualAction::Delete,
+        KeyCode::Char('c') => VisualAction::Change,
+        KeyCode::Char('>') => VisualAction::Indent,
+        KeyCode::Char('<') => VisualAction::Unindent,
+        KeyCode::Char('~') => VisualAction::ToggleCase,
+        KeyCode::Char('u') => VisualAction::Lowercase,
+        KeyCode::Char('U') => VisualAction::Uppercase,
+        _ => return None,
+    };
+
+    Some(Action::Visual(action))
+}
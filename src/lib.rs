@@ -1,21 +1,235 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
 use std::path::PathBuf;
 
 #[macro_use] extern crate log;
 
 pub mod ui;
 pub mod application;
+pub mod cargo;
+pub mod command;
+pub mod cursors;
+pub mod diagnostics;
+pub mod diff;
+pub mod document;
+pub mod events;
+pub mod finder;
+pub mod git;
+pub mod grep;
+pub mod history;
+pub mod jobs;
+pub mod keys;
+pub mod loader;
+pub mod lsp;
+pub mod motion;
+pub mod registers;
+pub mod search;
+pub mod selection;
+pub mod substitute;
+pub mod text;
+pub mod vt;
+pub mod watcher;
 pub mod windows;
 
+use diagnostics::{ Diagnostic, DiagnosticSet, Report };
+use git::{ Hunk, RepoStatus };
+use jobs::JobStatus;
+use registers::{ Register, Registers };
+use text::{ Encoding, LineEnding };
+use ui::bus::State;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Search,
+    Command,
+    Visual,
+    VisualLine,
+    VisualBlock,
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mode::Normal => write!(f, "NORMAL"),
+            Mode::Insert => write!(f, "INSERT"),
+            Mode::Search => write!(f, "SEARCH"),
+            Mode::Command => write!(f, "COMMAND"),
+            Mode::Visual => write!(f, "VISUAL"),
+            Mode::VisualLine => write!(f, "V-LINE"),
+            Mode::VisualBlock => write!(f, "V-BLOCK"),
+        }
+    }
+}
+
+/// What the selected buffer knows about the file it is showing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileStatus {
+    pub modified: bool,
+    pub line_count: usize,
+    pub line_ending: LineEnding,
+    pub encoding: Encoding,
+}
+
+/// A request for the buffer to show `path` with the cursor at `position`,
+/// a `(column, line)` pair.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Jump {
+    pub id: u64,
+    pub path: PathBuf,
+    pub position: (usize, usize),
+}
+
+/// Where the selected buffer's cursors are on screen. The primary one is
+/// the terminal's own cursor; the others are drawn by the buffer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CursorPosition {
+    pub primary: (u16, u16),
+    pub secondary: Vec<(u16, u16)>,
+}
+
+/// Every change that can be made to `AppState`. Windows and jobs send these
+/// instead of changing the state themselves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// Adds a file to the open files if it isn't there already, and selects it
+    OpenFile(PathBuf),
+    /// Opens a file with the cursor at a `(column, line)`
+    Jump { path: PathBuf, position: (usize, usize) },
+    /// Where the selected buffer's cursors are on screen, and how far it is scrolled
+    MoveCursor { position: CursorPosition, scroll: usize },
+    SetMode(Mode),
+    SetFileStatus(FileStatus),
+    SetCommandLine(String),
+    ShowMessage(String),
+    SetJobs(Vec<JobStatus>),
+    ToggleSidebar,
+    /// Replaces the text of an open file between two `(column, line)`s
+    Edit { path: PathBuf, start: (usize, usize), end: (usize, usize), text: String },
+    /// Replaces what a diagnostic provider said about a file
+    SetDiagnostics(Report),
+    /// Forgets everything a diagnostic provider said
+    ClearDiagnostics(String),
+    SetQuickfix(Vec<(PathBuf, Diagnostic)>),
+    /// Jumps to an entry in the quickfix list
+    SelectQuickfix(usize),
+    /// Replaces how a file differs from `HEAD`, as of a revision of its
+    /// history. Diffs of older revisions than the last one are dropped.
+    SetHunks { path: PathBuf, revision: u64, hunks: Vec<Hunk> },
+    /// Puts back what `HEAD` has in place of a hunk
+    RevertHunk { path: PathBuf, hunk: Hunk },
+    /// Replaces what git thinks of every file under the root
+    SetGitStatus(RepoStatus),
+    /// Fills a register, `None` being the unnamed one
+    SetRegister { name: Option<char>, register: Register },
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct AppState {
+    /// The directory the dir tree and project-wide searches start from.
+    pub root: PathBuf,
     pub sidebar_toggle: bool,
-    pub cursor_position: (u16, u16),
+    pub cursor_position: CursorPosition,
+    pub scroll_offset: usize,
     pub open_files: Vec<PathBuf>,
-    pub selected_file: usize
+    pub selected_file: usize,
+    pub mode: Mode,
+    pub file_status: FileStatus,
+    /// Text being typed into the command gutter, including its prompt.
+    pub command_line: String,
+    /// Feedback shown in the command gutter, such as a match count or error.
+    pub message: String,
+    pub jump: Option<Jump>,
+    /// Background jobs still running, for the status line.
+    pub jobs: Vec<JobStatus>,
+    pub diagnostics: DiagnosticSet,
+    /// What the last `:make` found, in the order it was found.
+    pub quickfix: Vec<(PathBuf, Diagnostic)>,
+    /// The quickfix entry last jumped to.
+    pub quickfix_index: Option<usize>,
+    /// How each open file differs from `HEAD`, in order.
+    pub hunks: BTreeMap<PathBuf, Vec<Hunk>>,
+    /// The revision each file's `hunks` were found for.
+    pub hunks_revision: BTreeMap<PathBuf, u64>,
+    /// The branch and changed files of the repository the root is in.
+    pub git_status: RepoStatus,
+    /// Yanked and deleted text, shared by every open file.
+    pub registers: Registers,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Adds `path` to the open files if it isn't there already, and selects it.
+    pub fn open_file(&mut self, path: PathBuf) {
+        match self.open_files.iter().position(|p| *p == path) {
+            Some(i) => self.selected_file = i,
+            None => {
+                self.open_files.push(path);
+                self.selected_file = self.open_files.len() - 1;
+            }
+        }
+    }
+
+    /// Opens `path` and asks for the cursor to be moved to `position`.
+    pub fn jump_to(&mut self, path: PathBuf, position: (usize, usize)) {
+        let id = self.jump.as_ref().map_or(0, |j| j.id + 1);
+
+        self.open_file(path.clone());
+        self.jump = Some(Jump { id, path, position });
+    }
+}
+
+impl State for AppState {
+    type Message = Message;
+
+    fn apply(&mut self, message: &Message) {
+        match message {
+            Message::OpenFile(path) => self.open_file(path.clone()),
+            Message::Jump { path, position } => self.jump_to(path.clone(), *position),
+            Message::MoveCursor { position, scroll } => {
+                self.cursor_position = position.clone();
+                self.scroll_offset = *scroll;
+            },
+            Message::SetMode(mode) => self.mode = *mode,
+            Message::SetFileStatus(status) => self.file_status = *status,
+            Message::SetCommandLine(text) => self.command_line = text.clone(),
+            Message::ShowMessage(text) => self.message = text.clone(),
+            Message::SetJobs(jobs) => self.jobs = jobs.clone(),
+            Message::ToggleSidebar => self.sidebar_toggle = !self.sidebar_toggle,
+            // The text is the buffer's business
+            Message::Edit { .. } | Message::RevertHunk { .. } => { },
+            Message::SetDiagnostics(report) => self.diagnostics.set(report),
+            Message::ClearDiagnostics(source) => self.diagnostics.clear_source(source),
+            Message::SetQuickfix(entries) => {
+                self.quickfix = entries.clone();
+                self.quickfix_index = None;
+            },
+            Message::SelectQuickfix(index) => {
+                if let Some((path, diagnostic)) = self.quickfix.get(*index) {
+                    self.quickfix_index = Some(*index);
+                    self.jump_to(path.clone(), diagnostic.start);
+                }
+            },
+            Message::SetHunks { path, revision, hunks } => {
+                // A slow diff finishing after a newer one
+                if self.hunks_revision.get(path).is_some_and(|last| last > revision) {
+                    return;
+                }
+
+                self.hunks.insert(path.clone(), hunks.clone());
+                self.hunks_revision.insert(path.clone(), *revision);
+            },
+            Message::SetGitStatus(status) => self.git_status = status.clone(),
+            Message::SetRegister { name, register } => self.registers.set(*name, register.clone()),
+        }
+    }
+
+    fn show_message(text: String) -> Message {
+        Message::ShowMessage(text)
+    }
 }
@@ -0,0 +1,172 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{ Read, Seek, SeekFrom };
+use std::path::{ Path, PathBuf };
+use std::sync::{
+    Arc,
+    atomic::{ AtomicBool, Ordering },
+    mpsc::{ self, Receiver },
+};
+use std::thread;
+
+use crate::events::Waker;
+use crate::text::{ Decoder, Encoding };
+
+/// Files are read this many bytes at a time.
+const CHUNK_SIZE: usize = 1 << 20;
+
+#[derive(Debug)]
+pub enum LoadEvent {
+    /// The text starts (over) from here, decoded as this. Sent first, and
+    /// again if the file turns out not to be what it was first taken for.
+    Start(Encoding),
+    /// The next piece of text, and how many bytes of the file it took to get
+    /// this far
+    Text { text: String, read: u64 },
+    Failed(String),
+}
+
+/// Reads and decodes a file on a background thread, a piece at a time, so
+/// it can be shown before it's all there. Dropping it stops the read.
+#[derive(Debug)]
+pub struct FileLoader {
+    receiver: Receiver<LoadEvent>,
+    cancelled: Arc<AtomicBool>,
+    total: u64,
+    read: u64,
+    done: bool,
+}
+
+impl FileLoader {
+    /// `waker` is woken after each piece. `total` is the size of the file,
+    /// for showing progress.
+    pub fn spawn(path: PathBuf, total: u64, waker: Option<Waker>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancelled);
+
+        thread::spawn(move || {
+            let send = |event: LoadEvent| {
+                let sent = sender.send(event).is_ok();
+
+                if let Some(waker) = &waker {
+                    waker.wake();
+                }
+
+                sent
+            };
+
+            if let Err(e) = load(&path, &flag, send) {
+                let _ = sender.send(LoadEvent::Failed(e.to_string()));
+            }
+
+            // The load is done once the sender is gone, so drop it before waking
+            drop(sender);
+
+            if let Some(waker) = &waker {
+                waker.wake();
+            }
+        });
+
+        Self { receiver, cancelled, total, read: 0, done: false }
+    }
+
+    /// Everything read since the last call, without blocking.
+    pub fn poll(&mut self) -> Vec<LoadEvent> {
+        let mut events = Vec::new();
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(event) => {
+                    match &event {
+                        LoadEvent::Start(_) => self.read = 0,
+                        LoadEvent::Text { read, .. } => self.read = *read,
+                        LoadEvent::Failed(_) => { },
+                    }
+
+                    events.push(event);
+                },
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+
+        events
+    }
+
+    /// How many bytes of how many have been read.
+    pub fn progress(&self) -> (u64, u64) {
+        (self.read, self.total)
+    }
+
+    /// Whether the file has been read, or failed to be, and everything has
+    /// been polled.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+impl Drop for FileLoader {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Reads `path` through `send` until it's done, cancelled, or `send` fails.
+/// Files without a byte order mark are read as UTF-8 until they turn out not
+/// to be, and then read again from the start as Latin-1.
+fn load<F>(path: &Path, cancelled: &AtomicBool, send: F) -> Result<(), Box<dyn Error>>
+where F: Fn(LoadEvent) -> bool {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![ 0; CHUNK_SIZE ];
+    let mut decoder: Option<Decoder> = None;
+    let mut read = 0;
+
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let len = file.read(&mut buffer)?;
+        let bytes = &buffer[..len];
+        read += len as u64;
+
+        let decoder = decoder.get_or_insert_with(|| {
+            let encoding = match Encoding::detect(bytes) {
+                // The rest of the file may not be, but it's a good guess
+                Encoding::Latin1 if std::str::from_utf8(bytes).is_err_and(|e| e.error_len().is_none()) => Encoding::Utf8,
+                encoding => encoding,
+            };
+
+            Decoder::new(encoding)
+        });
+
+        // The first piece since starting, or starting over
+        if read == len as u64 && !send(LoadEvent::Start(decoder.encoding())) {
+            return Ok(());
+        }
+
+        let text = match decoder.decode(bytes, len == 0) {
+            Ok(text) => text,
+            Err(_) if decoder.encoding() == Encoding::Utf8 => {
+                *decoder = Decoder::new(Encoding::Latin1);
+                file.seek(SeekFrom::Start(0))?;
+                read = 0;
+
+                continue;
+            },
+            Err(e) => return Err(e),
+        };
+
+        if !text.is_empty() && !send(LoadEvent::Text { text, read }) {
+            return Ok(());
+        }
+
+        if len == 0 {
+            return Ok(());
+        }
+    }
+}
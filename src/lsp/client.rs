@@ -0,0 +1,575 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{ BufReader, BufWriter };
+use std::path::{ Path, PathBuf };
+use std::process::{ Child, Command, Stdio };
+use std::sync::mpsc::{ self, Receiver, Sender, TryRecvError };
+use std::thread;
+use std::time::Duration;
+
+use ropey::Rope;
+use serde_json::{ json, Value };
+
+use crate::diagnostics::{ Diagnostic, Severity };
+use crate::events::Waker;
+
+use super::protocol::{ self, PositionEncoding };
+use super::{ CompletionItem, Location, LspEvent, ServerConfig };
+
+/// JSON-RPC's code for requests the server doesn't handle.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestKind {
+    Initialize,
+    Hover,
+    Completion,
+    Definition,
+}
+
+/// A request still waiting for its response, and where it was made.
+#[derive(Debug)]
+struct Pending {
+    kind: RequestKind,
+    path: PathBuf,
+    position: (usize, usize),
+}
+
+/// What the client knows of a document it told the server about.
+#[derive(Debug)]
+struct OpenDocument {
+    path: PathBuf,
+    // The text as last sent, which the server's positions refer to
+    text: Rope,
+}
+
+/// What the server said it can do.
+#[derive(Debug, Default)]
+struct Capabilities {
+    hover: bool,
+    completion: bool,
+    definition: bool,
+}
+
+/// One running language server, talked to over its stdin and stdout. The
+/// reading and writing happen on threads of their own, so a slow server
+/// never holds up the UI.
+#[derive(Debug)]
+pub struct LanguageClient {
+    language_id: String,
+    child: Option<Child>,
+    sender: Option<Sender<Value>>,
+    receiver: Receiver<Value>,
+    next_id: u64,
+    pending: HashMap<u64, Pending>,
+    // By URI
+    documents: HashMap<String, OpenDocument>,
+    encoding: PositionEncoding,
+    capabilities: Capabilities,
+    initialized: bool,
+    // Everything sent before the server answered `initialize`
+    queued: Vec<Value>,
+    running: bool,
+}
+
+impl LanguageClient {
+    /// Starts the server `config` describes in `root`, and starts
+    /// initializing it. `waker` is woken whenever the server sends something.
+    pub fn spawn(config: &ServerConfig, root: &Path, waker: Option<Waker>) -> Result<Self, Box<dyn Error>> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().ok_or("No stdin")?;
+        let stdout = child.stdout.take().ok_or("No stdout")?;
+
+        let (sender, outgoing) = mpsc::channel::<Value>();
+        let (incoming, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut writer = BufWriter::new(stdin);
+
+            for message in outgoing {
+                if let Err(e) = protocol::write_message(&mut writer, &message) {
+                    warn!("Can't write to language server: {}", e);
+                    break;
+                }
+            }
+        });
+
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+
+            loop {
+                match protocol::read_message(&mut reader) {
+                    Ok(Some(message)) => {
+                        if incoming.send(message).is_err() {
+                            break;
+                        }
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!("Can't read from language server: {}", e);
+                        break;
+                    }
+                }
+
+                if let Some(waker) = &waker {
+                    waker.wake();
+                }
+            }
+
+            // The client notices the server is gone once this is dropped
+            drop(incoming);
+
+            if let Some(waker) = &waker {
+                waker.wake();
+            }
+        });
+
+        let mut client = Self {
+            language_id: config.language_id.clone(),
+            child: Some(child),
+            sender: Some(sender),
+            receiver,
+            next_id: 0,
+            pending: HashMap::new(),
+            documents: HashMap::new(),
+            encoding: PositionEncoding::default(),
+            capabilities: Capabilities::default(),
+            initialized: false,
+            queued: Vec::new(),
+            running: true,
+        };
+
+        client.initialize(root);
+
+        Ok(client)
+    }
+
+    pub fn language_id(&self) -> &str {
+        &self.language_id
+    }
+
+    /// Whether the server is still there to talk to.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Whether the server has answered `initialize`, and takes requests.
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    pub fn is_open(&self, path: &Path) -> bool {
+        self.documents.values().any(|d| d.path == path)
+    }
+
+    fn initialize(&mut self, root: &Path) {
+        let root_uri = protocol::path_to_uri(root);
+        let name = root.canonicalize().ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_default();
+
+        let params = json!({
+            "processId": std::process::id(),
+            "clientInfo": { "name": "gof" },
+            "rootUri": root_uri,
+            "workspaceFolders": [ { "uri": root_uri, "name": name } ],
+            "capabilities": {
+                "general": {
+                    "positionEncodings": [ "utf-32", "utf-16" ],
+                },
+                "textDocument": {
+                    "synchronization": { "didSave": true },
+                    "publishDiagnostics": { },
+                    "hover": { "contentFormat": [ "plaintext", "markdown" ] },
+                    "completion": { "completionItem": { "snippetSupport": false } },
+                    "definition": { },
+                },
+            },
+        });
+
+        let id = self.next_request_id();
+        self.pending.insert(id, Pending { kind: RequestKind::Initialize, path: PathBuf::new(), position: (0, 0) });
+        self.write(protocol::request(id, "initialize", params));
+    }
+
+    fn next_request_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn write(&mut self, message: Value) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(message);
+        }
+    }
+
+    /// Sends `message` once the server is initialized.
+    fn send(&mut self, message: Value) {
+        if self.initialized {
+            self.write(message);
+        } else {
+            self.queued.push(message);
+        }
+    }
+
+    pub fn did_open(&mut self, path: &Path, text: &Rope, version: u64) {
+        let uri = protocol::path_to_uri(path);
+
+        self.send(protocol::notification("textDocument/didOpen", json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": self.language_id,
+                "version": version,
+                "text": text.to_string(),
+            }
+        })));
+
+        self.documents.insert(uri, OpenDocument { path: path.to_path_buf(), text: text.clone() });
+    }
+
+    /// Sends the whole of `text`, which every server takes, however it would
+    /// rather be kept in sync.
+    pub fn did_change(&mut self, path: &Path, text: &Rope, version: u64) {
+        let uri = protocol::path_to_uri(path);
+        let Some(document) = self.documents.get_mut(&uri) else { return };
+
+        document.text = text.clone();
+
+        self.send(protocol::notification("textDocument/didChange", json!({
+            "textDocument": { "uri": uri, "version": version },
+            "contentChanges": [ { "text": text.to_string() } ],
+        })));
+    }
+
+    pub fn did_save(&mut self, path: &Path) {
+        let uri = protocol::path_to_uri(path);
+
+        if self.documents.contains_key(&uri) {
+            self.send(protocol::notification("textDocument/didSave", json!({
+                "textDocument": { "uri": uri },
+            })));
+        }
+    }
+
+    pub fn did_close(&mut self, path: &Path) {
+        let uri = protocol::path_to_uri(path);
+
+        if self.documents.remove(&uri).is_some() {
+            self.send(protocol::notification("textDocument/didClose", json!({
+                "textDocument": { "uri": uri },
+            })));
+        }
+    }
+
+    /// Asks about `position`, a `(column, line)`, in `path`. Returns `false`
+    /// if the request can't be made: the server isn't ready, doesn't do
+    /// this, or hasn't been told about the file.
+    fn position_request(&mut self, kind: RequestKind, method: &str, path: &Path, position: (usize, usize)) -> bool {
+        let supported = match kind {
+            RequestKind::Hover => self.capabilities.hover,
+            RequestKind::Completion => self.capabilities.completion,
+            RequestKind::Definition => self.capabilities.definition,
+            _ => true,
+        };
+
+        if !self.initialized || !supported {
+            return false;
+        }
+
+        let uri = protocol::path_to_uri(path);
+        let Some(document) = self.documents.get(&uri) else { return false };
+
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": self.encoding.to_lsp(&document.text, position),
+        });
+
+        let id = self.next_request_id();
+        self.pending.insert(id, Pending { kind, path: path.to_path_buf(), position });
+        self.write(protocol::request(id, method, params));
+
+        true
+    }
+
+    pub fn hover(&mut self, path: &Path, position: (usize, usize)) -> bool {
+        self.position_request(RequestKind::Hover, "textDocument/hover", path, position)
+    }
+
+    pub fn completion(&mut self, path: &Path, position: (usize, usize)) -> bool {
+        self.position_request(RequestKind::Completion, "textDocument/completion", path, position)
+    }
+
+    pub fn definition(&mut self, path: &Path, position: (usize, usize)) -> bool {
+        self.position_request(RequestKind::Definition, "textDocument/definition", path, position)
+    }
+
+    /// Everything the server has sent since the last call, without blocking.
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let mut events = Vec::new();
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => events.extend(self.handle_message(message)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if self.running {
+                        self.running = false;
+                        self.sender = None;
+                        events.push(LspEvent::Exited { language_id: self.language_id.clone() });
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        events
+    }
+
+    fn handle_message(&mut self, message: Value) -> Option<LspEvent> {
+        let id = message.get("id").cloned();
+
+        match (id, message.get("method").and_then(Value::as_str)) {
+            // A request from the server
+            (Some(id), Some(method)) => {
+                let result = match method {
+                    "workspace/configuration" => {
+                        let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                        Some(Value::Array(vec![ Value::Null; items ]))
+                    },
+                    "window/workDoneProgress/create" | "client/registerCapability" | "client/unregisterCapability" => Some(Value::Null),
+                    _ => None,
+                };
+
+                let response = match result {
+                    Some(result) => protocol::response(id, result),
+                    None => protocol::error_response(id, METHOD_NOT_FOUND, "Not supported"),
+                };
+
+                self.write(response);
+                None
+            },
+            (None, Some(method)) => self.handle_notification(method, &message["params"]),
+            (Some(id), None) => {
+                let pending = self.pending.remove(&id.as_u64()?)?;
+
+                if let Some(error) = message.get("error") {
+                    debug!("{} request failed: {}", self.language_id, error);
+                    return None;
+                }
+
+                self.handle_response(pending, &message["result"])
+            },
+            (None, None) => None,
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Value) -> Option<LspEvent> {
+        match method {
+            "textDocument/publishDiagnostics" => {
+                let uri = params["uri"].as_str()?;
+                let document = self.documents.get(uri)?;
+
+                let diagnostics = params["diagnostics"].as_array()?.iter()
+                    .filter_map(|d| self.diagnostic(&document.text, d))
+                    .collect();
+
+                Some(LspEvent::Diagnostics { path: document.path.clone(), diagnostics })
+            },
+            "window/showMessage" | "window/logMessage" => {
+                debug!("{}: {}", self.language_id, params["message"].as_str().unwrap_or_default());
+                None
+            },
+            _ => None,
+        }
+    }
+
+    fn handle_response(&mut self, pending: Pending, result: &Value) -> Option<LspEvent> {
+        let Pending { kind, path, position } = pending;
+
+        match kind {
+            RequestKind::Initialize => {
+                let capabilities = &result["capabilities"];
+
+                self.encoding = capabilities["positionEncoding"].as_str()
+                    .and_then(PositionEncoding::from_name)
+                    .unwrap_or_default();
+                self.capabilities = Capabilities {
+                    hover: is_provided(&capabilities["hoverProvider"]),
+                    completion: is_provided(&capabilities["completionProvider"]),
+                    definition: is_provided(&capabilities["definitionProvider"]),
+                };
+
+                debug!("{} initialized, counting positions in {}", self.language_id, self.encoding.name());
+
+                self.initialized = true;
+                self.write(protocol::notification("initialized", json!({ })));
+
+                for message in std::mem::take(&mut self.queued) {
+                    self.write(message);
+                }
+
+                None
+            },
+            RequestKind::Hover => {
+                Some(LspEvent::Hover { path, position, text: hover_text(&result["contents"]) })
+            },
+            RequestKind::Completion => {
+                let items = match result {
+                    Value::Array(items) => items.as_slice(),
+                    Value::Object(list) => list.get("items").and_then(Value::as_array).map_or(&[][..], Vec::as_slice),
+                    _ => &[],
+                };
+
+                let text = &self.documents.get(&protocol::path_to_uri(&path))?.text;
+                let items = items.iter().filter_map(|item| self.completion_item(text, item)).collect();
+
+                Some(LspEvent::Completion { path, position, items })
+            },
+            RequestKind::Definition => {
+                let locations = match result {
+                    Value::Array(locations) => locations.iter().filter_map(|l| self.location(l)).collect(),
+                    Value::Null => Vec::new(),
+                    location => self.location(location).into_iter().collect(),
+                };
+
+                Some(LspEvent::Definition(locations))
+            },
+        }
+    }
+
+    fn diagnostic(&self, text: &Rope, diagnostic: &Value) -> Option<Diagnostic> {
+        let range = &diagnostic["range"];
+
+        let severity = match diagnostic["severity"].as_u64() {
+            Some(2) => Severity::Warning,
+            Some(3) => Severity::Info,
+            Some(4) => Severity::Hint,
+            _ => Severity::Error,
+        };
+
+        Some(Diagnostic {
+            start: self.encoding.from_lsp(text, &range["start"])?,
+            end: self.encoding.from_lsp(text, &range["end"])?,
+            severity,
+            message: diagnostic["message"].as_str()?.to_string(),
+            source: diagnostic["source"].as_str().map(str::to_string),
+        })
+    }
+
+    fn completion_item(&self, text: &Rope, item: &Value) -> Option<CompletionItem> {
+        let label = item["label"].as_str()?.to_string();
+        let edit = &item["textEdit"];
+
+        // Insert and replace edits give two ranges; replacing is what the
+        // cursor being inside a word calls for
+        let range = match (&edit["range"], &edit["replace"]) {
+            (range @ Value::Object(_), _) | (_, range @ Value::Object(_)) => {
+                let start = self.encoding.from_lsp(text, &range["start"])?;
+                let end = self.encoding.from_lsp(text, &range["end"])?;
+
+                Some((start, end))
+            },
+            _ => None,
+        };
+
+        let text = edit["newText"].as_str()
+            .or(item["insertText"].as_str())
+            .unwrap_or(&label)
+            .to_string();
+
+        Some(CompletionItem {
+            detail: item["detail"].as_str().map(str::to_string),
+            label,
+            text,
+            range,
+        })
+    }
+
+    fn location(&self, location: &Value) -> Option<Location> {
+        // `LocationLink`s name their target differently
+        let uri = location["uri"].as_str().or(location["targetUri"].as_str())?;
+        let range = match &location["targetSelectionRange"] {
+            Value::Null => &location["range"],
+            range => range,
+        };
+
+        let path = self.documents.get(uri)
+            .map(|d| d.path.clone())
+            .or_else(|| protocol::uri_to_path(uri))?;
+
+        // Files the server hasn't been told about have to be read to make
+        // sense of the position
+        let position = match self.documents.get(uri) {
+            Some(document) => self.encoding.from_lsp(&document.text, &range["start"])?,
+            None => {
+                let text = std::fs::read_to_string(&path).map(|t| Rope::from_str(&t)).unwrap_or_default();
+                self.encoding.from_lsp(&text, &range["start"])?
+            }
+        };
+
+        Some(Location { path, position })
+    }
+}
+
+impl Drop for LanguageClient {
+    fn drop(&mut self) {
+        let Some(mut child) = self.child.take() else { return };
+
+        if self.running && self.initialized {
+            let id = self.next_request_id();
+
+            self.write(protocol::request(id, "shutdown", Value::Null));
+            self.write(protocol::notification("exit", Value::Null));
+        }
+
+        // Closes the server's stdin once everything has been written
+        self.sender = None;
+
+        // Give the server a moment to go quietly, without holding anyone up
+        thread::spawn(move || {
+            for _ in 0..20 {
+                if let Ok(Some(_)) = child.try_wait() {
+                    return;
+                }
+
+                thread::sleep(Duration::from_millis(50));
+            }
+
+            let _ = child.kill();
+            let _ = child.wait();
+        });
+    }
+}
+
+/// Whether a server capability is there, which it may say with `true` or
+/// with an object of options.
+fn is_provided(capability: &Value) -> bool {
+    !matches!(capability, Value::Null | Value::Bool(false))
+}
+
+/// The plain text of hover contents, which may be a string, a marked
+/// string, markup, or a list of any of those.
+fn hover_text(contents: &Value) -> String {
+    let text = match contents {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter().map(hover_text).filter(|t| !t.is_empty()).collect::<Vec<_>>().join("\n\n"),
+        Value::Object(part) => part.get("value").and_then(Value::as_str).unwrap_or_default().to_string(),
+        _ => String::new(),
+    };
+
+    // Code fences are for rendering markdown, which isn't done here
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,214 @@
+mod client;
+pub use client::*;
+
+pub mod protocol;
+
+use std::path::{ Path, PathBuf };
+
+use ropey::Rope;
+
+use crate::diagnostics::{ Diagnostic, DiagnosticProvider, Report };
+use crate::events::Waker;
+
+/// How to start a language server, and which files it's for.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Sent to the server with each file, e.g. `rust`
+    pub language_id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    /// Extensions of the files the server is for, without the dot
+    pub extensions: Vec<String>,
+}
+
+impl ServerConfig {
+    pub fn new(language_id: &str, command: &str) -> Self {
+        Self {
+            language_id: language_id.to_string(),
+            command: command.to_string(),
+            args: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+
+    pub fn args(self, args: &[&str]) -> Self {
+        Self { args: args.iter().map(|a| a.to_string()).collect(), ..self }
+    }
+
+    pub fn extensions(self, extensions: &[&str]) -> Self {
+        Self { extensions: extensions.iter().map(|e| e.to_string()).collect(), ..self }
+    }
+
+    pub fn handles(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| self.extensions.iter().any(|x| x == e))
+    }
+}
+
+/// Something offered to complete the text before the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub detail: Option<String>,
+    /// What to insert
+    pub text: String,
+    /// What `text` replaces, as `(column, line)`s, if the server said.
+    /// Otherwise it replaces the word before the cursor.
+    pub range: Option<((usize, usize), (usize, usize))>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    // (column, line)
+    pub position: (usize, usize),
+}
+
+/// Something a language server has sent. Positions are `(column, line)`s
+/// in chars, whatever the server counts in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LspEvent {
+    /// Every diagnostic for `path`, replacing the last lot
+    Diagnostics { path: PathBuf, diagnostics: Vec<Diagnostic> },
+    /// About `position` in `path`, where it was asked for. Empty if there's
+    /// nothing to say.
+    Hover { path: PathBuf, position: (usize, usize), text: String },
+    Completion { path: PathBuf, position: (usize, usize), items: Vec<CompletionItem> },
+    Definition(Vec<Location>),
+    /// The server quit or crashed, and won't be started again
+    Exited { language_id: String },
+}
+
+/// Every language server there's a config for, each started once a file
+/// it's for is opened.
+#[derive(Debug, Default)]
+pub struct LanguageServers {
+    configs: Vec<ServerConfig>,
+    clients: Vec<LanguageClient>,
+    // Languages whose server couldn't be started, or has exited
+    failed: Vec<String>,
+    root: PathBuf,
+    waker: Option<Waker>,
+    // Diagnostics held back from `poll` for `poll_diagnostics`
+    reports: Vec<Report>,
+}
+
+impl LanguageServers {
+    pub fn new(configs: Vec<ServerConfig>) -> Self {
+        Self { configs, ..Self::default() }
+    }
+
+    /// Where servers are started from, and what they take as the project.
+    pub fn set_root(&mut self, root: &Path) {
+        self.root = root.to_path_buf();
+    }
+
+    pub fn set_waker(&mut self, waker: Waker) {
+        self.waker = Some(waker);
+    }
+
+    /// The running server for `path`'s language, started if it hasn't been.
+    fn client_for(&mut self, path: &Path) -> Option<&mut LanguageClient> {
+        let config = self.configs.iter().find(|c| c.handles(path))?;
+
+        if self.failed.contains(&config.language_id) {
+            return None;
+        }
+
+        let index = match self.clients.iter().position(|c| c.language_id() == config.language_id) {
+            Some(index) => index,
+            None => {
+                let root = if self.root.as_os_str().is_empty() { Path::new(".") } else { &self.root };
+
+                match LanguageClient::spawn(config, root, self.waker.clone()) {
+                    Ok(client) => {
+                        debug!("Started {} for {}", config.command, config.language_id);
+                        self.clients.push(client);
+                        self.clients.len() - 1
+                    },
+                    Err(e) => {
+                        warn!("Can't start {}: {}", config.command, e);
+                        self.failed.push(config.language_id.clone());
+                        return None;
+                    }
+                }
+            }
+        };
+
+        Some(&mut self.clients[index])
+    }
+
+    /// Whether there is a server for `path`, running or yet to be started.
+    pub fn handles(&self, path: &Path) -> bool {
+        self.configs.iter().any(|c| c.handles(path) && !self.failed.contains(&c.language_id))
+    }
+
+    /// Tells the server for `path` about the file's text, as it is at
+    /// `version`, opening it first if it hasn't been.
+    pub fn sync(&mut self, path: &Path, text: &Rope, version: u64) {
+        let Some(client) = self.client_for(path) else { return };
+
+        if client.is_open(path) {
+            client.did_change(path, text, version);
+        } else {
+            client.did_open(path, text, version);
+        }
+    }
+
+    pub fn did_save(&mut self, path: &Path) {
+        if let Some(client) = self.client_for(path) {
+            client.did_save(path);
+        }
+    }
+
+    /// Asks what's at `position` in `path`. Returns `false` if there's no
+    /// server ready to say; the answer otherwise comes from `poll`.
+    pub fn hover(&mut self, path: &Path, position: (usize, usize)) -> bool {
+        self.client_for(path).is_some_and(|c| c.hover(path, position))
+    }
+
+    pub fn completion(&mut self, path: &Path, position: (usize, usize)) -> bool {
+        self.client_for(path).is_some_and(|c| c.completion(path, position))
+    }
+
+    pub fn definition(&mut self, path: &Path, position: (usize, usize)) -> bool {
+        self.client_for(path).is_some_and(|c| c.definition(path, position))
+    }
+
+    /// Everything every server has sent since the last call, except
+    /// diagnostics, which are kept for `poll_diagnostics`. Servers that have
+    /// exited are dropped, and not started again.
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let mut events = Vec::new();
+
+        for event in self.clients.iter_mut().flat_map(LanguageClient::poll) {
+            match event {
+                LspEvent::Diagnostics { path, diagnostics } => {
+                    self.reports.push(Report { source: "lsp".to_string(), path, diagnostics });
+                },
+                event => events.push(event),
+            }
+        }
+
+        for client in self.clients.iter().filter(|c| !c.is_running()) {
+            warn!("Language server for {} exited", client.language_id());
+            self.failed.push(client.language_id().to_string());
+        }
+
+        self.clients.retain(LanguageClient::is_running);
+
+        events
+    }
+}
+
+impl DiagnosticProvider for LanguageServers {
+    /// Only what's been picked up by `poll`.
+    fn poll_diagnostics(&mut self) -> Vec<Report> {
+        std::mem::take(&mut self.reports)
+    }
+
+    fn set_waker(&mut self, waker: Waker) {
+        LanguageServers::set_waker(self, waker);
+    }
+}
@@ -0,0 +1,204 @@
+//! The wire format of the Language Server Protocol: JSON-RPC messages with
+//! HTTP-style headers, file URIs, and positions counted in the units the
+//! server and client agreed on.
+
+use std::error::Error;
+use std::io::{ self, BufRead, Write };
+use std::path::{ Path, PathBuf };
+
+use ropey::Rope;
+use serde_json::{ json, Value };
+
+/// Writes `message` with the header announcing its length.
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Reads the next message, or `None` once the other side has hung up.
+pub fn read_message<R: BufRead>(reader: &mut R) -> Result<Option<Value>, Box<dyn Error>> {
+    let mut length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let header = line.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        // Content-Type is the only other header, and there's only one
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+
+    let length = length.ok_or("Message without a Content-Length")?;
+    let mut body = vec![ 0; length ];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+pub fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+pub fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+pub fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// What a position's `character` counts. Servers use UTF-16 unless they
+/// agree to something else.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+    Utf8,
+    #[default]
+    Utf16,
+    Utf32,
+}
+
+impl PositionEncoding {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "utf-8" => Some(Self::Utf8),
+            "utf-16" => Some(Self::Utf16),
+            "utf-32" => Some(Self::Utf32),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16 => "utf-16",
+            Self::Utf32 => "utf-32",
+        }
+    }
+
+    /// `(column, line)` in chars as an LSP position in `text`.
+    pub fn to_lsp(self, text: &Rope, (column, line): (usize, usize)) -> Value {
+        let line = line.min(text.len_lines() - 1);
+        let start = text.line_to_char(line);
+        let char_idx = start + column.min(line_len(text, line));
+
+        let character = match self {
+            Self::Utf8 => text.char_to_byte(char_idx) - text.char_to_byte(start),
+            Self::Utf16 => text.char_to_utf16_cu(char_idx) - text.char_to_utf16_cu(start),
+            Self::Utf32 => char_idx - start,
+        };
+
+        json!({ "line": line, "character": character })
+    }
+
+    /// An LSP position in `text` as a `(column, line)` in chars. Positions
+    /// past the end of a line or of the text are moved back onto it.
+    pub fn from_lsp(self, text: &Rope, position: &Value) -> Option<(usize, usize)> {
+        let line = position.get("line")?.as_u64()? as usize;
+        let character = position.get("character")?.as_u64()? as usize;
+
+        let last = text.len_lines() - 1;
+
+        if line > last {
+            return Some((line_len(text, last), last));
+        }
+
+        let start = text.line_to_char(line);
+        let len = line_len(text, line);
+
+        let column = match self {
+            Self::Utf8 => {
+                let byte = (text.char_to_byte(start) + character).min(text.len_bytes());
+                text.byte_to_char(byte) - start
+            },
+            Self::Utf16 => {
+                let unit = (text.char_to_utf16_cu(start) + character).min(text.len_utf16_cu());
+                text.utf16_cu_to_char(unit) - start
+            },
+            Self::Utf32 => character,
+        };
+
+        Some((column.min(len), line))
+    }
+}
+
+/// The length of `line` in chars, leaving out its line break.
+fn line_len(text: &Rope, line: usize) -> usize {
+    let slice = text.line(line);
+    let len = slice.len_chars();
+
+    let newline = match (len.checked_sub(2).map(|i| slice.char(i)), len.checked_sub(1).map(|i| slice.char(i))) {
+        (Some('\r'), Some('\n')) => 2,
+        (_, Some('\n' | '\r')) => 1,
+        _ => 0,
+    };
+
+    len - newline
+}
+
+/// A `file://` URI for `path`, made absolute first.
+pub fn path_to_uri(path: &Path) -> String {
+    let absolute = path.canonicalize()
+        .or_else(|_| std::env::current_dir().map(|dir| dir.join(path)))
+        .unwrap_or_else(|_| path.to_path_buf());
+
+    let mut uri = "file://".to_string();
+
+    for byte in absolute.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    uri
+}
+
+/// The path a `file://` URI points at.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+
+    while i < encoded.len() {
+        let decoded = match encoded[i] {
+            b'%' => {
+                encoded.get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            },
+            _ => None,
+        };
+
+        match decoded {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            },
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
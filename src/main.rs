@@ -1,91 +1,220 @@
 use std::error::Error;
 use std::fs::File;
-use std::time::Duration;
 
-use crossterm::event::{ KeyEvent, KeyCode, read, poll, Event as InputEvent };
+use crossterm::event::{ KeyEvent, KeyCode, KeyModifiers, Event as InputEvent };
 use simplelog::{WriteLogger, Config};
 
 use gof_lib::{
     application::{ Application, Event },
+    events::AppEvent,
     ui::{
         *,
         window::{ Window, WindowAlignment, WindowInfo },
     },
-    windows::*, AppState,
+    lsp::ServerConfig,
+    windows::*, AppState, Message, Mode,
 };
 
+// Indices into the window list built in `main`
+const DIR_TREE: usize = 1;
+const GREP: usize = 4;
+const DIAGNOSTICS: usize = 5;
+const TERMINAL: usize = 6;
+const BUFFER: usize = 8;
+
 fn main() -> Result<(), Box<dyn Error>> {
     // Initialize logger
     WriteLogger::init(log::LevelFilter::Debug, Config::default(), File::create("./debug.log")?)?;
 
+    let path = "./src/main.rs";
+
+    let mut state = AppState::new();
+    state.root = ".".into();
+    state.sidebar_toggle = true;
+    state.open_files = vec![ path.into() ];
+
+    // A file that can't be read is reported, rather than stopping startup
+    let buffer = match Buffer::new(WindowInfo::new().fill(), path) {
+        Ok(buffer) => buffer,
+        Err(e) => {
+            state.message = format!("{}: {}", path, e);
+            Buffer::new_empty_buffer(WindowInfo::new().fill())
+        }
+    };
+
+    let buffer = buffer.language_servers(vec![
+        ServerConfig::new("rust", "rust-analyzer").extensions(&[ "rs" ]),
+    ]);
+
+    state.file_status = buffer.file_status();
+
     let windows: Vec<Box<dyn Window<AppState>>> = vec![ 
-        Empty::new(
+        StatusLine::new(
+            WindowInfo::new()
+                .align(WindowAlignment::Bottom)
+                .fill_horizontal(1),
+        ).boxed(),
+        DirTree::new(
             WindowInfo::new()
                 .fill_vertical(32),
-            "[ DIR TREE ]".to_string(),
         ).boxed(),
         Tabs::new(
             WindowInfo::new()
                 .fill_horizontal(2),
         ).boxed(),
-        Empty::new(
+        CommandLine::new(
             WindowInfo::new()
                 .align(WindowAlignment::Bottom)
                 .fill_horizontal(3),
-            "[ MODE / COMMAND GUTTER ]".to_string(),
         ).boxed(),
-        LineNumbers::new(
+        Grep::new(
+            WindowInfo::new()
+                .align(WindowAlignment::Bottom)
+                .fill_horizontal(12),
+        ).boxed(),
+        Diagnostics::new(
             WindowInfo::new()
-                .fill_vertical(4)
+                .align(WindowAlignment::Bottom)
+                .fill_horizontal(10),
         ).boxed(),
-        Buffer::new(
+        Terminal::new(
+            WindowInfo::new()
+                .align(WindowAlignment::Bottom)
+                .fill_horizontal(14),
+        ).boxed(),
+        LineNumbers::new(
             WindowInfo::new()
-                .fill(),
-            "./src/main.rs"
-        )?.boxed()
+                .fill_vertical(6)
+        ).boxed(),
+        buffer.boxed()
     ];
-    
-    let mut state = AppState::new();
-    state.open_files = vec![ "./src/main.rs".into() ];
 
-    let app = Application::new(windows, state);
+    let mut app = Application::new(windows, state);
+    app.ui.hide_window(GREP);
+    app.ui.hide_window(DIAGNOSTICS);
+    app.ui.hide_window(TERMINAL);
+    app.ui.jobs.on_change(|jobs| Message::SetJobs(jobs.to_vec()));
 
     app.run(
-        |_| { },
-        |ui| app_loop(ui)
+        // The dir tree comes first, but typing should start in the buffer
+        |ui| {
+            ui.select_window(BUFFER);
+            ui.move_cursor_to_selected();
+        },
+        app_loop
     )
 }
 
-fn app_loop(ui: &mut UI<AppState>) -> Event {
-    if poll(Duration::from_secs_f32(1.0 / 180.0)).unwrap() {
-        match read().unwrap() {
-            InputEvent::Key(KeyEvent { code: KeyCode::Char('q'), .. }) => 
-                return Event::Exit,
+fn app_loop(ui: &mut UI<AppState>, event: AppEvent) -> Event {
+    // Background work is picked up by `Application::run` itself
+    let AppEvent::Input(input) = event else { return Event::Sleep };
+    let normal_mode = ui.state().mode == Mode::Normal;
 
-            InputEvent::Key(KeyEvent { code: KeyCode::Char('n'), .. }) => {
-                ui.state.sidebar_toggle = !ui.state.sidebar_toggle;
+    match input {
+        // Popups take every key until they close
+        InputEvent::Key(KeyEvent { code, modifiers, .. }) if ui.has_modal_popup() => {
+            let selected_file = ui.state().selected_file;
 
-                if ui.state.sidebar_toggle {
-                    ui.show_window(0);
-                } else {
-                    ui.hide_window(0);
-                }
-            },
+            ui.pass_input_to_selected(code, modifiers);
 
-            InputEvent::Key(KeyEvent { code: KeyCode::Char('R'), .. }) | 
-            InputEvent::Resize(_, _) =>
-                return Event::RecalculateUI,
+            if !ui.has_modal_popup() && ui.state().selected_file != selected_file {
+                ui.select_window(BUFFER);
+            }
+        },
 
-            InputEvent::Key(KeyEvent { code, modifiers }) => {
-                ui.pass_input_to_selected(code, modifiers);
-                ui.update_cursor_position(ui.state.cursor_position, CursorUpdateMode::RelativeToSelected);
-            },
+        InputEvent::Key(KeyEvent { code: KeyCode::Char('t'), modifiers: KeyModifiers::CONTROL, .. }) => {
+            if ui.selected_index() == TERMINAL {
+                ui.hide_window(TERMINAL);
+                ui.select_window(BUFFER);
+            } else {
+                ui.show_window(TERMINAL);
+                ui.select_window(TERMINAL);
+            }
+        },
 
-            _ => { },
-        }
+        // The shell gets every other key, Ctrl-c included
+        InputEvent::Key(KeyEvent { code, modifiers, .. }) if ui.selected_index() == TERMINAL
+            && !(code == KeyCode::Char('w') && modifiers == KeyModifiers::CONTROL) => {
+            ui.pass_input_to_selected(code, modifiers);
+        },
+
+        InputEvent::Key(KeyEvent { code: KeyCode::Char('q'), .. }) if normal_mode && ui.selected_index() == BUFFER => 
+            return Event::Exit,
+
+        InputEvent::Key(KeyEvent { code: KeyCode::Char('n'), modifiers: KeyModifiers::CONTROL, .. }) => {
+            ui.send(Message::ToggleSidebar);
+
+            if ui.state().sidebar_toggle {
+                ui.show_window(DIR_TREE);
+            } else {
+                ui.hide_window(DIR_TREE);
+            }
+        },
+
+        InputEvent::Key(KeyEvent { code: KeyCode::Char('g'), modifiers: KeyModifiers::CONTROL, .. }) => {
+            if ui.selected_index() == GREP {
+                ui.hide_window(GREP);
+                ui.select_window(BUFFER);
+            } else {
+                ui.show_window(GREP);
+                ui.select_window(GREP);
+            }
+        },
+
+        InputEvent::Key(KeyEvent { code: KeyCode::Char('d'), modifiers: KeyModifiers::CONTROL, .. }) => {
+            if ui.selected_index() == DIAGNOSTICS {
+                ui.hide_window(DIAGNOSTICS);
+                ui.select_window(BUFFER);
+            } else {
+                ui.show_window(DIAGNOSTICS);
+                ui.select_window(DIAGNOSTICS);
+            }
+        },
+
+        InputEvent::Key(KeyEvent { code: KeyCode::Char('p'), modifiers: KeyModifiers::CONTROL, .. }) => {
+            let finder = Finder::new(
+                WindowInfo::new()
+                    .centered()
+                    .bounds(100, 24)
+                    .modal(),
+                ui.state().root.clone(),
+            );
+
+            ui.open_popup(finder.boxed()).unwrap();
+        },
 
-        Event::Draw
-    } else {
-        Event::Sleep
+        InputEvent::Key(KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL, .. }) => {
+            ui.jobs.cancel_all();
+        },
+
+        InputEvent::Key(KeyEvent { code: KeyCode::Char('w'), modifiers: KeyModifiers::CONTROL, .. }) => {
+            ui.select_next_window().unwrap();
+        },
+
+        InputEvent::Key(KeyEvent { code: KeyCode::Char('R'), .. }) if normal_mode =>
+            return Event::RecalculateUI,
+
+        InputEvent::Paste(text) => ui.pass_paste_to_selected(&text),
+
+        InputEvent::Resize(_, _) =>
+            return Event::RecalculateUI,
+
+        InputEvent::Key(KeyEvent { code, modifiers, .. }) => {
+            let opened = |ui: &UI<AppState>| (ui.state().jump.as_ref().map(|j| j.id), ui.state().selected_file);
+            let before = opened(ui);
+
+            ui.pass_input_to_selected(code, modifiers);
+
+            // Whatever was opened should get the focus
+            if opened(ui) != before {
+                ui.select_window(BUFFER);
+            }
+        },
+
+        _ => return Event::Sleep,
     }
+
+    ui.move_cursor_to_selected();
+
+    Event::Draw
 }
@@ -0,0 +1,603 @@
+//! The motions and text objects operators act on, worked out over a rope.
+//! Positions are `(column, line)` in chars, like the cursor's.
+
+use std::ops::{ Range, RangeInclusive };
+
+use ropey::Rope;
+
+use crate::search::line_content;
+use crate::text;
+
+/// Something done to the text a motion or text object covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    /// Deletes, leaving the cursor where the new text would go
+    Change,
+    Yank,
+    Indent,
+    Unindent,
+}
+
+impl Operator {
+    pub fn from_key(c: char) -> Option<Self> {
+        match c {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            '>' => Some(Operator::Indent),
+            '<' => Some(Operator::Unindent),
+            _ => None,
+        }
+    }
+}
+
+/// What an operator acts on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Span {
+    /// A range of chars
+    Chars(Range<usize>),
+    /// Whole lines, line breaks and all
+    Lines(RangeInclusive<usize>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// `w`, to the start of the next word
+    WordForward,
+    /// `b`, to the start of this word or the one before
+    WordBackward,
+    /// `e`, to the end of this word or the next
+    WordEnd,
+    /// `0`
+    LineStart,
+    /// `$`
+    LineEnd,
+    /// `gg`, or the line the count names
+    FirstLine,
+    /// `G`, or the line the count names
+    LastLine,
+    /// `f`, onto the next `char` on the line
+    FindForward(char),
+    /// `F`
+    FindBackward(char),
+    /// `t`, up to the next `char` on the line
+    TillForward(char),
+    /// `T`
+    TillBackward(char),
+    /// `%`, to the bracket matching the one under or after the cursor
+    MatchingBracket,
+}
+
+impl Motion {
+    /// The motion a key stands for on its own, without a prefix.
+    pub fn from_key(c: char) -> Option<Self> {
+        match c {
+            'h' => Some(Motion::Left),
+            'l' => Some(Motion::Right),
+            'k' => Some(Motion::Up),
+            'j' => Some(Motion::Down),
+            'w' => Some(Motion::WordForward),
+            'b' => Some(Motion::WordBackward),
+            'e' => Some(Motion::WordEnd),
+            '0' => Some(Motion::LineStart),
+            '$' => Some(Motion::LineEnd),
+            'G' => Some(Motion::LastLine),
+            '%' => Some(Motion::MatchingBracket),
+            _ => None,
+        }
+    }
+
+    /// The motion `prefix` then `c` stand for, like `gg` or `fx`.
+    pub fn from_keys(prefix: char, c: char) -> Option<Self> {
+        match prefix {
+            'g' if c == 'g' => Some(Motion::FirstLine),
+            'f' => Some(Motion::FindForward(c)),
+            'F' => Some(Motion::FindBackward(c)),
+            't' => Some(Motion::TillForward(c)),
+            'T' => Some(Motion::TillBackward(c)),
+            _ => None,
+        }
+    }
+
+    /// Whether operators act on whole lines from the cursor's to the target's.
+    pub fn is_linewise(&self) -> bool {
+        matches!(self, Motion::Up | Motion::Down | Motion::FirstLine | Motion::LastLine)
+    }
+
+    /// Whether operators take the char the motion ends on too.
+    pub fn is_inclusive(&self) -> bool {
+        matches!(
+            self,
+            Motion::WordEnd | Motion::LineEnd | Motion::FindForward(_) | Motion::TillForward(_) | Motion::MatchingBracket
+        )
+    }
+
+    /// Where the motion moves the cursor, `count` times over, or `None` if it
+    /// can't, like `f` with nothing to find. `count` is only `None` when none
+    /// was typed, which matters to `gg` and `G`.
+    pub fn target(&self, content: &Rope, cursor: (usize, usize), count: Option<usize>) -> Option<(usize, usize)> {
+        // Nothing lies past the last char, and only the line break past the
+        // last char on a line
+        let last = last_line(content);
+        let end = (content.line_to_char(last) + line_len(content, last)).saturating_sub(1);
+        let target = self.target_char(content, cursor, count)?.min(end);
+
+        let (column, line) = position(content, target);
+        let len = line_len(content, line);
+
+        match self {
+            Motion::Up | Motion::Down => Some((cursor.0, line)),
+            _ => Some((column.min(len.saturating_sub(1)), line)),
+        }
+    }
+
+    /// What an operator with this motion acts on.
+    pub fn span(&self, content: &Rope, cursor: (usize, usize), count: Option<usize>) -> Option<Span> {
+        let from = char_index(content, cursor);
+        let to = self.target_char(content, cursor, count)?;
+
+        if self.is_linewise() {
+            let (first, last) = (content.char_to_line(from), content.char_to_line(to).min(last_line(content)));
+            return Some(Span::Lines(first.min(last)..=first.max(last)));
+        }
+
+        let (start, mut end) = (from.min(to), from.max(to));
+        let end_line = content.char_to_line(end);
+
+        if self.is_inclusive() && end < content.line_to_char(end_line) + line_len(content, end_line) {
+            end += 1;
+        }
+
+        // A word motion that ran over the end of a line stops there instead
+        if *self == Motion::WordForward && content.char_to_line(end) > content.char_to_line(start) {
+            let mut word_end = end;
+
+            while word_end > start && is_blank(content.char(word_end - 1)) {
+                word_end -= 1;
+            }
+
+            if word_end > start {
+                end = word_end;
+            }
+        }
+
+        Some(Span::Chars(start..end))
+    }
+
+    /// The char index the motion ends on, which can be past the end of the
+    /// line or the text, where operators stop.
+    fn target_char(&self, content: &Rope, (column, line): (usize, usize), count: Option<usize>) -> Option<usize> {
+        let times = count.unwrap_or(1).max(1);
+        let line = line.min(last_line(content));
+        let len = line_len(content, line);
+        let line_start = content.line_to_char(line);
+        let column = column.min(len);
+        let cursor = line_start + column;
+
+        let target = match self {
+            Motion::Left => line_start + column.saturating_sub(times),
+            Motion::Right => line_start + (column + times).min(len),
+            Motion::Up => content.line_to_char(line.saturating_sub(times)),
+            Motion::Down => content.line_to_char((line + times).min(last_line(content))),
+            Motion::WordForward => (0..times).fold(cursor, |i, _| next_word_start(content, i)),
+            Motion::WordBackward => (0..times).fold(cursor, |i, _| previous_word_start(content, i)),
+            Motion::WordEnd => (0..times).fold(cursor, |i, _| next_word_end(content, i)),
+            Motion::LineStart => line_start,
+            Motion::LineEnd => {
+                let line = (line + times - 1).min(last_line(content));
+                content.line_to_char(line) + line_len(content, line).saturating_sub(1)
+            },
+            Motion::FirstLine | Motion::LastLine => {
+                let line = match (self, count) {
+                    (_, Some(count)) => count.max(1) - 1,
+                    (Motion::FirstLine, None) => 0,
+                    _ => last_line(content),
+                };
+                let line = line.min(last_line(content));
+
+                content.line_to_char(line) + first_non_blank(content, line)
+            },
+            Motion::FindForward(c) | Motion::TillForward(c) => {
+                let chars: Vec<char> = line_content(content.line(line)).chars().collect();
+
+                let found = (column + 1..chars.len()).filter(|i| chars[*i] == *c).nth(times - 1)?;
+                let found = if matches!(self, Motion::TillForward(_)) { found - 1 } else { found };
+
+                line_start + found
+            },
+            Motion::FindBackward(c) | Motion::TillBackward(c) => {
+                let chars: Vec<char> = line_content(content.line(line)).chars().collect();
+
+                let found = (0..column).rev().filter(|i| chars[*i] == *c).nth(times - 1)?;
+                let found = if matches!(self, Motion::TillBackward(_)) { found + 1 } else { found };
+
+                line_start + found
+            },
+            Motion::MatchingBracket => {
+                let chars: Vec<char> = line_content(content.line(line)).chars().collect();
+                // Angle brackets are left out, as they are as often comparisons
+                let at = (column..chars.len()).find(|i| matches!(chars[*i], '(' | ')' | '[' | ']' | '{' | '}'))?;
+
+                matching_bracket(content, line_start + at)?
+            },
+        };
+
+        Some(target)
+    }
+}
+
+/// The text objects after `i` or `a`: `iw` is the word under the cursor, and
+/// `aw` that and the space after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextObject {
+    Word { around: bool },
+    /// Between a pair of brackets, or with them
+    Bracket { open: char, close: char, around: bool },
+    /// Between a pair of quotes on one line, or with them
+    Quote { quote: char, around: bool },
+    Paragraph { around: bool },
+}
+
+impl TextObject {
+    /// The object `prefix` (`i` or `a`) then `c` stand for, like `i(`.
+    pub fn from_keys(prefix: char, c: char) -> Option<Self> {
+        let around = match prefix {
+            'i' => false,
+            'a' => true,
+            _ => return None,
+        };
+
+        let object = match c {
+            'w' => TextObject::Word { around },
+            'p' => TextObject::Paragraph { around },
+            '"' | '\'' | '`' => TextObject::Quote { quote: c, around },
+            'b' => TextObject::Bracket { open: '(', close: ')', around },
+            'B' => TextObject::Bracket { open: '{', close: '}', around },
+            c => {
+                let (open, close) = bracket_pair(c)?;
+                TextObject::Bracket { open, close, around }
+            },
+        };
+
+        Some(object)
+    }
+
+    /// What the object under the cursor covers, `count` times over, or
+    /// `None` if there is none, like `i(` outside any brackets.
+    pub fn span(&self, content: &Rope, cursor: (usize, usize), count: Option<usize>) -> Option<Span> {
+        let times = count.unwrap_or(1).max(1);
+        let line = cursor.1.min(last_line(content));
+
+        match *self {
+            TextObject::Word { around } => {
+                let line_start = content.line_to_char(line);
+                let chars: Vec<char> = line_content(content.line(line)).chars().collect();
+
+                if chars.is_empty() {
+                    return None;
+                }
+
+                let len = chars.len();
+                let (mut start, mut end) = run(&chars, cursor.0.min(len - 1));
+
+                // The space after a word goes with it, or the space before it
+                // if there is none after. Space goes with the word after it.
+                if around && is_blank(chars[start]) {
+                    end = run(&chars, end.min(len - 1)).1;
+                } else if around && end < len && is_blank(chars[end]) {
+                    end = run(&chars, end).1;
+                } else if around && start > 0 && is_blank(chars[start - 1]) {
+                    start = run(&chars, start - 1).0;
+                }
+
+                for _ in 1..times {
+                    if end >= len {
+                        break;
+                    }
+
+                    let after_space = is_blank(chars[end]);
+                    end = run(&chars, end).1;
+
+                    if around && end < len && (after_space || is_blank(chars[end])) {
+                        end = run(&chars, end).1;
+                    }
+                }
+
+                Some(Span::Chars(line_start + start..line_start + end))
+            },
+            TextObject::Bracket { open, close, around } => {
+                let mut at = char_index(content, (cursor.0, line));
+                let mut pair = None;
+
+                for _ in 0..times {
+                    let start = enclosing_bracket(content, at, open, close)?;
+                    let end = matching_bracket(content, start)?;
+
+                    pair = Some((start, end));
+                    at = start.checked_sub(1)?;
+                }
+
+                let (start, end) = pair?;
+
+                match around {
+                    true => Some(Span::Chars(start..end + 1)),
+                    false => Some(Span::Chars(start + 1..end)),
+                }
+            },
+            TextObject::Quote { quote, around } => {
+                let line_start = content.line_to_char(line);
+                let chars: Vec<char> = line_content(content.line(line)).chars().collect();
+                let column = cursor.0.min(chars.len());
+
+                let quotes: Vec<usize> = (0..chars.len())
+                    .filter(|i| chars[*i] == quote && (*i == 0 || chars[*i - 1] != '\\'))
+                    .collect();
+
+                // Quotes pair up from the start of the line; outside every
+                // pair, the next one counts
+                let (start, end) = quotes.chunks_exact(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .find(|(_, end)| column <= *end)?;
+
+                if !around {
+                    return Some(Span::Chars(line_start + start + 1..line_start + end));
+                }
+
+                let (mut start, mut end) = (start, end + 1);
+
+                if end < chars.len() && is_blank(chars[end]) {
+                    end = run(&chars, end).1;
+                } else if start > 0 && is_blank(chars[start - 1]) {
+                    start = run(&chars, start - 1).0;
+                }
+
+                Some(Span::Chars(line_start + start..line_start + end))
+            },
+            TextObject::Paragraph { around } => {
+                let last = last_line(content);
+                let blank = |line: usize| line_content(content.line(line)).trim().is_empty();
+                // The run of lines around `line` that are blank, or not, like it
+                let run_start = |line: usize| (0..=line).rev().take_while(|l| blank(*l) == blank(line)).last().unwrap_or(line);
+                let run_end = |line: usize| (line..=last).take_while(|l| blank(*l) == blank(line)).last().unwrap_or(line);
+
+                let (mut start, mut end) = (run_start(line), run_end(line));
+
+                // Blank lines go with the paragraph before them, or the one
+                // after them if there is none before
+                if around && blank(line) {
+                    end = run_end((end + 1).min(last));
+                } else if around && end < last {
+                    end = run_end(end + 1);
+                } else if around && start > 0 {
+                    start = run_start(start - 1);
+                }
+
+                for _ in 1..times {
+                    if end >= last {
+                        break;
+                    }
+
+                    let after_blank = blank(end + 1);
+                    end = run_end(end + 1);
+
+                    if around && end < last && (after_blank || blank(end + 1)) {
+                        end = run_end(end + 1);
+                    }
+                }
+
+                Some(Span::Lines(start..=end))
+            },
+        }
+    }
+}
+
+/// The kinds of char a word is made of: a word is a run of one kind.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Blank,
+    Keyword,
+    Punctuation,
+}
+
+fn class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Blank
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Keyword
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+fn is_blank(c: char) -> bool {
+    class(c) == CharClass::Blank
+}
+
+/// The run of chars of the same class as the one at `at`, as `start..end`.
+fn run(chars: &[char], at: usize) -> (usize, usize) {
+    let kind = class(chars[at]);
+    let start = (0..at).rev().take_while(|i| class(chars[*i]) == kind).last().unwrap_or(at);
+    let end = (at..chars.len()).take_while(|i| class(chars[*i]) == kind).last().unwrap_or(at) + 1;
+
+    (start, end)
+}
+
+/// Whether `i` is the first char of a line with nothing on it. Empty lines
+/// count as words of their own.
+fn is_empty_line(content: &Rope, i: usize) -> bool {
+    let line = content.char_to_line(i);
+    content.line_to_char(line) == i && matches!(content.get_char(i), Some('\n' | '\r'))
+}
+
+fn next_word_start(content: &Rope, mut i: usize) -> usize {
+    let len = content.len_chars();
+
+    if i >= len {
+        return len;
+    }
+
+    let kind = class(content.char(i));
+
+    if kind != CharClass::Blank {
+        while i < len && class(content.char(i)) == kind {
+            i += 1;
+        }
+    }
+
+    while i < len && is_blank(content.char(i)) {
+        i += 1;
+
+        if content.char(i - 1) == '\n' && is_empty_line(content, i) {
+            break;
+        }
+    }
+
+    i
+}
+
+fn previous_word_start(content: &Rope, mut i: usize) -> usize {
+    if i == 0 {
+        return 0;
+    }
+
+    i -= 1;
+
+    while i > 0 && is_blank(content.char(i)) && !is_empty_line(content, i) {
+        i -= 1;
+    }
+
+    if is_blank(content.char(i)) {
+        return i;
+    }
+
+    let kind = class(content.char(i));
+
+    while i > 0 && class(content.char(i - 1)) == kind {
+        i -= 1;
+    }
+
+    i
+}
+
+fn next_word_end(content: &Rope, mut i: usize) -> usize {
+    let len = content.len_chars();
+
+    i += 1;
+
+    while i < len && is_blank(content.char(i)) {
+        i += 1;
+    }
+
+    if i >= len {
+        return len.saturating_sub(1);
+    }
+
+    let kind = class(content.char(i));
+
+    while i + 1 < len && class(content.char(i + 1)) == kind {
+        i += 1;
+    }
+
+    i
+}
+
+/// The pair a bracket belongs to, opening then closing.
+fn bracket_pair(c: char) -> Option<(char, char)> {
+    match c {
+        '(' | ')' => Some(('(', ')')),
+        '[' | ']' => Some(('[', ']')),
+        '{' | '}' => Some(('{', '}')),
+        '<' | '>' => Some(('<', '>')),
+        _ => None,
+    }
+}
+
+/// The bracket that matches the one at `at`, skipping over nested pairs.
+fn matching_bracket(content: &Rope, at: usize) -> Option<usize> {
+    let c = content.char(at);
+    let (open, close) = bracket_pair(c)?;
+    let mut depth = 0usize;
+
+    if c == open {
+        for (i, c) in content.chars_at(at).enumerate() {
+            depth = match c {
+                c if c == open => depth + 1,
+                c if c == close => depth - 1,
+                _ => depth,
+            };
+
+            if depth == 0 {
+                return Some(at + i);
+            }
+        }
+    } else {
+        let mut chars = content.chars_at(at + 1);
+
+        for i in (0..=at).rev() {
+            depth = match chars.prev()? {
+                c if c == close => depth + 1,
+                c if c == open => depth - 1,
+                _ => depth,
+            };
+
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+
+    None
+}
+
+/// The opening bracket of the innermost pair around `at`, which can be the
+/// bracket at `at` itself.
+fn enclosing_bracket(content: &Rope, at: usize, open: char, close: char) -> Option<usize> {
+    match content.get_char(at)? {
+        c if c == open => return Some(at),
+        c if c == close => return matching_bracket(content, at),
+        _ => { },
+    }
+
+    let mut depth = 0usize;
+    let mut chars = content.chars_at(at);
+
+    for i in (0..at).rev() {
+        match chars.prev()? {
+            c if c == close => depth += 1,
+            c if c == open && depth == 0 => return Some(i),
+            c if c == open => depth -= 1,
+            _ => { },
+        }
+    }
+
+    None
+}
+
+fn last_line(content: &Rope) -> usize {
+    text::line_count(content).saturating_sub(1)
+}
+
+fn line_len(content: &Rope, line: usize) -> usize {
+    line_content(content.line(line)).chars().count()
+}
+
+fn first_non_blank(content: &Rope, line: usize) -> usize {
+    content.line(line).chars().take_while(|c| *c == ' ' || *c == '\t').count()
+}
+
+/// The char at `(column, line)`, or the end of the line if it is past it.
+pub fn char_index(content: &Rope, (column, line): (usize, usize)) -> usize {
+    let line = line.min(last_line(content));
+    content.line_to_char(line) + column.min(line_len(content, line))
+}
+
+/// The `(column, line)` of the char at `i`.
+pub fn position(content: &Rope, i: usize) -> (usize, usize) {
+    let line = content.char_to_line(i);
+    (i - content.line_to_char(line), line)
+}
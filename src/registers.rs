@@ -0,0 +1,145 @@
+//! Where yanked and deleted text is kept for pasting: the unnamed register,
+//! the named ones `a` to `z`, and the system clipboard.
+
+use std::collections::BTreeMap;
+use std::io::{ self, Write };
+
+/// How text was taken, which decides how it is put back.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RegisterKind {
+    /// Part of a line, or lines from the middle of one to the middle of another
+    #[default]
+    Charwise,
+    /// Whole lines, each ending in a newline, put back above or below one
+    Linewise,
+    /// A rectangle, one line of it per line, put back at the same column on
+    /// each line from the cursor's down
+    Blockwise,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Register {
+    /// Lines always end in `\n`, whatever the file they came from uses
+    pub text: String,
+    pub kind: RegisterKind,
+}
+
+impl Register {
+    pub fn new(text: String, kind: RegisterKind) -> Self {
+        Self { text, kind }
+    }
+}
+
+/// Registers are named like vim's: `"` is the unnamed register, `a` to `z`
+/// are named registers that `A` to `Z` append to, and `+` and `*` are the
+/// system clipboard.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Registers {
+    unnamed: Register,
+    named: BTreeMap<char, Register>,
+    clipboard: Register,
+}
+
+impl Registers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_valid(name: char) -> bool {
+        matches!(name, '"' | '+' | '*') || name.is_ascii_alphabetic()
+    }
+
+    /// What a register holds, if anything. `None` is the unnamed register.
+    /// The clipboard can't be read back from the terminal, so it holds what
+    /// was last copied to it from here.
+    pub fn get(&self, name: Option<char>) -> Option<&Register> {
+        let register = match name {
+            None | Some('"') => &self.unnamed,
+            Some('+' | '*') => &self.clipboard,
+            Some(c) if c.is_ascii_alphabetic() => self.named.get(&c.to_ascii_lowercase())?,
+            Some(_) => return None,
+        };
+
+        (!register.text.is_empty()).then_some(register)
+    }
+
+    /// Fills a register, and the unnamed one with it. Uppercase names add
+    /// to the end of their lowercase register instead.
+    pub fn set(&mut self, name: Option<char>, register: Register) {
+        let stored = match name {
+            None | Some('"') => register,
+            Some('+' | '*') => {
+                self.clipboard = register.clone();
+                register
+            },
+            Some(c) if c.is_ascii_uppercase() => {
+                let named = self.named.entry(c.to_ascii_lowercase()).or_default();
+                append(named, register);
+                named.clone()
+            },
+            Some(c) if c.is_ascii_lowercase() => {
+                self.named.insert(c, register.clone());
+                register
+            },
+            Some(_) => return,
+        };
+
+        self.unnamed = stored;
+    }
+}
+
+/// Adds `register` to the end of `to`. Anything added to whole lines, or
+/// added as whole lines, goes on lines of its own.
+fn append(to: &mut Register, register: Register) {
+    if to.text.is_empty() {
+        *to = register;
+        return;
+    }
+
+    let linewise = to.kind == RegisterKind::Linewise || register.kind == RegisterKind::Linewise;
+
+    if linewise && !to.text.ends_with('\n') {
+        to.text.push('\n');
+    }
+
+    to.text.push_str(&register.text);
+
+    if linewise {
+        if !to.text.ends_with('\n') {
+            to.text.push('\n');
+        }
+
+        to.kind = RegisterKind::Linewise;
+    }
+}
+
+/// Puts `text` on the system clipboard with an OSC 52 escape sequence,
+/// which the terminal we are running in passes on, even over ssh. Not every
+/// terminal supports it, and some only do once it is turned on.
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
+/// `bytes` in standard base64, padded with `=`.
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,219 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use regex::{ Regex, RegexBuilder };
+use ropey::{ Rope, RopeSlice };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+impl SearchDirection {
+    pub fn reverse(self) -> Self {
+        match self {
+            SearchDirection::Forward => SearchDirection::Backward,
+            SearchDirection::Backward => SearchDirection::Forward,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    /// Treat the pattern as a regular expression rather than literal text.
+    pub regex: bool,
+    /// Ignore case unless the pattern contains an uppercase letter.
+    /// `\c` and `\C` in the pattern force case-insensitive and
+    /// case-sensitive matching regardless.
+    pub smart_case: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self { regex: true, smart_case: true }
+    }
+}
+
+/// A match within a single line, in chars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    pattern: String,
+    direction: SearchDirection,
+    regex: Regex,
+}
+
+impl Search {
+    pub fn new(pattern: &str, direction: SearchDirection, options: SearchOptions) -> Result<Self, regex::Error> {
+        let parsed = ParsedPattern::parse(pattern, options.regex);
+        let ignore_case = parsed.ignore_case.unwrap_or(options.smart_case && !parsed.uppercase);
+
+        let source = if options.regex {
+            parsed.source
+        } else {
+            regex::escape(&parsed.source)
+        };
+
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(ignore_case)
+            .build()?;
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            direction,
+            regex,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn direction(&self) -> SearchDirection {
+        self.direction
+    }
+
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    /// Byte ranges of every non-empty match in `line`.
+    pub fn find_in_str<'a>(&'a self, line: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.regex.find_iter(line)
+            .map(|m| m.range())
+            .filter(|r| !r.is_empty())
+    }
+
+    /// Every match on `line` of `text`.
+    pub fn matches_in_line(&self, text: &Rope, line: usize) -> Vec<SearchMatch> {
+        let slice = text.line(line);
+        let content = line_content(slice);
+
+        self.find_in_str(&content)
+            .map(|range| SearchMatch {
+                line,
+                start: content[..range.start].chars().count(),
+                end: content[..range.end].chars().count(),
+            })
+            .collect()
+    }
+
+    /// Every match in `text`, searched one line at a time so only lines that
+    /// straddle a rope chunk boundary are copied.
+    pub fn find_all(&self, text: &Rope) -> Vec<SearchMatch> {
+        (0..text.len_lines())
+            .flat_map(|line| self.matches_in_line(text, line))
+            .collect()
+    }
+
+    /// The first match after (or before) `from`, a `(column, line)` pair,
+    /// wrapping around the ends of `text`.
+    pub fn find_next(&self, text: &Rope, from: (usize, usize), direction: SearchDirection) -> Option<SearchMatch> {
+        let (column, line) = from;
+        let line_count = text.len_lines();
+        let line = line.min(line_count.saturating_sub(1));
+
+        match direction {
+            SearchDirection::Forward => {
+                let after_cursor = self.matches_in_line(text, line).into_iter()
+                    .find(|m| m.start > column);
+
+                after_cursor.or_else(|| {
+                    (line + 1..line_count).chain(0..=line)
+                        .find_map(|l| self.matches_in_line(text, l).into_iter().next())
+                })
+            },
+            SearchDirection::Backward => {
+                let before_cursor = self.matches_in_line(text, line).into_iter()
+                    .rev()
+                    .find(|m| m.start < column);
+
+                before_cursor.or_else(|| {
+                    (0..line).rev().chain((line..line_count).rev())
+                        .find_map(|l| self.matches_in_line(text, l).into_iter().last())
+                })
+            },
+        }
+    }
+}
+
+/// A pattern with its `\c` and `\C` flags taken out.
+struct ParsedPattern {
+    source: String,
+    /// What the flags ask for, `\c` winning over `\C`
+    ignore_case: Option<bool>,
+    /// Whether the text to find has an uppercase letter. In a regex, escapes
+    /// like `\S` or `\p{Greek}` aren't text.
+    uppercase: bool,
+}
+
+impl ParsedPattern {
+    fn parse(pattern: &str, regex: bool) -> Self {
+        let mut source = String::with_capacity(pattern.len());
+        let (mut insensitive, mut sensitive, mut uppercase) = (false, false, false);
+        let mut chars = pattern.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                uppercase |= c.is_uppercase();
+                source.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('c') => insensitive = true,
+                Some('C') => sensitive = true,
+                Some(next) => {
+                    source.push(c);
+                    source.push(next);
+                    uppercase |= !regex && next.is_uppercase();
+
+                    if regex && chars.peek() == Some(&'{') {
+                        for c in chars.by_ref() {
+                            source.push(c);
+
+                            if c == '}' {
+                                break;
+                            }
+                        }
+                    }
+                },
+                None => source.push(c),
+            }
+        }
+
+        let ignore_case = match (insensitive, sensitive) {
+            (true, _) => Some(true),
+            (false, true) => Some(false),
+            (false, false) => None,
+        };
+
+        Self { source, ignore_case, uppercase }
+    }
+}
+
+/// The text of `line` without its line ending, borrowed if the line sits in a
+/// single rope chunk.
+pub fn line_content(line: RopeSlice) -> Cow<str> {
+    let text = match line.as_str() {
+        Some(s) => Cow::Borrowed(s),
+        None => Cow::Owned(line.to_string()),
+    };
+
+    let trimmed = text.trim_end_matches(['\n', '\r']).len();
+
+    match text {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[..trimmed]),
+        Cow::Owned(mut s) => {
+            s.truncate(trimmed);
+            Cow::Owned(s)
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::ops::{ Range, RangeInclusive };
+
+use ropey::Rope;
+
+use crate::search::line_content;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionKind {
+    /// Everything from one character to another
+    Char,
+    /// Every line from one to another, whole
+    Line,
+    /// The rectangle with the two ends in opposite corners
+    Block,
+}
+
+/// A visual selection, from where it was started to wherever the cursor is
+/// now. Either end can come first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub kind: SelectionKind,
+    pub anchor: (usize, usize),
+}
+
+impl Selection {
+    pub fn new(kind: SelectionKind, anchor: (usize, usize)) -> Self {
+        Self { kind, anchor }
+    }
+
+    /// The end that comes first in the text, then the other one.
+    pub fn ends(&self, cursor: (usize, usize)) -> ((usize, usize), (usize, usize)) {
+        let (anchor, cursor) = ((self.anchor.1, self.anchor.0), (cursor.1, cursor.0));
+        let (start, end) = if anchor <= cursor { (anchor, cursor) } else { (cursor, anchor) };
+
+        ((start.1, start.0), (end.1, end.0))
+    }
+
+    pub fn lines(&self, cursor: (usize, usize)) -> RangeInclusive<usize> {
+        self.anchor.1.min(cursor.1)..=self.anchor.1.max(cursor.1)
+    }
+
+    /// The columns a block covers, whatever lines it is on.
+    fn block_columns(&self, cursor: (usize, usize)) -> Range<usize> {
+        self.anchor.0.min(cursor.0)..self.anchor.0.max(cursor.0) + 1
+    }
+
+    /// The columns selected on `line`, in chars. They run one past the end
+    /// of the line when its line break is selected too.
+    pub fn columns(&self, content: &Rope, cursor: (usize, usize), line: usize) -> Option<Range<usize>> {
+        if !self.lines(cursor).contains(&line) || line >= content.len_lines() {
+            return None;
+        }
+
+        let len = line_content(content.line(line)).chars().count();
+        let (start, end) = self.ends(cursor);
+
+        let columns = match self.kind {
+            SelectionKind::Char => {
+                let first = if line == start.1 { start.0.min(len) } else { 0 };
+                let last = if line == end.1 { end.0.min(len) } else { len };
+
+                first..last + 1
+            },
+            SelectionKind::Line => 0..len + 1,
+            SelectionKind::Block => {
+                let columns = self.block_columns(cursor);
+                columns.start.min(len)..columns.end.min(len)
+            },
+        };
+
+        Some(columns)
+    }
+
+    /// The chars of `content` the selection covers: one range for a run of
+    /// text or lines, and one per line for a block, top to bottom. Line
+    /// breaks are only taken with the lines they end.
+    pub fn ranges(&self, content: &Rope, cursor: (usize, usize)) -> Vec<Range<usize>> {
+        let last = content.len_lines() - 1;
+        let lines = self.lines(cursor);
+        let lines = (*lines.start()).min(last)..=(*lines.end()).min(last);
+        let char_range = |line: usize, columns: Range<usize>| {
+            let start = content.line_to_char(line);
+            let len = content.line(line).len_chars();
+
+            start + columns.start.min(len)..start + columns.end.min(len)
+        };
+
+        match self.kind {
+            SelectionKind::Char | SelectionKind::Line => {
+                let first = self.columns(content, cursor, *lines.start()).unwrap_or_default();
+                let last = self.columns(content, cursor, *lines.end()).unwrap_or_default();
+
+                // The last line's break is all of it past its text
+                let last = match last.end > line_content(content.line(*lines.end())).chars().count() {
+                    true => last.start..usize::MAX,
+                    false => last,
+                };
+
+                let range = char_range(*lines.start(), first).start..char_range(*lines.end(), last).end;
+                vec![ range ]
+            },
+            SelectionKind::Block => lines
+                .map(|line| char_range(line, self.columns(content, cursor, line).unwrap_or_default()))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::ops::Range;
+
+use regex::Regex;
+use ropey::Rope;
+
+use crate::history::Transaction;
+use crate::search::line_content;
+
+/// A match waiting to be replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingReplacement {
+    pub line: usize,
+    /// Byte range within the line
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl PendingReplacement {
+    /// The match's `(start, end)` columns in chars, clamped to the line in
+    /// case `text` changed since it was found.
+    pub fn columns(&self, text: &Rope) -> (usize, usize) {
+        let Some(line) = text.get_line(self.line) else { return (0, 0) };
+        let content = line_content(line);
+        let column = |end: usize| {
+            let end = end.min(content.len());
+            let end = (0..=end).rev().find(|i| content.is_char_boundary(*i)).unwrap_or(0);
+
+            content[..end].chars().count()
+        };
+
+        (column(self.range.start), column(self.range.end))
+    }
+}
+
+/// Walks the matches of a `:s` command over a range of lines, one at a time,
+/// so each can be confirmed or skipped. Every replacement goes into a single
+/// transaction.
+#[derive(Debug)]
+pub struct Substitution {
+    regex: Regex,
+    template: String,
+    global: bool,
+    line: usize,
+    end_line: usize,
+    // Byte offset within `line` to search from
+    offset: usize,
+    // Where the last non-empty match on `line` ended, as a byte offset
+    match_end: Option<usize>,
+    replacements: usize,
+    lines_changed: usize,
+    last_changed_line: Option<usize>,
+    transaction: Transaction,
+}
+
+impl Substitution {
+    /// `template` uses `regex`'s expansion syntax. `lines` is inclusive.
+    pub fn new(regex: Regex, template: String, global: bool, lines: (usize, usize), cursor: (usize, usize)) -> Self {
+        Self {
+            regex,
+            template,
+            global,
+            line: lines.0,
+            end_line: lines.1,
+            offset: 0,
+            match_end: None,
+            replacements: 0,
+            lines_changed: 0,
+            last_changed_line: None,
+            transaction: Transaction::new(cursor),
+        }
+    }
+
+    /// Finds the next match at or after the current position.
+    pub fn next_match(&mut self, text: &Rope) -> Option<PendingReplacement> {
+        while self.line <= self.end_line && self.line < text.len_lines() {
+            let content = line_content(text.line(self.line));
+
+            if self.offset <= content.len() {
+                if let Some(captures) = self.regex.captures_at(&content, self.offset) {
+                    let m = captures.get(0).unwrap();
+
+                    // An empty match right where the last one ended isn't a
+                    // match of its own, as in `s/x*/-/g` on `axxb`
+                    if m.is_empty() && self.match_end == Some(m.start()) {
+                        self.match_end = None;
+
+                        match content[m.start()..].chars().next() {
+                            Some(c) => self.offset = m.start() + c.len_utf8(),
+                            None => self.next_line(),
+                        }
+
+                        continue;
+                    }
+
+                    let mut replacement = String::new();
+                    captures.expand(&self.template, &mut replacement);
+
+                    return Some(PendingReplacement {
+                        line: self.line,
+                        range: m.range(),
+                        replacement,
+                    });
+                }
+            }
+
+            self.next_line();
+        }
+
+        None
+    }
+
+    fn next_line(&mut self) {
+        self.line += 1;
+        self.offset = 0;
+        self.match_end = None;
+    }
+
+    /// Moves past `pending` without replacing it.
+    pub fn skip(&mut self, text: &Rope, pending: &PendingReplacement) {
+        self.advance_past(text, pending.range.end, pending.range.is_empty());
+    }
+
+    fn advance_past(&mut self, text: &Rope, end: usize, empty_match: bool) {
+        if !self.global {
+            self.next_line();
+            return;
+        }
+
+        self.offset = end;
+        self.match_end = (!empty_match).then_some(end);
+
+        // Step over a char after an empty match so it isn't found again
+        if empty_match {
+            let content = line_content(text.line(self.line));
+
+            match content[end..].chars().next() {
+                Some(c) => self.offset += c.len_utf8(),
+                None => self.next_line(),
+            }
+        }
+    }
+
+    /// Replaces `pending`, which must be the match last returned by
+    /// `next_match`.
+    pub fn replace(&mut self, text: &mut Rope, pending: &PendingReplacement) {
+        let line_start = text.line_to_char(pending.line);
+        let (start, end) = pending.columns(text);
+
+        self.transaction.replace(text, line_start + start..line_start + end, &pending.replacement);
+        self.replacements += 1;
+
+        if self.last_changed_line != Some(pending.line) {
+            self.lines_changed += 1;
+        }
+
+        // Newlines in the replacement push the rest of the range down
+        let new_lines = pending.replacement.matches('\n').count();
+        let end = match pending.replacement.rfind('\n') {
+            Some(i) => pending.replacement.len() - i - 1,
+            None => pending.range.start + pending.replacement.len(),
+        };
+
+        self.line += new_lines;
+        self.end_line += new_lines;
+        self.last_changed_line = Some(self.line);
+
+        self.transaction.set_cursor_after((start, pending.line));
+        self.advance_past(text, end, pending.range.is_empty());
+    }
+
+    /// Replaces every remaining match without asking.
+    pub fn replace_all(&mut self, text: &mut Rope) {
+        while let Some(pending) = self.next_match(text) {
+            self.replace(text, &pending);
+        }
+    }
+
+    pub fn replacements(&self) -> usize {
+        self.replacements
+    }
+
+    pub fn lines_changed(&self) -> usize {
+        self.lines_changed
+    }
+
+    /// Where to leave the cursor: on the last replacement, if there was one.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        (self.replacements > 0).then(|| self.transaction.cursor_after())
+    }
+
+    pub fn finish(self) -> Transaction {
+        self.transaction
+    }
+}
@@ -0,0 +1,237 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use ropey::Rope;
+
+/// The number of lines in `text`, not counting the empty line ropey reports
+/// after a trailing line break.
+pub fn line_count(text: &Rope) -> usize {
+    let lines = text.len_lines();
+
+    if lines > 1 && text.line(lines - 1).len_chars() == 0 {
+        lines - 1
+    } else {
+        lines
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+    /// Both, which are kept as they are
+    Mixed,
+}
+
+impl LineEnding {
+    /// Works out the line ending of `text` from all of its line breaks.
+    pub fn detect(text: &Rope) -> Self {
+        let (mut lf, mut crlf) = (0, 0);
+
+        for line in text.lines() {
+            let len = line.len_chars();
+
+            if len == 0 || line.char(len - 1) != '\n' {
+                continue;
+            }
+
+            if len >= 2 && line.char(len - 2) == '\r' {
+                crlf += 1;
+            } else {
+                lf += 1;
+            }
+        }
+
+        match (lf, crlf) {
+            (_, 0) => LineEnding::Lf,
+            (0, _) => LineEnding::Crlf,
+            _ => LineEnding::Mixed,
+        }
+    }
+
+    /// `text` with every line break made this kind. Mixed line endings leave
+    /// `text` as it is.
+    pub fn normalize(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.replace("\r\n", "\n"),
+            LineEnding::Crlf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+            LineEnding::Mixed => text.to_string(),
+        }
+    }
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineEnding::Lf => write!(f, "LF"),
+            LineEnding::Crlf => write!(f, "CRLF"),
+            LineEnding::Mixed => write!(f, "mixed"),
+        }
+    }
+}
+
+const UTF8_BOM: &[u8] = &[ 0xEF, 0xBB, 0xBF ];
+const UTF16LE_BOM: &[u8] = &[ 0xFF, 0xFE ];
+const UTF16BE_BOM: &[u8] = &[ 0xFE, 0xFF ];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    /// Works out how `bytes` are encoded. UTF-16 is only recognized by its
+    /// byte order mark, and anything that isn't valid UTF-8 is taken to be
+    /// Latin-1, which every byte is.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(UTF8_BOM) {
+            Encoding::Utf8Bom
+        } else if bytes.starts_with(UTF16LE_BOM) {
+            Encoding::Utf16Le
+        } else if bytes.starts_with(UTF16BE_BOM) {
+            Encoding::Utf16Be
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Encoding::Utf8
+        } else {
+            Encoding::Latin1
+        }
+    }
+
+    /// The byte order mark files in this encoding start with, if any.
+    pub fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8Bom => UTF8_BOM,
+            Encoding::Utf16Le => UTF16LE_BOM,
+            Encoding::Utf16Be => UTF16BE_BOM,
+            Encoding::Utf8 | Encoding::Latin1 => &[],
+        }
+    }
+
+    /// Decodes `bytes`, skipping the byte order mark if there is one.
+    pub fn decode(self, bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+        Decoder::new(self).decode(bytes, true)
+    }
+
+    /// Encodes `text`, without a byte order mark. Fails if `text` has chars
+    /// the encoding can't represent.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut bytes = Vec::with_capacity(text.len());
+
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => bytes.extend_from_slice(text.as_bytes()),
+            Encoding::Utf16Le => bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            Encoding::Utf16Be => bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+            Encoding::Latin1 => {
+                for c in text.chars() {
+                    let byte = u8::try_from(c).map_err(|_| format!("{:?} can't be written as {}", c, self))?;
+                    bytes.push(byte);
+                }
+            },
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Decodes text that arrives a piece at a time, as it's read. A char split
+/// between two pieces is decoded once the rest of it arrives.
+#[derive(Debug)]
+pub struct Decoder {
+    encoding: Encoding,
+    // Bytes from the end of the last piece that didn't make a whole char
+    pending: Vec<u8>,
+    started: bool,
+}
+
+impl Decoder {
+    pub fn new(encoding: Encoding) -> Self {
+        Self { encoding, pending: Vec::new(), started: false }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Decodes the next piece, skipping the byte order mark if it's the
+    /// first. `last` says nothing comes after `bytes`, so an unfinished char
+    /// at the end is an error rather than left for the next call.
+    pub fn decode(&mut self, bytes: &[u8], last: bool) -> Result<String, Box<dyn Error>> {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+
+        let mut input = input.as_slice();
+
+        if !self.started {
+            let bom = self.encoding.bom();
+
+            if input.len() < bom.len() && !last {
+                self.pending = input.to_vec();
+                return Ok(String::new());
+            }
+
+            input = input.strip_prefix(bom).unwrap_or(input);
+            self.started = true;
+        }
+
+        match self.encoding {
+            Encoding::Utf8 | Encoding::Utf8Bom => match std::str::from_utf8(input) {
+                Ok(text) => Ok(text.to_string()),
+                // The last char is cut short, rather than invalid
+                Err(e) if e.error_len().is_none() && !last => {
+                    let (valid, rest) = input.split_at(e.valid_up_to());
+                    self.pending = rest.to_vec();
+
+                    Ok(std::str::from_utf8(valid)?.to_string())
+                },
+                Err(e) => Err(e.into()),
+            },
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let (whole, rest) = input.split_at(input.len() / 2 * 2);
+
+                if last && !rest.is_empty() {
+                    return Err(format!("Invalid {}: odd number of bytes", self.encoding).into());
+                }
+
+                let mut units: Vec<u16> = whole.chunks_exact(2)
+                    .map(|pair| match self.encoding {
+                        Encoding::Utf16Le => u16::from_le_bytes([ pair[0], pair[1] ]),
+                        _ => u16::from_be_bytes([ pair[0], pair[1] ]),
+                    })
+                    .collect();
+
+                // A leading surrogate waits for the one after it
+                let split = !last && units.last().is_some_and(|u| (0xD800..0xDC00).contains(u));
+
+                if split {
+                    units.pop();
+                    self.pending = input[whole.len() - 2..].to_vec();
+                } else {
+                    self.pending = rest.to_vec();
+                }
+
+                char::decode_utf16(units)
+                    .collect::<Result<String, _>>()
+                    .map_err(|e| format!("Invalid {}: {}", self.encoding, e).into())
+            },
+            Encoding::Latin1 => Ok(input.iter().map(|&b| b as char).collect()),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "utf-8"),
+            Encoding::Utf8Bom => write!(f, "utf-8-bom"),
+            Encoding::Utf16Le => write!(f, "utf-16le"),
+            Encoding::Utf16Be => write!(f, "utf-16be"),
+            Encoding::Latin1 => write!(f, "latin-1"),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 use std::error::Error;
 use std::io::Write;
 
@@ -13,6 +13,26 @@ use crossterm::{
 
 const BORDER_CHARS_LIGHT: [&str; 12] = [ "─", "│", "┌", "┐", "└", "┘", "├", "┤", "┬", "┴", "┼", " " ];
 const BORDER_CHARS_HEAVY: [&str; 12] = [ "━", "┃", "┏", "┓", "┗", "┛", "┣", "┫", "┳", "┻", "╋", " " ];
+const BORDER_CHARS_ROUNDED: [&str; 12] = [ "─", "│", "╭", "╮", "╰", "╯", "├", "┤", "┬", "┴", "┼", " " ];
+const BORDER_CHARS_DOUBLE: [&str; 12] = [ "═", "║", "╔", "╗", "╚", "╝", "╠", "╣", "╦", "╩", "╬", " " ];
+const BORDER_CHARS_ASCII: [&str; 12] = [ "-", "|", "+", "+", "+", "+", "+", "+", "+", "+", "+", " " ];
+
+// Mixed heavy/light glyphs, indexed by which of the glyph's arms are heavy.
+// The bit order of each index is noted next to the table.
+const MIXED_HORIZONTAL: [&str; 4] = [ "─", "╼", "╾", "━" ]; // right, left
+const MIXED_VERTICAL: [&str; 4] = [ "│", "╽", "╿", "┃" ]; // down, up
+const MIXED_DOWN_RIGHT: [&str; 4] = [ "┌", "┍", "┎", "┏" ]; // right, down
+const MIXED_DOWN_LEFT: [&str; 4] = [ "┐", "┑", "┒", "┓" ]; // left, down
+const MIXED_UP_RIGHT: [&str; 4] = [ "└", "┕", "┖", "┗" ]; // right, up
+const MIXED_UP_LEFT: [&str; 4] = [ "┘", "┙", "┚", "┛" ]; // left, up
+const MIXED_T_RIGHT: [&str; 8] = [ "├", "┟", "┝", "┢", "┞", "┠", "┡", "┣" ]; // down, right, up
+const MIXED_T_LEFT: [&str; 8] = [ "┤", "┧", "┥", "┪", "┦", "┨", "┩", "┫" ]; // down, left, up
+const MIXED_T_DOWN: [&str; 8] = [ "┬", "┭", "┮", "┯", "┰", "┱", "┲", "┳" ]; // left, right, down
+const MIXED_T_UP: [&str; 8] = [ "┴", "┵", "┶", "┷", "┸", "┹", "┺", "┻" ]; // left, right, up
+const MIXED_JUNCTION: [&str; 16] = [
+    "┼", "┽", "╁", "╅", "┾", "┿", "╆", "╈",
+    "╀", "╃", "╂", "╉", "╄", "╇", "╊", "╋",
+]; // left, down, right, up
 
 #[derive(Clone, Copy)]
 enum BorderDirection {
@@ -27,12 +47,108 @@ enum BorderDirection {
     THorizontalDown = 8,
     THorizontalUp = 9,
     Junction = 10,
-    Null = 13
+    Null = 11
+}
+
+impl BorderDirection {
+    // 0 = Top, 1 = Right, 2 = Down, 3 = Left
+    fn arms(self) -> [bool; 4] {
+        match self {
+            BorderDirection::Horizontal => [false, true, false, true],
+            BorderDirection::Vertical => [true, false, true, false],
+            BorderDirection::CornerDownRight => [false, true, true, false],
+            BorderDirection::CornerDownLeft => [false, false, true, true],
+            BorderDirection::CornerUpRight => [true, true, false, false],
+            BorderDirection::CornerUpLeft => [true, false, false, true],
+            BorderDirection::TVerticalRight => [true, true, true, false],
+            BorderDirection::TVerticalLeft => [true, false, true, true],
+            BorderDirection::THorizontalDown => [false, true, true, true],
+            BorderDirection::THorizontalUp => [true, true, false, true],
+            BorderDirection::Junction => [true, true, true, true],
+            BorderDirection::Null => [false, false, false, false],
+        }
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BorderStyle {
-    Light, 
+    Light,
     Heavy,
+    Rounded,
+    Double,
+    Ascii,
+}
+
+impl BorderStyle {
+    /// Returns `self`, or `BorderStyle::Ascii` if the terminal can't display
+    /// box-drawing characters.
+    pub fn supported(self) -> Self {
+        if terminal_supports_utf8() {
+            self
+        } else {
+            BorderStyle::Ascii
+        }
+    }
+
+    fn chars(self) -> &'static [&'static str; 12] {
+        match self {
+            BorderStyle::Light => &BORDER_CHARS_LIGHT,
+            BorderStyle::Heavy => &BORDER_CHARS_HEAVY,
+            BorderStyle::Rounded => &BORDER_CHARS_ROUNDED,
+            BorderStyle::Double => &BORDER_CHARS_DOUBLE,
+            BorderStyle::Ascii => &BORDER_CHARS_ASCII,
+        }
+    }
+}
+
+impl Default for BorderStyle {
+    fn default() -> Self {
+        BorderStyle::Light.supported()
+    }
+}
+
+/// Checks the locale environment variables, in the order libc resolves them,
+/// for a UTF-8 codeset.
+pub fn terminal_supports_utf8() -> bool {
+    if cfg!(windows) {
+        return true;
+    }
+
+    for var in ["LC_ALL", "LC_CTYPE", "LANG"] {
+        if let Ok(value) = std::env::var(var) {
+            if !value.is_empty() {
+                let value = value.to_lowercase();
+                return value.contains("utf-8") || value.contains("utf8");
+            }
+        }
+    }
+
+    false
+}
+
+/// Picks the glyph for a cell given the weight of each of its arms
+/// (`None` = no arm, `Some(false)` = light, `Some(true)` = heavy).
+///
+/// Order is Top, Right, Down, Left.
+fn mixed_glyph(arms: [Option<bool>; 4]) -> &'static str {
+    let heavy = |arm: Option<bool>| arm.unwrap_or(false) as usize;
+    let [up, right, down, left] = arms;
+    let (hu, hr, hd, hl) = (heavy(up), heavy(right), heavy(down), heavy(left));
+
+    match arms.map(|a| a.is_some()) {
+        [false, true, false, true] => MIXED_HORIZONTAL[hr + 2 * hl],
+        [true, false, true, false] => MIXED_VERTICAL[hd + 2 * hu],
+        [false, true, true, false] => MIXED_DOWN_RIGHT[hr + 2 * hd],
+        [false, false, true, true] => MIXED_DOWN_LEFT[hl + 2 * hd],
+        [true, true, false, false] => MIXED_UP_RIGHT[hr + 2 * hu],
+        [true, false, false, true] => MIXED_UP_LEFT[hl + 2 * hu],
+        [true, true, true, false] => MIXED_T_RIGHT[hd + 2 * hr + 4 * hu],
+        [true, false, true, true] => MIXED_T_LEFT[hd + 2 * hl + 4 * hu],
+        [false, true, true, true] => MIXED_T_DOWN[hl + 2 * hr + 4 * hd],
+        [true, true, false, true] => MIXED_T_UP[hl + 2 * hr + 4 * hu],
+        [true, true, true, true] => MIXED_JUNCTION[hl + 2 * hd + 4 * hr + 8 * hu],
+        _ => " ",
+    }
 }
 
 #[derive(Hash, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
@@ -44,40 +160,86 @@ impl From<(u16, u16)> for Point {
     }
 }
 
+impl Point {
+    // 0 = Top, 1 = Right, 2 = Down, 3 = Left
+    fn neighbor(self, arm: usize) -> Option<Point> {
+        let Point(x, y) = self;
+
+        match arm {
+            0 => y.checked_sub(1).map(|y| Point(x, y)),
+            1 => x.checked_add(1).map(|x| Point(x, y)),
+            2 => y.checked_add(1).map(|y| Point(x, y)),
+            _ => x.checked_sub(1).map(|x| Point(x, y)),
+        }
+    }
+}
+
+/// The cells making up the borders of every window, each stored once along
+/// with the glyph direction it was last computed to have.
+#[derive(Default)]
 pub struct Border {
-    points: Vec<Point>,
-    directions: HashMap<Point, BorderDirection>,
+    cells: HashMap<Point, BorderDirection>,
+    emphasized: HashSet<Point>,
 }
 
 impl Border {
     pub fn new() -> Self {
-        Self { points: Vec::new(), directions: HashMap::new() }
+        Self::default()
     }
 
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Adds `other` to the border. Only the new cells and their immediate
+    /// neighbours have their directions recomputed.
     pub fn append(&mut self, other: Vec<(u16, u16)>) {
+        let mut dirty = HashSet::new();
+
         for o in other {
-            self.points.push(o.into());
-            self.directions.insert(o.into(), BorderDirection::Null);
+            let point = Point::from(o);
+
+            if let Entry::Vacant(cell) = self.cells.entry(point) {
+                cell.insert(BorderDirection::Null);
+                dirty.insert(point);
+
+                dirty.extend(
+                    (0..4)
+                        .filter_map(|arm| point.neighbor(arm))
+                        .filter(|n| self.cells.contains_key(n))
+                );
+            }
         }
 
-        self.recalculate_directions();
+        for point in dirty {
+            let dir = self.calculate_direction(point);
+            self.cells.insert(point, dir);
+        }
     }
 
-    pub fn recalculate_directions(&mut self) {
-        for point in &self.points {
-            self.directions.insert(*point, self.calculate_direction(*point));
-        }
+    /// Marks `points` to be drawn heavy when using `BorderStyle::Light`.
+    /// Replaces any previously emphasized points.
+    pub fn set_emphasis(&mut self, points: Vec<(u16, u16)>) {
+        self.emphasized = points.into_iter()
+            .map(Point::from)
+            .filter(|p| self.cells.contains_key(p))
+            .collect();
     }
 
     fn calculate_direction(&self, point: Point) -> BorderDirection {
-        let Point(x, y) = point;
+        let has_neighbor = |arm| point.neighbor(arm)
+            .is_some_and(|n| self.cells.contains_key(&n));
 
         // 0 = Top, 1 = Right, 2 = Down, 3 = Left
         let neighbors: [bool; 4] = [
-            y != 0 && self.directions.contains_key(&Point(x, y - 1)),
-            self.directions.contains_key(&Point(x + 1, y)),
-            self.directions.contains_key(&Point(x, y + 1)),
-            x != 0 && self.directions.contains_key(&Point(x - 1, y)),
+            has_neighbor(0),
+            has_neighbor(1),
+            has_neighbor(2),
+            has_neighbor(3),
         ];
 
         match neighbors {
@@ -96,25 +258,71 @@ impl Border {
         }
     }
 
+    fn glyph(&self, point: Point, dir: BorderDirection, style: BorderStyle) -> &'static str {
+        if style != BorderStyle::Light || !self.emphasized.contains(&point) {
+            return style.chars()[dir as usize];
+        }
+
+        // An arm is heavy if the cell it leads to is emphasized too. Arms that
+        // lead nowhere (the ends of a line) take the weight of the cell itself.
+        let mut arms = [None; 4];
+        for (arm, present) in dir.arms().into_iter().enumerate() {
+            if present {
+                let heavy = match point.neighbor(arm) {
+                    Some(n) if self.cells.contains_key(&n) => self.emphasized.contains(&n),
+                    _ => true,
+                };
+
+                arms[arm] = Some(heavy);
+            }
+        }
+
+        mixed_glyph(arms)
+    }
+
     pub fn draw<T: Write>(&self, queue: &mut T, style: BorderStyle, color: ContentStyle) -> Result<(), Box<dyn Error>> {
-        for point in &self.points {
-            let dir = self.directions[&point];
-            let Point(x, y) = point;
-
-            let c = match style {
-                BorderStyle::Light => BORDER_CHARS_LIGHT[dir as usize],
-                BorderStyle::Heavy => BORDER_CHARS_HEAVY[dir as usize],
-            };
-
-            queue!(
-                queue, 
-                MoveTo(*x, *y),
-                PrintStyledContent(
-                    color.apply(c)
-                )
-            )?;
+        for (point, dir) in &self.cells {
+            self.draw_cell(queue, *point, *dir, style, color)?;
         }
 
         Ok(())
     }
+
+    /// Draws only the cells at `points`, skipping any that aren't part of
+    /// the border.
+    pub fn draw_points<T: Write>(&self, queue: &mut T, points: &[(u16, u16)], style: BorderStyle, color: ContentStyle)
+    -> Result<(), Box<dyn Error>> {
+        for point in points {
+            let point = Point::from(*point);
+
+            if let Some(dir) = self.cells.get(&point) {
+                self.draw_cell(queue, point, *dir, style, color)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The glyph drawn at `point` in `style`, if it's part of the border.
+    pub fn glyph_at(&self, point: (u16, u16), style: BorderStyle) -> Option<&'static str> {
+        let point = Point::from(point);
+
+        self.cells.get(&point).map(|dir| self.glyph(point, *dir, style))
+    }
+
+    fn draw_cell<T: Write>(&self, queue: &mut T, point: Point, dir: BorderDirection, style: BorderStyle, color: ContentStyle)
+    -> Result<(), Box<dyn Error>> {
+        let Point(x, y) = point;
+        let c = self.glyph(point, dir, style);
+
+        queue!(
+            queue,
+            MoveTo(x, y),
+            PrintStyledContent(
+                color.apply(c)
+            )
+        )?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,46 @@
+use std::fmt::Debug;
+
+/// Application state that only changes by applying messages to it, so every
+/// change can be passed on to the windows that care about it. Jobs send
+/// messages from other threads, so they have to be `Send`.
+pub trait State: 'static {
+    type Message: Debug + Send;
+
+    fn apply(&mut self, message: &Self::Message);
+
+    /// The message that shows `text` to the user, used for errors windows
+    /// return from handling input.
+    fn show_message(text: String) -> Self::Message;
+}
+
+/// Collects the messages a window sends while handling input or another
+/// message. `UI` applies them to the state once the window is done.
+#[derive(Debug)]
+pub struct Bus<M> {
+    messages: Vec<M>,
+}
+
+impl<M> Bus<M> {
+    pub fn new() -> Self {
+        Self { messages: Vec::new() }
+    }
+
+    pub fn send(&mut self, message: M) {
+        self.messages.push(message);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Every message sent since the last call, oldest first.
+    pub fn take(&mut self) -> Vec<M> {
+        std::mem::take(&mut self.messages)
+    }
+}
+
+impl<M> Default for Bus<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
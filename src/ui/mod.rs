@@ -1,6 +1,8 @@
+#[allow(clippy::module_inception)]
 mod ui;
 pub use ui::*;
 
 pub mod border;
+pub mod bus;
 pub mod rect;
 pub mod window;
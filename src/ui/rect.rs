@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Rect {
     pub x: u16,
     pub y: u16,
@@ -20,17 +20,24 @@ impl Rect {
             y >= self.y && y <= self.y + self.height
     }
 
+    /// Every cell on the outline of the rect, each listed once.
     pub fn border_points(&self) -> Vec<(u16, u16)>{
         let mut border_points = Vec::new();
 
-        for x in self.x..self.width + self.x {
+        for x in self.x..=self.width + self.x {
             border_points.push((x, self.y));
-            border_points.push((x, self.y + self.height));
+
+            if self.height > 0 {
+                border_points.push((x, self.y + self.height));
+            }
         }
 
-        for y in self.y..=self.height + self.y {
+        for y in self.y + 1..self.height + self.y {
             border_points.push((self.x, y));
-            border_points.push((self.width + self.x, y));
+
+            if self.width > 0 {
+                border_points.push((self.width + self.x, y));
+            }
         }
 
         border_points
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, usize};
+use std::collections::{ HashSet, VecDeque };
 use std::error::Error;
 use std::io::Write;
 
@@ -14,11 +14,15 @@ use crossterm::{
     }, terminal::{Clear, ClearType},
 };
 
+use crate::events::{ Timers, Waker };
+use crate::jobs::Jobs;
+
 use super::{
     border::{
         Border,
         BorderStyle
     },
+    bus::{ Bus, State },
     rect::Rect,
     window::{ 
         FillMode,
@@ -35,38 +39,67 @@ pub enum CursorUpdateMode {
     RelativeToSelected
 }
 
-pub struct UI<STATE> {
-    pub state: STATE,
+/// How many messages one input or job update may lead to, counting the
+/// messages windows send back while handling them, before the rest are
+/// dropped. Only reached if windows keep answering each other.
+pub const MAX_MESSAGES: usize = 1024;
+
+pub struct UI<STATE: State> {
+    state: STATE,
+    pub jobs: Jobs<STATE>,
+    pub timers: Timers,
     pub cursor_position: (u16, u16),
     windows: Vec<Box<dyn Window<STATE>>>,
     selected: usize,
     hidden: HashSet<usize>,
     window_bounds: Option<Vec<Rect>>,
     border: Option<Border>,
+    border_style: BorderStyle,
+    // Overlays opened at runtime, bottom to top
+    popups: Vec<Box<dyn Window<STATE>>>,
+    waker: Option<Waker>,
+    // What is on screen: each window's generation as last drawn (`None` if
+    // it needs drawing), and every overlay
+    drawn: Vec<Option<u64>>,
+    drawn_overlays: Vec<(Rect, Option<u64>)>,
+    redraw_all: bool,
     recalculate: bool
 }
 
-impl<STATE: Clone> UI<STATE> {
+impl<STATE: State> UI<STATE> {
     pub fn new(windows: Vec<Box<dyn Window<STATE>>>, state: STATE) -> Self {
         debug!("Creating new ui with {} windows.", windows.len());
 
         Self {
+            drawn: vec![ None; windows.len() ],
+            drawn_overlays: Vec::new(),
+            redraw_all: true,
             state,
+            jobs: Jobs::new(),
+            timers: Timers::new(),
             cursor_position: (0, 0),
             windows, 
             selected: 0,
             hidden: HashSet::new(),
             window_bounds: None,
             border: None,
+            border_style: BorderStyle::default(),
+            popups: Vec::new(),
+            waker: None,
             recalculate: true
         }
     }
 
-    pub fn windows(&self) -> Vec<&Box<dyn Window<STATE>>> {
+    /// Only ever changed by `send`.
+    pub fn state(&self) -> &STATE {
+        &self.state
+    }
+
+    pub fn windows(&self) -> Vec<&dyn Window<STATE>> {
         self.windows.iter()
             .enumerate()
             .filter(|(i, _)| !self.hidden.contains(i))
-            .map(|(_, w)| w)
+            .map(|(_, w)| w.as_ref())
             .collect()
     }
 
@@ -78,8 +111,8 @@ impl<STATE: Clone> UI<STATE> {
             .collect()
     }
 
-    pub fn selected(&self) -> &Box<dyn Window<STATE>> {
-        &self.windows[self.selected]
+    pub fn selected(&self) -> &dyn Window<STATE> {
+        self.windows[self.selected].as_ref()
     }
     
     pub fn selected_mut(&mut self) -> &mut Box<dyn Window<STATE>> {
@@ -90,6 +123,174 @@ impl<STATE: Clone> UI<STATE> {
         self.selected
     }
 
+    /// The topmost modal popup if there is one, otherwise the selected window.
+    fn focused(&self) -> &dyn Window<STATE> {
+        match self.popups.iter().rev().find(|p| p.info().modal) {
+            Some(popup) => popup.as_ref(),
+            None => self.selected(),
+        }
+    }
+
+    /// Opens `window` above every other overlay, placed by its `WindowRoot`
+    /// and sized by its `WindowMode::Bounds`. It stays open until its
+    /// `should_close` returns `true`, or `close_popup` is called.
+    pub fn open_popup(&mut self, mut window: Box<dyn Window<STATE>>) -> Result<(), Box<dyn Error>> {
+        let bounds = self.overlay_bounds(window.info(), crossterm::terminal::size()?);
+
+        window.set_bounds(bounds);
+        window.update_state(&self.state);
+
+        if let Some(waker) = &self.waker {
+            window.set_waker(waker.clone());
+        }
+
+        self.popups.push(window);
+
+        Ok(())
+    }
+
+    /// Closes the topmost popup.
+    pub fn close_popup(&mut self) {
+        self.popups.pop();
+    }
+
+    pub fn has_popup(&self) -> bool {
+        !self.popups.is_empty()
+    }
+
+    /// Whether input is going to a popup rather than the selected window.
+    pub fn has_modal_popup(&self) -> bool {
+        self.popups.iter().any(|p| p.info().modal)
+    }
+
+    /// Opens every popup a window has asked for.
+    fn open_requested_popups(&mut self) {
+        let requested: Vec<_> = self.windows.iter_mut()
+            .chain(self.popups.iter_mut())
+            .flat_map(|w| w.take_popups())
+            .collect();
+
+        for popup in requested {
+            if let Err(e) = self.open_popup(popup) {
+                warn!("Can't open popup: {}", e);
+            }
+        }
+    }
+
+    fn spawn_requested_jobs(&mut self) {
+        let requested: Vec<_> = self.windows.iter_mut()
+            .chain(self.popups.iter_mut())
+            .flat_map(|w| w.take_jobs())
+            .collect();
+
+        for request in requested {
+            self.jobs.spawn_request(request);
+        }
+    }
+
+    // Every window is ticked whenever the main loop wakes, so the timers
+    // don't have to remember who asked for them
+    fn set_requested_timeouts(&mut self) {
+        let requested: Vec<_> = self.windows.iter_mut()
+            .chain(self.popups.iter_mut())
+            .flat_map(|w| w.take_timeouts())
+            .collect();
+
+        for delay in requested {
+            self.timers.set_timeout(delay);
+        }
+    }
+
+    /// Drops every popup that has asked to be closed. Returns `true` if there
+    /// were any.
+    fn close_finished_popups(&mut self) -> bool {
+        let count = self.popups.len();
+        self.popups.retain(|p| !p.should_close());
+
+        self.popups.len() != count
+    }
+
+    /// Where an overlay with `info` goes. Overlays are clamped to the screen,
+    /// and windows without `WindowMode::Bounds` cover as much of it as they can.
+    fn overlay_bounds(&self, info: WindowInfo, term_size: (u16, u16)) -> Rect {
+        let (term_width, term_height) = term_size;
+        // The rect's right and bottom edges are inclusive
+        let (max_width, max_height) = (term_width.saturating_sub(3), term_height.saturating_sub(3));
+
+        let (width, height) = match info.mode {
+            WindowMode::Bounds { width, height } => (width.min(max_width), height.min(max_height)),
+            _ => (max_width, max_height),
+        };
+
+        let (x, y) = match info.root {
+            WindowRoot::Point { x, y } => (x, y),
+            WindowRoot::Cursor => {
+                let (cursor_x, cursor_y) = self.cursor_position;
+
+                // Line the content up with the cursor
+                let y = if cursor_y + height + 1 < term_height {
+                    cursor_y + 1
+                } else {
+                    cursor_y.saturating_sub(height + 1)
+                };
+
+                (cursor_x.saturating_sub(1), y)
+            },
+            _ => ((term_width - width) / 2, (term_height - height) / 2),
+        };
+
+        Rect {
+            x: x.min(term_width.saturating_sub(width + 1)),
+            y: y.min(term_height.saturating_sub(height + 1)),
+            width,
+            height,
+        }
+    }
+
+    /// Moves every overlay to where it should be now, e.g. after the cursor
+    /// has moved.
+    fn place_overlays(&mut self) -> Result<(), Box<dyn Error>> {
+        let term_size = crossterm::terminal::size()?;
+
+        // Popups are overlays wherever their root says they go
+        let placed: Vec<Rect> = self.windows().iter()
+            .map(|w| w.info())
+            .filter(|info| info.root.is_overlay())
+            .chain(self.popups.iter().map(|p| p.info()))
+            .map(|info| self.overlay_bounds(info, term_size))
+            .collect();
+
+        let overlays = self.windows.iter_mut()
+            .enumerate()
+            .filter(|(i, w)| !self.hidden.contains(i) && w.info().root.is_overlay())
+            .map(|(_, w)| w)
+            .chain(self.popups.iter_mut());
+
+        for (window, bounds) in overlays.zip(placed) {
+            if window.get_bounds() != bounds {
+                window.set_bounds(bounds);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lets jobs and windows with background work wake the main loop.
+    pub fn set_waker(&mut self, waker: Waker) {
+        for window in &mut self.windows {
+            window.set_waker(waker.clone());
+        }
+
+        self.jobs.set_waker(waker.clone());
+        self.waker = Some(waker);
+    }
+
+    /// Sets the style used to draw window borders, falling back to
+    /// `BorderStyle::Ascii` on terminals without UTF-8 support.
+    pub fn set_border_style(&mut self, style: BorderStyle) {
+        self.border_style = style.supported();
+    }
+
     pub fn select_window(&mut self, index: usize) {
         self.selected = index;
     }
@@ -147,19 +348,113 @@ impl<STATE: Clone> UI<STATE> {
         self.recalculate = true;
     }
 
+    /// Brings every window, hidden or not, up to date with the whole state.
     pub fn update_windows_state(&mut self) {
-        let state = self.state.clone();
+        for window in self.windows.iter_mut().chain(self.popups.iter_mut()) {
+            window.update_state(&self.state);
+        }
+    }
+
+    /// Applies `message` to the state, then passes it on to every window.
+    pub fn send(&mut self, message: STATE::Message) {
+        self.dispatch(vec![ message ]);
+    }
+
+    /// Applies each message in turn, passing it on to every window, hidden
+    /// or not, and then applies whatever the windows sent back. Any popups,
+    /// jobs and timers the windows asked for are started last.
+    fn dispatch(&mut self, messages: Vec<STATE::Message>) {
+        let mut queue = VecDeque::from(messages);
+        let mut bus = Bus::new();
+        let mut handled = 0;
+
+        while let Some(message) = queue.pop_front() {
+            if handled == MAX_MESSAGES {
+                warn!("Dropping {} messages, starting with {:?}.", queue.len() + 1, message);
+                break;
+            }
 
-        for window in self.windows_mut() {
-            window.update_state(&state);
+            handled += 1;
+            self.state.apply(&message);
+
+            for window in self.windows.iter_mut().chain(self.popups.iter_mut()) {
+                window.on_message(&self.state, &message, &mut bus);
+            }
+
+            queue.extend(bus.take());
         }
+
+        self.open_requested_popups();
+        self.spawn_requested_jobs();
+        self.set_requested_timeouts();
     }
 
+    /// Ticks every window, hidden or not. Returns `true` if a visible window
+    /// needs to be redrawn.
+    pub fn tick_windows(&mut self) -> bool {
+        let mut redraw = false;
+        let mut bus = Bus::new();
+        let popups = self.popups.len();
+
+        for (i, window) in self.windows.iter_mut().enumerate() {
+            if window.tick(&self.state, &mut bus) && !self.hidden.contains(&i) {
+                redraw = true;
+            }
+        }
+
+        for popup in &mut self.popups {
+            redraw |= popup.tick(&self.state, &mut bus);
+        }
+
+        self.dispatch(bus.take());
+
+        redraw | (self.popups.len() != popups) | self.close_finished_popups()
+    }
+
+    /// Applies whatever background jobs have sent back. Returns `true` if
+    /// anything needs to be redrawn.
+    pub fn run_jobs(&mut self) -> bool {
+        let mut bus = Bus::new();
+        let redraw = self.jobs.drain(&mut bus);
+
+        self.dispatch(bus.take());
+
+        redraw
+    }
+
+    /// Passes input to the topmost modal popup if there is one, otherwise to
+    /// the selected window, then applies the messages it sent.
     pub fn pass_input_to_selected(&mut self, code: KeyCode, modifiers: KeyModifiers) {
-        let mut state = self.state.clone();
-        self.selected_mut().handle_input(&mut state, code, modifiers).unwrap();
+        let mut bus = Bus::new();
+
+        let window = match self.popups.iter().rposition(|p| p.info().modal) {
+            Some(i) => &mut self.popups[i],
+            None => &mut self.windows[self.selected],
+        };
 
-        self.state = state;
+        if let Err(e) = window.handle_input(&self.state, &mut bus, code, modifiers) {
+            bus.send(STATE::show_message(e.to_string()));
+        }
+
+        self.close_finished_popups();
+        self.dispatch(bus.take());
+    }
+
+    /// Like `pass_input_to_selected`, for pasted text.
+    pub fn pass_paste_to_selected(&mut self, text: &str) {
+        let mut bus = Bus::new();
+
+        let window = match self.popups.iter().rposition(|p| p.info().modal) {
+            Some(i) => &mut self.popups[i],
+            None => &mut self.windows[self.selected],
+        };
+
+        if let Err(e) = window.handle_paste(&self.state, &mut bus, text) {
+            bus.send(STATE::show_message(e.to_string()));
+        }
+
+        self.close_finished_popups();
+        self.dispatch(bus.take());
     }
 
     pub fn update_cursor_position(&mut self, position: (u16, u16), mode: CursorUpdateMode) {
@@ -168,7 +463,7 @@ impl<STATE: Clone> UI<STATE> {
         match mode {
             CursorUpdateMode::Absolute => self.cursor_position = position,
             CursorUpdateMode::RelativeToSelected => {
-                let selected = self.selected().get_bounds();
+                let selected = self.focused().get_bounds();
                 let (window_x, window_y) = (selected.x, selected.y);
 
                 self.cursor_position = (window_x + x + 1, window_y + y + 1);
@@ -185,15 +480,20 @@ impl<STATE: Clone> UI<STATE> {
         self.update_cursor_position((0, 0), CursorUpdateMode::RelativeToSelected);
     }
 
-    fn window_max_width(&self, window: Rect, used_space: &Vec<Rect>) -> Option<u16> {
+    /// Moves the cursor to wherever the focused window wants it, if anywhere.
+    pub fn move_cursor_to_selected(&mut self) {
+        if let Some(position) = self.focused().cursor_position() {
+            self.update_cursor_position(position, CursorUpdateMode::RelativeToSelected);
+        }
+    }
+
+    fn window_max_width(&self, window: Rect, used_space: &[Rect]) -> Option<u16> {
         let Rect { x, y, height, .. } = window;
 
         let mut min_width = u16::MAX;
         for space in used_space {
-            if x < space.x {
-                if y + height >= space.y && y < space.y + space.height {
-                    min_width = min_width.min(space.x - x);
-                }
+            if x < space.x && y + height >= space.y && y < space.y + space.height {
+                min_width = min_width.min(space.x - x);
             }
         }
 
@@ -204,15 +504,13 @@ impl<STATE: Clone> UI<STATE> {
         }
     }
 
-    fn window_max_height(&self, window: Rect, used_space: &Vec<Rect>) -> Option<u16> {
+    fn window_max_height(&self, window: Rect, used_space: &[Rect]) -> Option<u16> {
         let Rect { x, y, width, .. } = window;
 
         let mut min_height = u16::MAX;
         for space in used_space {
-            if y < space.y {
-                if x >= space.x || x + width < space.x + space.width {
-                    min_height = min_height.min(space.y - y);
-                }
+            if y < space.y && (x >= space.x || x + width < space.x + space.width) {
+                min_height = min_height.min(space.y - y);
             }
         }
 
@@ -223,11 +521,11 @@ impl<STATE: Clone> UI<STATE> {
         }
     }
 
-    fn calculate_origin(&self, used_space: &Vec<Rect>, alignment: WindowAlignment, term_size: (u16, u16)) -> (u16, u16) {
+    fn calculate_origin(&self, used_space: &[Rect], alignment: WindowAlignment, term_size: (u16, u16)) -> (u16, u16) {
         if used_space.is_empty() {
             match alignment {
                 WindowAlignment::Top => (0, 0),
-                WindowAlignment::Bottom => (0, term_size.1),
+                WindowAlignment::Bottom => (0, term_size.1 - 1),
             }
         } else {
             let (max_width, max_height) = term_size;
@@ -251,8 +549,9 @@ impl<STATE: Clone> UI<STATE> {
                     for y in 1..max_height {
                         for x in 0..max_width {
                             if !used_space.iter().any(|r| r.contains((x, max_height - y))) {
-                                let x = x.min(term_size.0).max(0);
-                                let y = (max_height - y).min(term_size.1).max(1);
+                                let x = x.min(term_size.0);
+                                // Share the border row with the window below, if there is one
+                                let y = (max_height - y + 1).min(max_height - 1).max(1);
 
                                 return (x, y);
                             }
@@ -265,15 +564,15 @@ impl<STATE: Clone> UI<STATE> {
         }
     }
 
-    fn get_window_dimensions(&self, info: WindowInfo, used_space: &Vec<Rect>) -> Result<Rect, Box<dyn Error>> {
+    fn get_window_dimensions(&self, info: WindowInfo, used_space: &[Rect]) -> Result<Rect, Box<dyn Error>> {
         let term_dim = crossterm::terminal::size()?;
 
         let WindowInfo { root, mode, .. } = info;
         let (x, mut y) = match root {
-            WindowRoot::Point { x, y } => (x, y),
             WindowRoot::Floating(alignment) => {
-                self.calculate_origin(&used_space, alignment, term_dim)
-            }
+                self.calculate_origin(used_space, alignment, term_dim)
+            },
+            _ => return Ok(self.overlay_bounds(info, term_dim)),
         };
 
         let (term_width, term_height) = term_dim;
@@ -293,49 +592,49 @@ impl<STATE: Clone> UI<STATE> {
                 }
 
                 let rect: Rect = (x, y, 0, height).into();
-                let width = self.window_max_width(rect, &used_space).unwrap_or(term_width - x);
+                let width = self.window_max_width(rect, used_space).unwrap_or(term_width - x);
 
                 (width, height)
             },
             WindowMode::FillV { width } => {
                 let rect: Rect = (x, y, width, 0).into();
-                let height = self.window_max_height(rect, &used_space).unwrap_or(term_height - y);
+                let height = self.window_max_height(rect, used_space).unwrap_or(term_height - y);
 
                 (width, height)
             },
             WindowMode::Fill(FillMode::FillHFirst) => {
                 let rect: Rect = (x, y, 0, 0).into();
-                let width = self.window_max_width(rect, &used_space).unwrap_or(term_width - x);
+                let width = self.window_max_width(rect, used_space).unwrap_or(term_width - x);
 
                 let rect: Rect = (x, y, width, 0).into();
-                let height = self.window_max_height(rect, &used_space).unwrap_or(term_height - y);
+                let height = self.window_max_height(rect, used_space).unwrap_or(term_height - y);
 
                 (width, height)
             },
             WindowMode::Fill(FillMode::FillVFirst) => {
                 let rect: Rect = (x, y, 0, 0).into();
-                let height = self.window_max_height(rect, &used_space).unwrap_or(term_height - y);
+                let height = self.window_max_height(rect, used_space).unwrap_or(term_height - y);
                 
                 let rect: Rect = (x, y, 0, height).into();
-                let width = self.window_max_width(rect, &used_space).unwrap_or(term_width - x);
+                let width = self.window_max_width(rect, used_space).unwrap_or(term_width - x);
 
                 (width, height)
             },
             WindowMode::Fill(FillMode::MaxArea) => {
                 // H First
                 let rect: Rect = (x, y, 0, 0).into();
-                let width_h = self.window_max_width(rect, &used_space).unwrap_or(term_width - x);
+                let width_h = self.window_max_width(rect, used_space).unwrap_or(term_width - x);
 
                 let rect: Rect = (x, y, width_h, 0).into();
-                let height_h = self.window_max_height(rect, &used_space).unwrap_or(term_height - y);
+                let height_h = self.window_max_height(rect, used_space).unwrap_or(term_height - y);
                 let area_h = width_h * height_h;
                 
                 // V First
                 let rect: Rect = (x, y, 0, 0).into();
-                let height_v = self.window_max_height(rect, &used_space).unwrap_or(term_height - y);
+                let height_v = self.window_max_height(rect, used_space).unwrap_or(term_height - y);
                 
                 let rect: Rect = (x, y, 0, height_v).into();
-                let width_v = self.window_max_width(rect, &used_space).unwrap_or(term_width - x);
+                let width_v = self.window_max_width(rect, used_space).unwrap_or(term_width - x);
                 let area_v = width_v * height_v;
 
                 if area_h > area_v {
@@ -353,25 +652,36 @@ impl<STATE: Clone> UI<STATE> {
         crossterm::execute!(std::io::stdout(), Clear(ClearType::All))?;
         info!("Recalculating UI...");
 
+        let mut window_bounds: Vec<Rect> = Vec::new();
         let mut used_space: Vec<Rect> = Vec::new();
         let mut border = Border::new();
 
         for window in self.windows() {
-            let bounds = self.get_window_dimensions(window.info(), &used_space)?;
+            let info = window.info();
+            let bounds = self.get_window_dimensions(info, &used_space)?;
+            window_bounds.push(bounds);
+
+            // Overlays don't take up any space, or share borders
+            if info.root.is_overlay() {
+                continue;
+            }
+
             used_space.push(bounds);
 
-            border.append(bounds.border_points());
+            if info.border {
+                border.append(bounds.border_points());
+            }
         }
 
-        for (window, bound) in self.windows_mut().iter_mut().zip(used_space.clone()) {
+        for (window, bound) in self.windows_mut().iter_mut().zip(window_bounds.clone()) {
             window.set_bounds(bound);
             // debug!("window at {bound:#}.");
         }
 
-        border.recalculate_directions();
-
-        self.window_bounds = Some(used_space);
+        self.window_bounds = Some(window_bounds);
         self.border = Some(border);
+        self.place_overlays()?;
+        self.redraw_all = true;
 
         self.recalculate = false;
         // info!("Finished recalculating UI.");
@@ -379,42 +689,73 @@ impl<STATE: Clone> UI<STATE> {
         Ok(())
     }
 
+    /// The visible tiled windows, with their indices and bounds.
+    fn tiled(&self) -> Vec<(usize, &dyn Window<STATE>, Rect)> {
+        let bounds = self.window_bounds.as_deref().unwrap_or_default();
+
+        self.windows.iter()
+            .enumerate()
+            .filter(|(i, _)| !self.hidden.contains(i))
+            .zip(bounds)
+            .filter(|((_, w), _)| !w.info().root.is_overlay())
+            .map(|((i, w), bound)| (i, w.as_ref(), *bound))
+            .collect()
+    }
+
     fn draw_content<T: Write>(&self, queue: &mut T) -> Result<(), Box<dyn Error>> {
-        if let Some(bounds) = &self.window_bounds {
-            for (window, bound) in self.windows().iter().zip(bounds) {
-                let Rect { x, y, width, height } = *bound;
-                self.move_cursor(queue, x + 1, y + 1)?;
+        for (_, window, bound) in self.tiled() {
+            self.draw_window(queue, window, bound)?;
+        }
 
-                let lines = window.lines();
-                let mut line_num = 1;
+        Ok(())
+    }
 
-                for line in lines {
-                    if line_num > height {
-                        break;
-                    }
+    /// Blanks out the window's content area.
+    fn clear_window<T: Write>(&self, queue: &mut T, window: &dyn Window<STATE>, bound: Rect) -> Result<(), Box<dyn Error>> {
+        let Rect { x, y, width, height } = bound;
 
-                    self.move_cursor(queue, x + 1, y + line_num)?;
-
-                    for (content, style) in line.iter_chunks() {
-                        let content = if content.len() > width as usize {
-                            &content[..width as usize]
-                        } else {
-                            content
-                        };
-
-                        queue!(
-                            queue,
-                            PrintStyledContent(
-                                style.apply(content)
-                            )
-                        )?;
-                    }
+        let rows = if window.info().border { height.saturating_sub(1) } else { height };
+        let blank = " ".repeat(width.saturating_sub(1) as usize);
+
+        for row in y + 1..=y + rows {
+            self.move_cursor(queue, x + 1, row)?;
+            queue!(queue, PrintStyledContent(ContentStyle::default().apply(blank.as_str())))?;
+        }
 
-                    line_num += 1;
+        Ok(())
+    }
+
+    fn draw_window<T: Write>(&self, queue: &mut T, window: &dyn Window<STATE>, bound: Rect) -> Result<(), Box<dyn Error>> {
+        let Rect { x, y, width, height } = bound;
+        self.move_cursor(queue, x + 1, y + 1)?;
+
+        // The last row belongs to the bottom border, unless there isn't one
+        let rows = if window.info().border { height.saturating_sub(1) } else { height };
+
+        for (line_num, line) in (1..=rows).zip(window.lines()) {
+            self.move_cursor(queue, x + 1, y + line_num)?;
+
+            let mut remaining = width.saturating_sub(1) as usize;
+
+            for (content, style) in line.iter_chunks() {
+                if remaining == 0 {
+                    break;
                 }
+
+                let end = content.char_indices()
+                    .nth(remaining)
+                    .map_or(content.len(), |(i, _)| i);
+                let content = &content[..end];
+
+                remaining -= content.chars().count();
+
+                queue!(
+                    queue,
+                    PrintStyledContent(
+                        style.apply(content)
+                    )
+                )?;
             }
-        } else {
-            unreachable!()
         }
 
         Ok(())
@@ -422,7 +763,7 @@ impl<STATE: Clone> UI<STATE> {
 
     fn draw_borders<T: Write>(&self, queue: &mut T) -> Result<(), Box<dyn Error>> {
         if let Some(border) = &self.border {
-            border.draw(queue, BorderStyle::Light, ContentStyle::default().with(Color::Black))?;
+            border.draw(queue, self.border_style, ContentStyle::default().with(Color::Black))?;
         } else {
             unreachable!()
         }
@@ -432,44 +773,159 @@ impl<STATE: Clone> UI<STATE> {
 
     fn draw_titles<T: Write>(&self, queue: &mut T) -> Result<(), Box<dyn Error>> {
         if let Some(bounds) = &self.window_bounds {
-            let titles: Vec<(&str, Option<ContentStyle>)> = self.windows().iter()
-                .map(|w| (w.title(), w.title_style()))
-                .collect();
+            for (window, bound) in self.windows().iter().zip(bounds) {
+                let info = window.info();
 
-            for ((title, style), bound) in titles.iter().zip(bounds) {
-                let Rect { x, y, width, .. } = *bound;
-                self.move_cursor(queue, x + 1, y)?;
+                if info.border && !info.root.is_overlay() {
+                    self.draw_title(queue, *window, *bound)?;
+                }
+            }
+        } else {
+            unreachable!()
+        }
 
-                let style = style.unwrap_or_default();
+        Ok(())
+    }
 
-                let content = if title.len() > width as usize - 2 {
-                    &title[..width as usize - 2]
-                } else {
-                    title
-                };
+    fn draw_title<T: Write>(&self, queue: &mut T, window: &dyn Window<STATE>, bound: Rect) -> Result<(), Box<dyn Error>> {
+        let Rect { x, y, width, .. } = bound;
+        self.move_cursor(queue, x + 1, y)?;
 
-                queue!(
-                    queue,
-                    PrintStyledContent(style.apply(content))
-                )?;
-            }
+        let title = window.title();
+        let style = window.title_style().unwrap_or_default();
+
+        let content = if title.len() > width as usize - 2 {
+            &title[..width as usize - 2]
         } else {
-            unreachable!()
+            title
+        };
+
+        queue!(
+            queue,
+            PrintStyledContent(style.apply(content))
+        )?;
+
+        Ok(())
+    }
+
+    /// Draws the overlay windows, then the popups, each above the last.
+    fn draw_overlays<T: Write>(&self, queue: &mut T) -> Result<(), Box<dyn Error>> {
+        let overlays = self.windows().into_iter()
+            .filter(|w| w.info().root.is_overlay())
+            .chain(self.popups.iter().map(|p| p.as_ref()));
+
+        for window in overlays {
+            self.draw_overlay(queue, window)?;
+        }
+
+        Ok(())
+    }
+
+    /// Blanks out the window's area, then draws it like any other window,
+    /// with a border of its own. Modal windows get an emphasized border.
+    fn draw_overlay<T: Write>(&self, queue: &mut T, window: &dyn Window<STATE>) -> Result<(), Box<dyn Error>> {
+        let info = window.info();
+        let bound = window.get_bounds();
+
+        self.clear_window(queue, window, bound)?;
+
+        if info.border {
+            let points = bound.border_points();
+            let mut border = Border::new();
+            border.append(points.clone());
+
+            if info.modal {
+                border.set_emphasis(points);
+            }
+
+            border.draw(queue, self.border_style, ContentStyle::default().with(Color::Black))?;
+        }
+
+        self.draw_window(queue, window, bound)?;
+
+        if info.border {
+            self.draw_title(queue, window, bound)?;
         }
 
         Ok(())
     }
 
+    /// Every visible overlay's bounds and generation, bottom to top.
+    fn overlays(&self) -> Vec<(Rect, Option<u64>)> {
+        self.windows().into_iter()
+            .filter(|w| w.info().root.is_overlay())
+            .chain(self.popups.iter().map(|p| p.as_ref()))
+            .map(|w| (w.get_bounds(), w.generation()))
+            .collect()
+    }
+
+    /// Forgets what is on screen, so the next draw redraws everything.
+    pub fn redraw_all(&mut self) {
+        self.redraw_all = true;
+    }
+
+    /// Draws whatever has changed since the last draw. Everything is redrawn
+    /// after the layout changes or an overlay moves, opens or closes;
+    /// otherwise only windows whose generation changed are, along with
+    /// their borders and any overlays above them.
     pub fn draw<T: Write>(&mut self, queue: &mut T) -> Result<(), Box<dyn Error>> {
         if self.window_bounds.is_none() || self.border.is_none() || self.recalculate {
             self.recalculate_ui()?;
         }
 
-        self.clear_all(queue)?;
+        self.place_overlays()?;
+
+        let overlays = self.overlays();
+        let overlays_moved = overlays.iter().map(|(r, _)| r)
+            .ne(self.drawn_overlays.iter().map(|(r, _)| r));
+
+        if self.redraw_all || overlays_moved {
+            self.clear_all(queue)?;
+
+            self.draw_content(queue)?;
+            self.draw_borders(queue)?;
+            self.draw_titles(queue)?;
+            self.draw_overlays(queue)?;
+        } else {
+            let dirty: Vec<(usize, &dyn Window<STATE>, Rect)> = self.tiled().into_iter()
+                .filter(|(i, w, _)| !unchanged(self.drawn[*i], w.generation()))
+                .collect();
+
+            for (_, window, bound) in &dirty {
+                self.clear_window(queue, *window, *bound)?;
+                self.draw_window(queue, *window, *bound)?;
+            }
+
+            // Titles sit on the top border, so redraw that under them
+            let border = self.border.as_ref().unwrap();
+
+            for (_, window, bound) in dirty.iter().filter(|(_, w, _)| w.info().border) {
+                let Rect { x, y, width, .. } = *bound;
+                let top: Vec<(u16, u16)> = (x..=x + width).map(|x| (x, y)).collect();
+
+                border.draw_points(queue, &top, self.border_style, ContentStyle::default().with(Color::Black))?;
+                self.draw_title(queue, *window, *bound)?;
+            }
+
+            let overlays_changed = !dirty.is_empty() || overlays.iter()
+                .zip(&self.drawn_overlays)
+                .any(|((_, now), (_, drawn))| !unchanged(*drawn, *now));
+
+            if overlays_changed {
+                self.draw_overlays(queue)?;
+            }
+        }
+
+        let generations: Vec<(usize, Option<u64>)> = self.tiled().into_iter()
+            .map(|(i, w, _)| (i, w.generation()))
+            .collect();
+
+        for (i, generation) in generations {
+            self.drawn[i] = generation;
+        }
 
-        self.draw_content(queue)?;
-        self.draw_borders(queue)?;
-        self.draw_titles(queue)?;
+        self.drawn_overlays = overlays;
+        self.redraw_all = false;
 
         // Now handled by Application::run()
         // queue.flush()?;
@@ -485,3 +941,9 @@ impl<STATE: Clone> UI<STATE> {
         queue!(queue, MoveTo(x, y)).map_err(|e| e.into())
     }
 }
+
+/// Whether a window drawn at generation `drawn` can be left alone now that
+/// it is at `now`. Windows without a generation are always redrawn.
+fn unchanged(drawn: Option<u64>, now: Option<u64>) -> bool {
+    now.is_some() && drawn == now
+}
@@ -1,9 +1,14 @@
 use std::error::Error;
 use std::ops::Range;
+use std::time::Duration;
 
 use crossterm::style::ContentStyle;
 use crossterm::event::{ KeyCode, KeyModifiers };
 
+use crate::events::Waker;
+use crate::jobs::JobRequest;
+
+use super::bus::{ Bus, State };
 use super::rect::Rect;
 
 #[derive(Debug, Clone)]
@@ -45,65 +50,110 @@ impl StyledContent {
         }
     }
 
+    /// Restyles the bytes in `range`, splitting any chunks it overlaps.
     pub fn style_range(&mut self, range: Range<usize>, style: ContentStyle) {
+        let len = self.content.len();
+        let range = range.start.min(len)..range.end.min(len);
+
+        if range.is_empty() {
+            return;
+        }
+
         let chunk = StyledChunk { start: range.start, end: range.end, style };
+        let mut chunks = Vec::with_capacity(self.chunks.len() + 2);
+        let mut inserted = false;
 
-        for (i, c) in self.chunks.iter().enumerate() {
-            // If the new chunk starts within an existing chunk...
-            if c.start < chunk.start && chunk.start < c.end {
-                let chunk_before = StyledChunk {
-                    start: c.start,
-                    end: chunk.start,
-                    ..(*c)
-                };
+        for c in self.chunks.drain(..) {
+            // `c` lies entirely before the new chunk
+            if c.end <= chunk.start {
+                chunks.push(c);
+                continue;
+            }
 
-                // ...AND is contained entirely within that chunk 
-                if chunk.end < c.end {
-                    let chunk_after = StyledChunk {
-                        start: chunk.end,
-                        end: c.end,
-                        ..(*c)
-                    };
-
-                    self.chunks[i] = chunk_before;
-                    self.chunks.insert(i + 1, chunk);
-                    self.chunks.insert(i + 2, chunk_after);
-                // ...AND ends exactly where that chunk ends
-                } else if chunk.end == c.end {
-                    self.chunks[i] = chunk_before;
-                    self.chunks.insert(i + 1, chunk);
-                // ...AND ends within another chunk
-                } else if chunk.end > c.end {
-                    let (index, ends_in_chunk) = self.chunks
-                        .iter()
-                        .enumerate()
-                        .find(|(_, c)| c.start < chunk.end && c.end > chunk.end)
-                        .unwrap();
-
-                    let chunk_after = StyledChunk {
-                        start: chunk.end,
-                        end: ends_in_chunk.end,
-                        ..(*ends_in_chunk)
-                    };
-
-                    // remove "overwritten" chunks
-                    self.chunks.drain(i..index);
-
-                    self.chunks[i] = chunk_before;
-                    self.chunks.insert(i + 1, chunk);
-                    self.chunks.insert(i + 2, chunk_after);
+            // `c` lies entirely after the new chunk
+            if c.start >= chunk.end {
+                if !inserted {
+                    chunks.push(chunk.clone());
+                    inserted = true;
                 }
 
-                break;
+                chunks.push(c);
+                continue;
+            }
+
+            // `c` overlaps the new chunk; keep whatever sticks out either side
+            if c.start < chunk.start {
+                chunks.push(StyledChunk { end: chunk.start, ..c });
+            }
+
+            if !inserted {
+                chunks.push(chunk.clone());
+                inserted = true;
+            }
+
+            if c.end > chunk.end {
+                chunks.push(StyledChunk { start: chunk.end, ..c });
+            }
+        }
+
+        if !inserted {
+            chunks.push(chunk);
+        }
+
+        self.chunks = chunks;
+    }
+
+    /// Lays `style` over the bytes in `range`. Its colors and attributes win,
+    /// and whatever it leaves unset shows through from below.
+    pub fn layer_range(&mut self, range: Range<usize>, style: ContentStyle) {
+        let len = self.content.len();
+        let range = range.start.min(len)..range.end.min(len);
+
+        if range.is_empty() {
+            return;
+        }
+
+        let mut chunks = Vec::with_capacity(self.chunks.len() + 2);
+
+        for c in self.chunks.drain(..) {
+            if c.end <= range.start || c.start >= range.end {
+                chunks.push(c);
+                continue;
+            }
+
+            if c.start < range.start {
+                chunks.push(StyledChunk { end: range.start, ..c });
+            }
+
+            let mut attributes = c.style.attributes;
+            attributes.extend(style.attributes);
+
+            let layered = ContentStyle {
+                foreground_color: style.foreground_color.or(c.style.foreground_color),
+                background_color: style.background_color.or(c.style.background_color),
+                underline_color: style.underline_color.or(c.style.underline_color),
+                attributes,
+            };
+
+            chunks.push(StyledChunk { start: c.start.max(range.start), end: c.end.min(range.end), style: layered });
+
+            if c.end > range.end {
+                chunks.push(StyledChunk { start: range.end, ..c });
             }
         }
+
+        self.chunks = chunks;
     }
 
     pub fn push(&mut self, content: String, style: ContentStyle) {
         let start = self.content.len();
 
         self.content.push_str(&content);
-        self.style_range(start..self.content.len(), style);
+
+        // Appended content lies past every existing chunk
+        if !content.is_empty() {
+            self.chunks.push(StyledChunk { start, end: self.content.len(), style });
+        }
     }
 
     pub fn iter_chunks(&self) -> std::vec::IntoIter<(&str, ContentStyle)> {
@@ -160,6 +210,16 @@ impl StyledContent {
     pub fn len(&self) -> usize {
         self.content.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+}
+
+impl Default for StyledContent {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -177,10 +237,21 @@ pub enum WindowMode {
     Fill(FillMode)
 }
 
+/// Where a window goes. `Floating` windows are tiled; the rest are overlays,
+/// which take up no space in the layout and are drawn on top of it.
 #[derive(Debug, Clone, Copy)]
 pub enum WindowRoot {
     Point { x: u16, y: u16 },
-    Floating(WindowAlignment)
+    Floating(WindowAlignment),
+    Centered,
+    /// Just below the terminal cursor, or above it if there's no room
+    Cursor
+}
+
+impl WindowRoot {
+    pub fn is_overlay(&self) -> bool {
+        !matches!(self, WindowRoot::Floating(_))
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -193,7 +264,9 @@ pub enum WindowAlignment {
 pub struct WindowInfo {
     pub root: WindowRoot,
     pub mode: WindowMode,
-    pub selectable: bool
+    pub selectable: bool,
+    pub border: bool,
+    pub modal: bool
 }
 
 impl WindowInfo {
@@ -201,7 +274,9 @@ impl WindowInfo {
         Self { 
             root: WindowRoot::Floating(WindowAlignment::Top), 
             mode: WindowMode::Fill(FillMode::FillHFirst),
-            selectable: false
+            selectable: false,
+            border: true,
+            modal: false
         }
     }
 
@@ -212,6 +287,20 @@ impl WindowInfo {
         }
     }
     
+    pub fn centered(self) -> Self {
+        Self {
+            root: WindowRoot::Centered,
+            ..self
+        }
+    }
+
+    pub fn at_cursor(self) -> Self {
+        Self {
+            root: WindowRoot::Cursor,
+            ..self
+        }
+    }
+
     pub fn bounds(self, width: u16, height: u16) -> Self {
         Self {
             mode: WindowMode::Bounds { width, height },
@@ -253,9 +342,32 @@ impl WindowInfo {
             ..self
         }
     }
+
+    /// The window draws no border or title, and its content may use the
+    /// rows its border would have taken.
+    pub fn borderless(self) -> Self {
+        Self {
+            border: false,
+            ..self
+        }
+    }
+
+    /// While open as a popup, the window takes all input and the cursor.
+    pub fn modal(self) -> Self {
+        Self {
+            modal: true,
+            ..self
+        }
+    }
+}
+
+impl Default for WindowInfo {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-pub trait Window<STATE>: std::fmt::Debug {
+pub trait Window<STATE: State>: std::fmt::Debug {
     // Immutable required functions
     fn info(&self) -> WindowInfo;
     fn lines(&self) -> Vec<StyledContent>;
@@ -271,7 +383,15 @@ pub trait Window<STATE>: std::fmt::Debug {
     fn title_style(&self) -> Option<ContentStyle> {
         None
     }
-    fn handle_input(&mut self, _state: &mut STATE, _code: KeyCode, _modifiers: KeyModifiers)
+    /// Changes to the state go through `bus`, and are applied once the
+    /// window returns.
+    fn handle_input(&mut self, _state: &STATE, _bus: &mut Bus<STATE::Message>, _code: KeyCode, _modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Text pasted into the terminal, all at once rather than as keys.
+    fn handle_paste(&mut self, _state: &STATE, _bus: &mut Bus<STATE::Message>, _text: &str)
     -> Result<(), Box<dyn Error>> {
         Ok(())
     }
@@ -281,5 +401,59 @@ pub trait Window<STATE>: std::fmt::Debug {
         Box::new(self)
     }
 
+    /// Brings the window up to date with all of `new_state`. Called once
+    /// before the first draw, and when a popup opens; after that, windows
+    /// only hear about what changes through `on_message`.
     fn update_state(&mut self, _new_state: &STATE) { }
+
+    /// Called for every message once it has been applied to `state`, so
+    /// windows can pick out the changes they care about. Anything sent to
+    /// `bus` is applied afterwards.
+    fn on_message(&mut self, _state: &STATE, _message: &STATE::Message, _bus: &mut Bus<STATE::Message>) { }
+
+    /// Called every time the main loop wakes up, whether or not there was
+    /// any input. Returns `true` if the window has new content to draw.
+    /// Anything sent to `bus` is applied afterwards.
+    fn tick(&mut self, _state: &STATE, _bus: &mut Bus<STATE::Message>) -> bool {
+        false
+    }
+
+    /// Changes whenever what `lines` or `title` would return does, so the
+    /// window is only redrawn when it has to be. Windows returning `None`
+    /// are redrawn every time anything is.
+    fn generation(&self) -> Option<u64> {
+        None
+    }
+
+    /// Windows doing work in the background should use `waker` to wake the
+    /// main loop when there is something new, or `tick` won't be called.
+    fn set_waker(&mut self, _waker: Waker) { }
+
+    /// Where the terminal cursor should be while this window is selected,
+    /// relative to the window's content.
+    fn cursor_position(&self) -> Option<(u16, u16)> {
+        None
+    }
+
+    /// Popups return `true` once they're finished with, and are then closed.
+    fn should_close(&self) -> bool {
+        false
+    }
+
+    /// Popups the window wants opened, such as an answer it was waiting on
+    /// in the background. Taken after every input, tick and message.
+    fn take_popups(&mut self) -> Vec<Box<dyn Window<STATE>>> {
+        Vec::new()
+    }
+
+    /// Jobs the window wants started, taken whenever popups are.
+    fn take_jobs(&mut self) -> Vec<JobRequest<STATE>> {
+        Vec::new()
+    }
+
+    /// Delays after which the window wants `tick` called, such as to do
+    /// something once the user stops typing. Taken whenever jobs are.
+    fn take_timeouts(&mut self) -> Vec<Duration> {
+        Vec::new()
+    }
 }
@@ -0,0 +1,640 @@
+//! A VT100/xterm screen: the grid of styled cells a program draws into by
+//! printing text and escape sequences, as a terminal would show it.
+
+use crossterm::event::{ KeyCode, KeyModifiers };
+use crossterm::style::{ Attribute, Color, ContentStyle };
+use vte::{ Params, Parser, Perform };
+
+use crate::ui::window::StyledContent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    c: char,
+    style: ContentStyle,
+}
+
+impl Cell {
+    /// An empty cell, keeping the background of `style` like xterm does.
+    fn blank(style: ContentStyle) -> Self {
+        Self { c: ' ', style: ContentStyle { background_color: style.background_color, ..ContentStyle::default() } }
+    }
+}
+
+type Rows = Vec<Vec<Cell>>;
+
+/// Everything escape sequences change, apart from the parser's own state.
+#[derive(Debug)]
+struct Grid {
+    width: usize,
+    height: usize,
+    rows: Rows,
+    /// The main screen's rows, while the alternate screen is showing
+    main: Option<Rows>,
+    /// `(column, row)`
+    cursor: (usize, usize),
+    saved_cursor: ((usize, usize), ContentStyle),
+    style: ContentStyle,
+    /// The rows that scroll, inclusive
+    scroll_region: (usize, usize),
+    /// The last column was just printed to, so the next print wraps first
+    wrap_pending: bool,
+    cursor_visible: bool,
+    /// Arrow keys are sent as SS3 sequences rather than CSI ones
+    application_cursor: bool,
+    /// Pastes are wrapped in escape sequences, so they aren't taken as typing
+    bracketed_paste: bool,
+    title: String,
+    /// Answers to queries, to be written back to the program
+    replies: Vec<u8>,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+
+        Self {
+            width,
+            height,
+            rows: vec![ vec![ Cell::blank(ContentStyle::default()); width ]; height ],
+            main: None,
+            cursor: (0, 0),
+            saved_cursor: ((0, 0), ContentStyle::default()),
+            style: ContentStyle::default(),
+            scroll_region: (0, height - 1),
+            wrap_pending: false,
+            cursor_visible: true,
+            application_cursor: false,
+            bracketed_paste: false,
+            title: String::new(),
+            replies: Vec::new(),
+        }
+    }
+
+    fn blank(&self) -> Cell {
+        Cell::blank(self.style)
+    }
+
+    fn blank_row(&self) -> Vec<Cell> {
+        vec![ self.blank(); self.width ]
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.max(1), height.max(1));
+
+        // Keep the cursor's row on screen by dropping rows from the top
+        let excess = (self.cursor.1 + 1).saturating_sub(height);
+        self.rows.drain(..excess);
+        self.cursor.1 -= excess;
+
+        let blank = self.blank();
+        for rows in std::iter::once(&mut self.rows).chain(self.main.as_mut()) {
+            rows.resize(height, vec![ blank; width ]);
+
+            for row in rows.iter_mut() {
+                row.resize(width, blank);
+            }
+        }
+
+        self.width = width;
+        self.height = height;
+        self.scroll_region = (0, height - 1);
+        self.wrap_pending = false;
+        self.move_to(self.cursor.0, self.cursor.1);
+    }
+
+    fn move_to(&mut self, column: usize, row: usize) {
+        self.cursor = (column.min(self.width - 1), row.min(self.height - 1));
+        self.wrap_pending = false;
+    }
+
+    /// Moves the rows of the scroll region up by `count`, blanking the
+    /// bottom ones.
+    fn scroll_up(&mut self, count: usize) {
+        let (top, bottom) = self.scroll_region;
+        let count = count.min(bottom + 1 - top);
+        let blank = self.blank_row();
+
+        self.rows[top..=bottom].rotate_left(count);
+        for row in &mut self.rows[bottom + 1 - count..=bottom] {
+            *row = blank.clone();
+        }
+    }
+
+    fn scroll_down(&mut self, count: usize) {
+        let (top, bottom) = self.scroll_region;
+        let count = count.min(bottom + 1 - top);
+        let blank = self.blank_row();
+
+        self.rows[top..=bottom].rotate_right(count);
+        for row in &mut self.rows[top..top + count] {
+            *row = blank.clone();
+        }
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor.1 == self.scroll_region.1 {
+            self.scroll_up(1);
+        } else if self.cursor.1 + 1 < self.height {
+            self.cursor.1 += 1;
+        }
+
+        self.wrap_pending = false;
+    }
+
+    fn reverse_index(&mut self) {
+        if self.cursor.1 == self.scroll_region.0 {
+            self.scroll_down(1);
+        } else {
+            self.cursor.1 = self.cursor.1.saturating_sub(1);
+        }
+
+        self.wrap_pending = false;
+    }
+
+    /// Lines inserted or deleted at the cursor only move the rows between it
+    /// and the bottom of the scroll region.
+    fn shift_lines(&mut self, count: usize, insert: bool) {
+        let (top, bottom) = self.scroll_region;
+
+        if !(top..=bottom).contains(&self.cursor.1) {
+            return;
+        }
+
+        let region = self.scroll_region;
+        self.scroll_region = (self.cursor.1, bottom);
+
+        if insert {
+            self.scroll_down(count);
+        } else {
+            self.scroll_up(count);
+        }
+
+        self.scroll_region = region;
+        self.cursor.0 = 0;
+        self.wrap_pending = false;
+    }
+
+    fn erase_in_display(&mut self, mode: u16) {
+        let (column, row) = self.cursor;
+        let blank = self.blank();
+
+        match mode {
+            0 => {
+                self.rows[row][column..].fill(blank);
+                for row in &mut self.rows[row + 1..] {
+                    row.fill(blank);
+                }
+            },
+            1 => {
+                self.rows[row][..=column].fill(blank);
+                for row in &mut self.rows[..row] {
+                    row.fill(blank);
+                }
+            },
+            2 | 3 => {
+                for row in &mut self.rows {
+                    row.fill(blank);
+                }
+            },
+            _ => { }
+        }
+    }
+
+    fn erase_in_line(&mut self, mode: u16) {
+        let (column, row) = self.cursor;
+        let blank = self.blank();
+        let row = &mut self.rows[row];
+
+        match mode {
+            0 => row[column..].fill(blank),
+            1 => row[..=column].fill(blank),
+            2 => row.fill(blank),
+            _ => { }
+        }
+    }
+
+    fn set_alternate_screen(&mut self, on: bool) {
+        match (on, self.main.is_some()) {
+            (true, false) => {
+                self.saved_cursor = (self.cursor, self.style);
+                let alternate = vec![ self.blank_row(); self.height ];
+                self.main = Some(std::mem::replace(&mut self.rows, alternate));
+            },
+            (false, true) => {
+                self.rows = self.main.take().unwrap();
+                let (cursor, style) = self.saved_cursor;
+                self.style = style;
+                self.move_to(cursor.0, cursor.1);
+            },
+            _ => { }
+        }
+    }
+
+    fn set_private_mode(&mut self, mode: u16, on: bool) {
+        match mode {
+            1 => self.application_cursor = on,
+            25 => self.cursor_visible = on,
+            47 | 1047 | 1049 => self.set_alternate_screen(on),
+            2004 => self.bracketed_paste = on,
+            _ => debug!("vt: unhandled private mode {} {}", mode, on),
+        }
+    }
+
+    /// Select Graphic Rendition: colors and attributes for what is printed
+    /// next. Parameters come in groups, so `38;5;n` and `38:5:n` both work,
+    /// as do `38;2;r;g;b`, `38:2:r:g:b` and `38:2::r:g:b`.
+    fn select_graphic_rendition(&mut self, params: &[Vec<u16>]) {
+        let mut params = params.iter();
+
+        while let Some(group) = params.next() {
+            let attribute = |style: &mut ContentStyle, attribute: Attribute, on: bool| {
+                if on {
+                    style.attributes.set(attribute);
+                } else {
+                    style.attributes.unset(attribute);
+                }
+            };
+
+            let style = &mut self.style;
+
+            match group[0] {
+                0 => *style = ContentStyle::default(),
+                1 => attribute(style, Attribute::Bold, true),
+                2 => attribute(style, Attribute::Dim, true),
+                3 => attribute(style, Attribute::Italic, true),
+                4 => attribute(style, Attribute::Underlined, true),
+                5 => attribute(style, Attribute::SlowBlink, true),
+                7 => attribute(style, Attribute::Reverse, true),
+                8 => attribute(style, Attribute::Hidden, true),
+                9 => attribute(style, Attribute::CrossedOut, true),
+                22 => {
+                    attribute(style, Attribute::Bold, false);
+                    attribute(style, Attribute::Dim, false);
+                },
+                23 => attribute(style, Attribute::Italic, false),
+                24 => attribute(style, Attribute::Underlined, false),
+                25 => attribute(style, Attribute::SlowBlink, false),
+                27 => attribute(style, Attribute::Reverse, false),
+                28 => attribute(style, Attribute::Hidden, false),
+                29 => attribute(style, Attribute::CrossedOut, false),
+                n @ 30..=37 => style.foreground_color = Some(ansi_color(n - 30)),
+                n @ 90..=97 => style.foreground_color = Some(ansi_color(n - 90 + 8)),
+                n @ 40..=47 => style.background_color = Some(ansi_color(n - 40)),
+                n @ 100..=107 => style.background_color = Some(ansi_color(n - 100 + 8)),
+                39 => style.foreground_color = None,
+                49 => style.background_color = None,
+                n @ (38 | 48) => {
+                    let rest: Vec<u16> = match group[..] {
+                        [ _ ] => {
+                            let kind = params.next().map_or(0, |p| p[0]);
+                            let count = if kind == 2 { 3 } else { 1 };
+
+                            std::iter::once(kind)
+                                .chain(params.by_ref().take(count).map(|p| p[0]))
+                                .collect()
+                        },
+                        // The color space xterm allows before the components
+                        [ _, 2, _, r, g, b, .. ] => vec![ 2, r, g, b ],
+                        _ => group[1..].to_vec(),
+                    };
+
+                    let color = match rest[..] {
+                        [ 5, index, .. ] => Color::AnsiValue(index as u8),
+                        [ 2, r, g, b, .. ] => Color::Rgb { r: r as u8, g: g as u8, b: b as u8 },
+                        _ => continue,
+                    };
+
+                    if n == 38 {
+                        style.foreground_color = Some(color);
+                    } else {
+                        style.background_color = Some(color);
+                    }
+                },
+                n => debug!("vt: unhandled SGR {}", n),
+            }
+        }
+    }
+}
+
+/// One of the 16 colors programs pick by number.
+fn ansi_color(index: u16) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::DarkRed,
+        2 => Color::DarkGreen,
+        3 => Color::DarkYellow,
+        4 => Color::DarkBlue,
+        5 => Color::DarkMagenta,
+        6 => Color::DarkCyan,
+        7 => Color::Grey,
+        8 => Color::DarkGrey,
+        9 => Color::Red,
+        10 => Color::Green,
+        11 => Color::Yellow,
+        12 => Color::Blue,
+        13 => Color::Magenta,
+        14 => Color::Cyan,
+        _ => Color::White,
+    }
+}
+
+impl Perform for Grid {
+    fn print(&mut self, c: char) {
+        if self.wrap_pending {
+            self.cursor.0 = 0;
+            self.line_feed();
+        }
+
+        let (column, row) = self.cursor;
+        self.rows[row][column] = Cell { c, style: self.style };
+
+        if column + 1 == self.width {
+            self.wrap_pending = true;
+        } else {
+            self.cursor.0 += 1;
+        }
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            // Backspace
+            0x08 => self.move_to(self.cursor.0.saturating_sub(1), self.cursor.1),
+            // Tab, to the next multiple of 8
+            0x09 => self.move_to((self.cursor.0 / 8 + 1) * 8, self.cursor.1),
+            // Line feed, vertical tab and form feed
+            0x0a..=0x0c => self.line_feed(),
+            // Carriage return
+            0x0d => self.move_to(0, self.cursor.1),
+            _ => { }
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        if ignore {
+            return;
+        }
+
+        let params: Vec<Vec<u16>> = params.iter().map(|p| p.to_vec()).collect();
+        // The first parameter, with 0 or a missing one meaning 1, for counts
+        // and positions
+        let count = |i: usize| params.get(i).map_or(1, |p| p[0].max(1)) as usize;
+        let mode = params.first().map_or(0, |p| p[0]);
+        let (column, row) = self.cursor;
+
+        if intermediates == b"?" {
+            match action {
+                'h' | 'l' => {
+                    for param in &params {
+                        self.set_private_mode(param[0], action == 'h');
+                    }
+                },
+                _ => debug!("vt: unhandled private CSI {}", action),
+            }
+
+            return;
+        }
+
+        if !intermediates.is_empty() {
+            return;
+        }
+
+        match action {
+            'A' => self.move_to(column, row.saturating_sub(count(0))),
+            'B' | 'e' => self.move_to(column, row + count(0)),
+            'C' | 'a' => self.move_to(column + count(0), row),
+            'D' => self.move_to(column.saturating_sub(count(0)), row),
+            'E' => self.move_to(0, row + count(0)),
+            'F' => self.move_to(0, row.saturating_sub(count(0))),
+            'G' | '`' => self.move_to(count(0) - 1, row),
+            'd' => self.move_to(column, count(0) - 1),
+            'H' | 'f' => self.move_to(count(1) - 1, count(0) - 1),
+            'J' => self.erase_in_display(mode),
+            'K' => self.erase_in_line(mode),
+            'L' => self.shift_lines(count(0), true),
+            'M' => self.shift_lines(count(0), false),
+            '@' => {
+                let blank = self.blank();
+                let line = &mut self.rows[row];
+                let count = count(0).min(self.width - column);
+
+                line[column..].rotate_right(count);
+                line[column..column + count].fill(blank);
+            },
+            'P' => {
+                let blank = self.blank();
+                let line = &mut self.rows[row];
+                let count = count(0).min(self.width - column);
+
+                line[column..].rotate_left(count);
+                line[self.width - count..].fill(blank);
+            },
+            'X' => {
+                let blank = self.blank();
+                let end = (column + count(0)).min(self.width);
+
+                self.rows[row][column..end].fill(blank);
+            },
+            'S' => self.scroll_up(count(0)),
+            'T' => self.scroll_down(count(0)),
+            'm' if params.is_empty() => self.style = ContentStyle::default(),
+            'm' => self.select_graphic_rendition(&params),
+            'r' => {
+                let top = count(0) - 1;
+                let bottom = params.get(1).map_or(self.height, |p| if p[0] == 0 { self.height } else { p[0] as usize });
+                let bottom = bottom.min(self.height) - 1;
+
+                if top < bottom {
+                    self.scroll_region = (top, bottom);
+                    self.move_to(0, 0);
+                }
+            },
+            's' => self.saved_cursor = (self.cursor, self.style),
+            'u' => {
+                let ((column, row), style) = self.saved_cursor;
+                self.style = style;
+                self.move_to(column, row);
+            },
+            'n' => match mode {
+                5 => self.replies.extend_from_slice(b"\x1b[0n"),
+                6 => self.replies.extend(format!("\x1b[{};{}R", row + 1, column + 1).bytes()),
+                _ => { }
+            },
+            // A VT100 with advanced video
+            'c' => self.replies.extend_from_slice(b"\x1b[?1;2c"),
+            _ => debug!("vt: unhandled CSI {:?} {}", params, action),
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        // Character set designations, which are all taken to be UTF-8
+        if !intermediates.is_empty() {
+            return;
+        }
+
+        match byte {
+            b'7' => self.saved_cursor = (self.cursor, self.style),
+            b'8' => {
+                let ((column, row), style) = self.saved_cursor;
+                self.style = style;
+                self.move_to(column, row);
+            },
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.line_feed();
+                self.cursor.0 = 0;
+            },
+            b'M' => self.reverse_index(),
+            b'c' => *self = Grid::new(self.width, self.height),
+            _ => { }
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        // Window titles
+        if let [ b"0" | b"2", title, .. ] = params {
+            self.title = String::from_utf8_lossy(title).into_owned();
+        }
+    }
+}
+
+/// What a program running in a terminal has drawn.
+pub struct Screen {
+    parser: Parser,
+    grid: Grid,
+}
+
+impl Screen {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { parser: Parser::new(), grid: Grid::new(width, height) }
+    }
+
+    /// Applies output from the program.
+    pub fn process(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.parser.advance(&mut self.grid, *byte);
+        }
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.grid.resize(width, height);
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.grid.width, self.grid.height)
+    }
+
+    /// Where the cursor is as a `(column, row)`, or `None` if the program
+    /// hid it.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.grid.cursor_visible.then_some(self.grid.cursor)
+    }
+
+    /// The title the program last set, if it set one.
+    pub fn title(&self) -> &str {
+        &self.grid.title
+    }
+
+    /// Answers to the program's queries since the last call, to be written
+    /// back to it.
+    pub fn take_replies(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.grid.replies)
+    }
+
+    /// Every row, with runs of cells in the same style grouped together.
+    pub fn lines(&self) -> Vec<StyledContent> {
+        self.grid.rows.iter()
+            .map(|row| {
+                let mut line = StyledContent::new();
+                let mut run = String::new();
+                let mut style = row[0].style;
+
+                for cell in row {
+                    if cell.style != style {
+                        line.push(std::mem::take(&mut run), style);
+                        style = cell.style;
+                    }
+
+                    run.push(cell.c);
+                }
+
+                line.push(run, style);
+                line
+            })
+            .collect()
+    }
+
+    /// The bytes a terminal sends for pasted text.
+    pub fn paste_bytes(&self, text: &str) -> Vec<u8> {
+        // Terminals send Enter as a carriage return, pasted or not
+        let text = text.replace("\r\n", "\r").replace('\n', "\r");
+
+        if self.grid.bracketed_paste {
+            format!("\x1b[200~{}\x1b[201~", text).into_bytes()
+        } else {
+            text.into_bytes()
+        }
+    }
+
+    /// The bytes a terminal sends for a key.
+    pub fn key_bytes(&self, code: KeyCode, modifiers: KeyModifiers) -> Vec<u8> {
+        let arrow = |c: u8| if self.grid.application_cursor { vec![ 0x1b, b'O', c ] } else { vec![ 0x1b, b'[', c ] };
+
+        let bytes = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => match c {
+                'a'..='z' | 'A'..='Z' => vec![ c.to_ascii_lowercase() as u8 & 0x1f ],
+                ' ' | '@' | '2' => vec![ 0 ],
+                '[' | '3' => vec![ 0x1b ],
+                '\\' | '4' => vec![ 0x1c ],
+                ']' | '5' => vec![ 0x1d ],
+                '^' | '6' => vec![ 0x1e ],
+                '_' | '7' | '/' => vec![ 0x1f ],
+                _ => c.to_string().into_bytes(),
+            },
+            KeyCode::Char(c) => c.to_string().into_bytes(),
+            KeyCode::Enter => vec![ b'\r' ],
+            KeyCode::Backspace => vec![ 0x7f ],
+            KeyCode::Tab => vec![ b'\t' ],
+            KeyCode::BackTab => b"\x1b[Z".to_vec(),
+            KeyCode::Esc => vec![ 0x1b ],
+            KeyCode::Up => arrow(b'A'),
+            KeyCode::Down => arrow(b'B'),
+            KeyCode::Right => arrow(b'C'),
+            KeyCode::Left => arrow(b'D'),
+            KeyCode::Home => arrow(b'H'),
+            KeyCode::End => arrow(b'F'),
+            KeyCode::Insert => b"\x1b[2~".to_vec(),
+            KeyCode::Delete => b"\x1b[3~".to_vec(),
+            KeyCode::PageUp => b"\x1b[5~".to_vec(),
+            KeyCode::PageDown => b"\x1b[6~".to_vec(),
+            KeyCode::F(n @ 1..=4) => vec![ 0x1b, b'O', b'P' + n - 1 ],
+            KeyCode::F(n) => {
+                let code = match n {
+                    5 => 15,
+                    6..=10 => n + 11,
+                    11..=12 => n + 12,
+                    _ => return Vec::new(),
+                };
+
+                format!("\x1b[{}~", code).into_bytes()
+            },
+            _ => return Vec::new(),
+        };
+
+        // Alt sends Escape first
+        if modifiers.contains(KeyModifiers::ALT) {
+            std::iter::once(0x1b).chain(bytes).collect()
+        } else {
+            bytes
+        }
+    }
+}
+
+impl std::fmt::Debug for Screen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Screen")
+            .field("width", &self.grid.width)
+            .field("height", &self.grid.height)
+            .field("cursor", &self.grid.cursor)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex, mpsc::{ self, Receiver } };
+
+use notify::{ Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher };
+
+use crate::events::Waker;
+
+type WatchedFiles = Arc<Mutex<HashMap<PathBuf, PathBuf>>>;
+
+/// Notices when other programs change files. The directories holding the
+/// files are watched rather than the files themselves, so files replaced by
+/// a rename, as formatters and `git checkout` do, are still noticed.
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    // Canonical paths of the watched files, to the paths they were watched by
+    files: WatchedFiles,
+    // How many watched files each watched directory holds
+    dirs: HashMap<PathBuf, usize>,
+    receiver: Receiver<PathBuf>,
+}
+
+impl FileWatcher {
+    /// `waker` is woken whenever a watched file changes.
+    pub fn new(waker: Option<Waker>) -> Result<Self, Box<dyn Error>> {
+        let (sender, receiver) = mpsc::channel();
+        let files = WatchedFiles::default();
+        let watched = Arc::clone(&files);
+
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("watcher: {}", e);
+                    return;
+                }
+            };
+
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+
+            // Everything else in the directories is ignored here, so it
+            // doesn't wake the main loop
+            let changed: Vec<PathBuf> = {
+                let files = watched.lock().unwrap();
+                event.paths.iter().filter_map(|p| files.get(p).cloned()).collect()
+            };
+
+            if changed.is_empty() {
+                return;
+            }
+
+            for path in changed {
+                let _ = sender.send(path);
+            }
+
+            if let Some(waker) = &waker {
+                waker.wake();
+            }
+        })?;
+
+        Ok(Self { watcher, files, dirs: HashMap::new(), receiver })
+    }
+
+    pub fn watch(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let (dir, canonical) = canonicalize(path)?;
+
+        if self.files.lock().unwrap().contains_key(&canonical) {
+            return Ok(());
+        }
+
+        if !self.dirs.contains_key(&dir) {
+            self.watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        }
+
+        *self.dirs.entry(dir).or_default() += 1;
+        self.files.lock().unwrap().insert(canonical, path.to_path_buf());
+
+        Ok(())
+    }
+
+    pub fn unwatch(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let (dir, canonical) = canonicalize(path)?;
+
+        if self.files.lock().unwrap().remove(&canonical).is_none() {
+            return Ok(());
+        }
+
+        if let Some(count) = self.dirs.get_mut(&dir) {
+            *count -= 1;
+
+            if *count == 0 {
+                self.dirs.remove(&dir);
+                self.watcher.unwatch(&dir)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Every watched file changed since the last call, once each, as the
+    /// paths they were watched by.
+    pub fn poll(&self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = Vec::new();
+
+        for path in self.receiver.try_iter() {
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+
+        changed
+    }
+}
+
+impl std::fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWatcher")
+            .field("dirs", &self.dirs)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The canonical path of `path`'s directory, and of `path` in it. The file
+/// itself doesn't have to exist.
+fn canonicalize(path: &Path) -> Result<(PathBuf, PathBuf), Box<dyn Error>> {
+    let name = path.file_name().ok_or_else(|| format!("{} is not a file", path.display()))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let dir = dir.canonicalize()?;
+    let file = dir.join(name);
+
+    Ok((dir, file))
+}
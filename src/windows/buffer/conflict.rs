@@ -0,0 +1,174 @@
+//! Asking what to do when the visible document changed on disk while it had
+//! changes of its own: keep the buffer, reload, or look at the difference
+//! first.
+
+use std::path::Path;
+
+use crossterm::{style::{ ContentStyle, Color, Stylize }, event::KeyCode};
+use ropey::Rope;
+
+use crate::diff::{ self, Change };
+use crate::search::line_content;
+use crate::text::{ self, Encoding };
+use crate::ui::window::StyledContent;
+
+use super::Buffer;
+
+/// The visible document changed on disk while it had changes of its own.
+#[derive(Debug)]
+pub(super) struct Conflict {
+    // What's on disk now, unless the document is too large to read it all
+    // just to ask
+    disk: Option<(Rope, Encoding)>,
+    // The diff from the document to the disk while it's being looked at,
+    // and how far it's scrolled
+    diff: Option<(Vec<StyledContent>, usize)>,
+}
+
+impl Conflict {
+    /// What to ask about `path` in the command gutter.
+    pub(super) fn prompt(&self, path: &Path) -> String {
+        let path = path.display();
+
+        match (&self.disk, &self.diff) {
+            (_, Some(_)) => "- buffer, + disk: (k)eep buffer, (r)eload, (d)iff off?".to_string(),
+            (Some(_), None) => format!("{} changed on disk: (k)eep buffer, (r)eload, (d)iff?", path),
+            (None, None) => format!("{} changed on disk: (k)eep buffer, (r)eload?", path),
+        }
+    }
+
+    /// The lines of the diff in a view `height` lines high, while it's
+    /// being looked at.
+    pub(super) fn visible_diff(&self, height: usize) -> Option<Vec<StyledContent>> {
+        let (lines, scroll) = self.diff.as_ref()?;
+
+        Some(lines.iter().skip(*scroll).take(height).cloned().collect())
+    }
+}
+
+/// Lines of context kept around each change in a diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// The changes turning `old` into `new`, a line each, with a few lines of
+/// context around them.
+fn diff_lines(old: &Rope, new: &Rope) -> Vec<StyledContent> {
+    let lines = |text: &Rope| -> Vec<String> {
+        (0..text::line_count(text))
+            .map(|i| line_content(text.line(i)).into_owned())
+            .collect()
+    };
+
+    let (old, new) = (lines(old), lines(new));
+    let old: Vec<&str> = old.iter().map(String::as_str).collect();
+    let new: Vec<&str> = new.iter().map(String::as_str).collect();
+
+    let changes = diff::diff(&old, &new);
+    let context = ContentStyle::default().with(Color::Grey);
+
+    if changes.iter().all(|c| matches!(c, Change::Equal(_))) {
+        return vec![ StyledContent::from_styled("Only the line endings differ".to_string(), context) ];
+    }
+
+    // Whether each line is close enough to a change to be shown
+    let mut shown = vec![ false; changes.len() ];
+
+    for (i, change) in changes.iter().enumerate() {
+        if !matches!(change, Change::Equal(_)) {
+            let end = (i + DIFF_CONTEXT + 1).min(changes.len());
+            shown[i.saturating_sub(DIFF_CONTEXT)..end].fill(true);
+        }
+    }
+
+    let mut lines = Vec::new();
+
+    for (i, change) in changes.iter().enumerate() {
+        if !shown[i] {
+            if i == 0 || shown[i - 1] {
+                lines.push(StyledContent::from_styled("···".to_string(), ContentStyle::default().with(Color::DarkGrey)));
+            }
+
+            continue;
+        }
+
+        let (prefix, line, style) = match change {
+            Change::Equal(line) => (' ', line, context),
+            Change::Delete(line) => ('-', line, ContentStyle::default().with(Color::Red)),
+            Change::Insert(line) => ('+', line, ContentStyle::default().with(Color::Green)),
+        };
+
+        lines.push(StyledContent::from_styled(format!("{} {}", prefix, line), style));
+    }
+
+    lines
+}
+
+impl Buffer {
+    /// Asks what to do if the visible document changed on disk while it had
+    /// changes of its own.
+    pub(super) fn check_conflict(&mut self) {
+        if !self.document.changed_on_disk || self.conflict.is_some() {
+            return;
+        }
+
+        if self.document.large {
+            self.conflict = Some(Conflict { disk: None, diff: None });
+            return;
+        }
+
+        match self.document.read_from_disk() {
+            Ok((disk, encoding)) if disk != self.document.content => {
+                self.conflict = Some(Conflict { disk: Some((disk, encoding)), diff: None });
+            },
+            Ok(_) => self.document.changed_on_disk = false,
+            Err(e) => debug!("Can't read {}: {}", self.document.path.display(), e),
+        }
+    }
+
+    pub(super) fn handle_conflict_input(&mut self, code: KeyCode) {
+        let height = self.view_height();
+        let Some(conflict) = &mut self.conflict else { return };
+
+        match code {
+            KeyCode::Char('k') => {
+                self.conflict = None;
+                self.document.changed_on_disk = false;
+                self.message = "Kept the buffer, which differs from the file on disk".to_string();
+            },
+            KeyCode::Char('r') => {
+                match self.conflict.take().unwrap().disk {
+                    Some((disk, encoding)) => {
+                        self.document.reload(disk, encoding);
+                        self.message = format!("\"{}\" reloaded", self.document.path.display());
+                    },
+                    None => {
+                        self.document.reload_in_background();
+                        self.document.start_loading(self.waker.clone());
+                    },
+                }
+
+                self.scroll_to_cursor();
+            },
+            KeyCode::Char('d') => {
+                let Some((disk, _)) = &conflict.disk else { return };
+
+                conflict.diff = match conflict.diff {
+                    Some(_) => None,
+                    None => Some((diff_lines(&self.document.content, disk), 0)),
+                };
+            },
+            KeyCode::Down | KeyCode::PageDown | KeyCode::Up | KeyCode::PageUp => {
+                let Some((lines, scroll)) = &mut conflict.diff else { return };
+                let step = if matches!(code, KeyCode::PageDown | KeyCode::PageUp) { height } else { 1 };
+
+                *scroll = match code {
+                    KeyCode::Down | KeyCode::PageDown => (*scroll + step).min(lines.len().saturating_sub(1)),
+                    _ => scroll.saturating_sub(step),
+                };
+            },
+            KeyCode::Esc => {
+                conflict.diff = None;
+            },
+            _ => { }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Comparing open documents with `HEAD` in the background once edits
+//! pause, and jumping to, previewing and reverting the hunks found.
+
+use std::collections::HashMap;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+
+use crate::document::Document;
+use crate::git::{ self, Hunk };
+use crate::history::Transaction;
+use crate::jobs::JobRequest;
+use crate::search::line_content;
+use crate::text;
+use crate::ui::window::Window;
+use crate::windows::HunkPreview;
+use crate::{ AppState, Message };
+
+use super::Buffer;
+
+/// How long changes have to pause before they're compared with `HEAD`.
+const HUNK_DELAY: Duration = Duration::from_millis(250);
+
+/// Files as they are in `HEAD`, `None` for ones that aren't committed.
+pub(super) type HeadTexts = Arc<Mutex<HashMap<PathBuf, Option<Arc<String>>>>>;
+
+/// Documents waiting for changes to pause before they're compared with
+/// `HEAD`.
+#[derive(Debug)]
+pub(super) struct PendingDiff {
+    // The latest revision of any of them, to tell when there are more changes
+    revision: u64,
+    due: Instant,
+    // When the last timer asked for goes off
+    timer: Instant,
+}
+
+/// Whether `document` has changed since it was last compared with `HEAD`,
+/// and can be.
+fn needs_diff(document: &Document) -> bool {
+    !document.large
+        && !document.is_loading()
+        && !document.path.as_os_str().is_empty()
+        && document.diffed_revision != Some(document.history.revision())
+}
+
+impl Buffer {
+    /// Compares every document that has changed since it last was with
+    /// `HEAD`, in the background, once the changes pause. Large documents are
+    /// left out.
+    pub(super) fn update_hunks(&mut self) {
+        let latest = std::iter::once(&self.document).chain(&self.background)
+            .filter(|d| needs_diff(d))
+            .map(|d| d.history.revision())
+            .max();
+
+        let Some(latest) = latest else {
+            self.pending_diff = None;
+            return;
+        };
+
+        let now = Instant::now();
+        let pending = self.pending_diff.get_or_insert(PendingDiff { revision: latest, due: now + HUNK_DELAY, timer: now });
+
+        if pending.revision != latest {
+            pending.revision = latest;
+            pending.due = now + HUNK_DELAY;
+        }
+
+        if now < pending.due {
+            // The timer went off before more changes pushed the diff back
+            if pending.timer <= now {
+                pending.timer = pending.due;
+                self.timeouts.push(pending.due - now);
+            }
+
+            return;
+        }
+
+        self.pending_diff = None;
+
+        for document in std::iter::once(&mut self.document).chain(&mut self.background) {
+            if !needs_diff(document) {
+                continue;
+            }
+
+            let revision = document.history.revision();
+            document.diffed_revision = Some(revision);
+
+            let (path, content, encoding) = (document.path.clone(), document.content.clone(), document.encoding);
+            let head_texts = Arc::clone(&self.head_texts);
+
+            let request = JobRequest::new("git diff", move |context| {
+                let cached = head_texts.lock().unwrap().get(&path).cloned();
+                let head = cached.unwrap_or_else(|| {
+                    let head = git::head_text(&path, encoding).map(Arc::new);
+                    head_texts.lock().unwrap().insert(path.clone(), head.clone());
+                    head
+                });
+
+                if context.is_cancelled() {
+                    return;
+                }
+
+                let hunks = match head {
+                    Some(head) => {
+                        let old: Vec<&str> = head.lines().collect();
+                        let new: Vec<String> = (0..text::line_count(&content))
+                            .map(|i| line_content(content.line(i)).into_owned())
+                            .collect();
+
+                        git::hunks(&old, &new.iter().map(String::as_str).collect::<Vec<_>>())
+                    },
+                    None => Vec::new(),
+                };
+
+                if !context.is_cancelled() {
+                    context.send(Message::SetHunks { path, revision, hunks });
+                }
+            });
+
+            if let Some(running) = self.hunk_jobs.insert(document.path.clone(), request.handle()) {
+                running.cancel();
+            }
+
+            self.jobs.push(request);
+        }
+    }
+
+    /// Moves the cursor to the start of the next hunk, or the previous one.
+    pub(super) fn jump_to_hunk(&mut self, state: &AppState, forward: bool) {
+        let line = self.document.cursor.1;
+        let hunks = state.hunks.get(&self.document.path).map_or(&[][..], Vec::as_slice);
+
+        let hunk = if forward {
+            hunks.iter().find(|h| h.start > line)
+        } else {
+            hunks.iter().rev().find(|h| h.start < line && !h.on_line(line))
+        };
+
+        match hunk {
+            Some(hunk) => {
+                self.document.cursor = (0, hunk.start.min(self.last_line()));
+                self.message = format!("Hunk {} of {}", hunks.iter().position(|h| h == hunk).unwrap() + 1, hunks.len());
+            },
+            None if hunks.is_empty() => self.message = "No changes since HEAD".to_string(),
+            None => self.message = "No more hunks".to_string(),
+        }
+    }
+
+    /// Opens a popup showing how the hunk at the cursor differs from `HEAD`.
+    pub(super) fn preview_hunk(&mut self, state: &AppState) {
+        let line = self.document.cursor.1;
+        let hunk = state.hunks.get(&self.document.path).and_then(|hunks| hunks.iter().find(|h| h.on_line(line)));
+
+        let Some(hunk) = hunk else {
+            self.message = "No changes here".to_string();
+            return;
+        };
+
+        let new: Vec<String> = (hunk.start..hunk.start + hunk.len)
+            .map(|i| line_content(self.document.content.line(i)).into_owned())
+            .collect();
+
+        self.popups.push(HunkPreview::new(self.document.path.clone(), hunk.clone(), &new).boxed());
+    }
+
+    /// Puts back what `HEAD` has in place of `hunk` in the document for
+    /// `path`, as one change.
+    pub(super) fn revert_hunk(&mut self, path: &Path, hunk: &Hunk) {
+        let document = match self.background.iter_mut().find(|d| d.path == path) {
+            Some(document) => document,
+            None if self.document.path == path => &mut self.document,
+            None => return,
+        };
+
+        if document.is_loading() {
+            return;
+        }
+
+        let content = &document.content;
+        let len = content.len_chars();
+        let lines = content.len_lines();
+        let start = content.line_to_char(hunk.start.min(lines));
+        let end = content.line_to_char((hunk.start + hunk.len).min(lines));
+
+        let newline = if document.line_ending == text::LineEnding::Crlf { "\r\n" } else { "\n" };
+        let ends_with_newline = len > 0 && content.char(len - 1) == '\n';
+        let mut text = hunk.old.join(newline);
+
+        // Keep the file ending the way it does
+        if !hunk.old.is_empty() {
+            if end < len || ends_with_newline {
+                text.push_str(newline);
+            } else if start == len && len > 0 {
+                text.insert_str(0, newline);
+            }
+        }
+
+        let mut transaction = Transaction::new(document.cursor);
+        transaction.replace(&mut document.content, start..end, &text);
+
+        document.cursor = (0, hunk.start.min(text::line_count(&document.content).saturating_sub(1)));
+        transaction.set_cursor_after(document.cursor);
+        document.history.commit(transaction);
+
+        if self.document.path == path {
+            self.scroll_to_cursor();
+        }
+    }
+}
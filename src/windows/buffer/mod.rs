@@ -0,0 +1,2227 @@
+use std::{path::{Path, PathBuf}, error::Error, fmt::Display, ops::{Range, RangeInclusive}};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crossterm::{style::{ ContentStyle, Color, Stylize, Attribute }, event::{KeyCode, KeyModifiers}};
+use ropey::Rope;
+
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent },
+};
+use crate::cargo;
+use crate::command::{ self, Command };
+use crate::cursors::{ self, CursorEdits };
+use crate::diagnostics::{ DiagnosticProvider, Report, Severity };
+use crate::document::Document;
+use crate::events::Waker;
+use crate::history::Transaction;
+use crate::jobs::{ JobHandle, JobRequest };
+use crate::keys::{ self, Action, InsertAt, Keys, Target, VisualAction };
+use crate::lsp::{ LanguageServers, LspEvent, ServerConfig };
+use crate::motion::{ self, Motion, Operator, Span, TextObject };
+use crate::registers::{ self, Register, RegisterKind };
+use crate::search::{ line_content, Search, SearchDirection, SearchMatch, SearchOptions };
+use crate::selection::{ Selection, SelectionKind };
+use crate::substitute::{ PendingReplacement, Substitution };
+use crate::text;
+use crate::watcher::FileWatcher;
+use crate::windows::{ Completion, Hover };
+use crate::{ AppState, CursorPosition, FileStatus, Message, Mode };
+
+mod conflict;
+mod hunks;
+mod substitution;
+
+use conflict::Conflict;
+use hunks::{ HeadTexts, PendingDiff };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    Search(SearchDirection),
+    Command,
+}
+
+/// Text being typed into the command gutter on the buffer's behalf.
+#[derive(Debug)]
+struct Prompt {
+    kind: PromptKind,
+    text: String,
+    origin: (usize, usize),
+    origin_scroll: usize,
+}
+
+/// What `>` adds to the start of a line, and `<` takes away.
+const INDENT: &str = "    ";
+
+/// A cursor, and whether it's the document's own.
+type Cursor = ((usize, usize), bool);
+
+/// Typing going into the text, until Esc.
+#[derive(Debug)]
+struct Insert {
+    // The revision of the change made since insert mode started, which the
+    // rest of the typing joins so it's all undone at once
+    revision: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct Buffer {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    document: Document,
+    // Open files other than `document`
+    background: Vec<Document>,
+    search_options: SearchOptions,
+    search: Option<Search>,
+    prompt: Option<Prompt>,
+    substitution: Option<(Substitution, PendingReplacement)>,
+    conflict: Option<Conflict>,
+    watcher: Option<FileWatcher>,
+    watching: bool,
+    waker: Option<Waker>,
+    servers: LanguageServers,
+    // Besides the language servers
+    providers: Vec<Box<dyn DiagnosticProvider>>,
+    // Hovers and completion menus waiting to be opened
+    popups: Vec<Box<dyn Window<AppState>>>,
+    jobs: Vec<JobRequest<AppState>>,
+    timeouts: Vec<Duration>,
+    pending_diff: Option<PendingDiff>,
+    // The comparison with `HEAD` running for each path, cancelled when a
+    // newer one starts
+    hunk_jobs: HashMap<PathBuf, JobHandle>,
+    // Shared with the comparisons, and started over whenever git's status
+    // changes
+    head_texts: HeadTexts,
+    // The command being typed
+    keys: Keys,
+    // The register the running command's yank, delete or put uses
+    register: Option<char>,
+    insert: Option<Insert>,
+    selection: Option<Selection>,
+    // Cursors besides the document's own, which every edit is made at too
+    cursors: Vec<(usize, usize)>,
+    // The change being built up while an edit is made at every cursor
+    batch: Option<Transaction>,
+    highlight_search: bool,
+    message: String,
+    generation: u64,
+}
+
+impl Buffer {
+    pub fn new_empty_buffer(info: WindowInfo) -> Self {
+        Buffer {
+            info,
+            bounds: None,
+            document: Document::new(),
+            background: Vec::new(),
+            search_options: SearchOptions::default(),
+            search: None,
+            prompt: None,
+            substitution: None,
+            conflict: None,
+            watcher: None,
+            watching: false,
+            waker: None,
+            servers: LanguageServers::default(),
+            providers: Vec::new(),
+            popups: Vec::new(),
+            jobs: Vec::new(),
+            timeouts: Vec::new(),
+            pending_diff: None,
+            hunk_jobs: HashMap::new(),
+            head_texts: HeadTexts::default(),
+            keys: Keys::new(),
+            register: None,
+            insert: None,
+            selection: None,
+            cursors: Vec::new(),
+            batch: None,
+            highlight_search: false,
+            message: String::new(),
+            generation: 0,
+        }
+    }
+
+    pub fn new<F>(info: WindowInfo, filepath: F) -> Result<Self, Box<dyn Error>>
+    where F: Into<PathBuf> + Display {
+        let mut b = Self::new_empty_buffer(info);
+        b.load_file(filepath)?;
+
+        Ok(b)
+    }
+
+    pub fn search_options(self, search_options: SearchOptions) -> Self {
+        Self { search_options, ..self }
+    }
+
+    /// The language servers to start for the files the buffer opens.
+    pub fn language_servers(self, configs: Vec<ServerConfig>) -> Self {
+        Self { servers: LanguageServers::new(configs), ..self }
+    }
+
+    /// Something besides the language servers to find problems with files.
+    pub fn diagnostic_provider(self, provider: Box<dyn DiagnosticProvider>) -> Self {
+        Self { providers: self.providers.into_iter().chain(Some(provider)).collect(), ..self }
+    }
+
+    /// Opens `filepath` and makes it the visible document, replacing the
+    /// current one.
+    pub fn load_file<F>(&mut self, filepath: F) -> Result<(), Box<dyn Error>>
+    where F: Into<PathBuf> + Display {
+        self.document = Document::load(filepath.into())?;
+        self.start_loading();
+        self.watch(&self.document.path.clone());
+
+        Ok(())
+    }
+
+    /// Makes the document for `path` visible, loading it if it isn't open yet.
+    pub fn switch_to(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if self.document.path == path {
+            return Ok(());
+        }
+
+        let document = match self.background.iter().position(|d| d.path == path) {
+            Some(i) => self.background.remove(i),
+            None => {
+                let document = Document::load(path)?;
+                self.watch(path);
+
+                document
+            },
+        };
+
+        // What was confirmed so far is kept, undone as one change
+        if let Some((substitution, _)) = self.substitution.take() {
+            self.finish_substitution(substitution);
+        }
+
+        let previous = std::mem::replace(&mut self.document, document);
+        self.background.push(previous);
+
+        // Anything in progress belonged to the previous document
+        self.prompt = None;
+        self.conflict = None;
+        self.selection = None;
+        self.insert = None;
+        self.cursors.clear();
+        self.start_loading();
+        self.scroll_to_cursor();
+
+        Ok(())
+    }
+
+    /// Starts loading the visible document in the background, if it's large
+    /// and something can wake us as it comes in. Without a waker, `tick`
+    /// starts it instead.
+    fn start_loading(&mut self) {
+        if self.waker.is_some() {
+            self.document.start_loading(self.waker.clone());
+        }
+    }
+
+    /// Takes in whatever every loading document has loaded since the last
+    /// call. Returns whether the visible one changed.
+    fn poll_loaders(&mut self) -> bool {
+        for document in &mut self.background {
+            if let Err(e) = document.poll_loader() {
+                warn!("Can't load {}: {}", document.path.display(), e);
+            }
+        }
+
+        let progress = self.document.load_progress();
+        let path = self.document.path.display().to_string();
+
+        match self.document.poll_loader() {
+            Ok(false) => false,
+            Ok(true) => {
+                self.message = match (self.document.load_progress(), progress) {
+                    (Some((read, total)), _) => format!(
+                        "Loading \"{}\"... {}% ({} of {} MB)",
+                        path,
+                        (read * 100).checked_div(total).unwrap_or(100),
+                        read >> 20,
+                        total >> 20
+                    ),
+                    (None, Some((_, total))) => format!("\"{}\" {}L, {}B", path, self.document.line_count(), total),
+                    (None, None) => String::new(),
+                };
+
+                true
+            },
+            Err(e) => {
+                self.message = format!("Can't load \"{}\": {}", path, e);
+                true
+            }
+        }
+    }
+
+    /// Starts noticing when other programs change any open document.
+    fn start_watching(&mut self, waker: Option<Waker>) {
+        self.watching = true;
+
+        match FileWatcher::new(waker) {
+            Ok(watcher) => {
+                self.watcher = Some(watcher);
+
+                let paths: Vec<PathBuf> = std::iter::once(&self.document)
+                    .chain(&self.background)
+                    .map(|d| d.path.clone())
+                    .collect();
+
+                for path in paths {
+                    self.watch(&path);
+                }
+            },
+            Err(e) => warn!("Can't watch open files: {}", e),
+        }
+    }
+
+    fn watch(&mut self, path: &Path) {
+        let Some(watcher) = &mut self.watcher else { return };
+
+        if path.as_os_str().is_empty() {
+            return;
+        }
+
+        if let Err(e) = watcher.watch(path) {
+            warn!("Can't watch {}: {}", path.display(), e);
+        }
+    }
+
+    /// Catches up with another program changing `path`. Documents without
+    /// changes of their own are reloaded; the rest are marked, to be asked
+    /// about once they're visible.
+    fn file_changed(&mut self, path: &Path) {
+        // A substitution being confirmed has changes of its own, and matches
+        // that wouldn't be where it thinks in the text from disk
+        if self.document.path == path {
+            if let Some((substitution, _)) = self.substitution.take() {
+                self.finish_substitution(substitution);
+            }
+        }
+
+        let waker = self.waker.clone();
+        let document = match self.background.iter_mut().find(|d| d.path == path) {
+            Some(document) => document,
+            None if self.document.path == path => &mut self.document,
+            None => return,
+        };
+
+        // Large documents are only read again once there's no asking, and
+        // what's being loaded is read up to wherever the file ends
+        if document.large {
+            if document.is_loading() || !document.changed_since_read() {
+                return;
+            }
+
+            if document.history.is_modified() {
+                document.changed_on_disk = true;
+                return;
+            }
+
+            document.reload_in_background();
+            document.start_loading(waker);
+            return;
+        }
+
+        let (content, encoding) = match document.read_from_disk() {
+            Ok(read) => read,
+            // Most likely deleted, or halfway through being replaced
+            Err(e) => {
+                debug!("Can't reload {}: {}", path.display(), e);
+                return;
+            }
+        };
+
+        if content == document.content {
+            return;
+        }
+
+        if document.history.is_modified() {
+            document.changed_on_disk = true;
+            return;
+        }
+
+        document.reload(content, encoding);
+
+        if self.document.path == path {
+            self.scroll_to_cursor();
+            self.message = format!("\"{}\" reloaded", path.display());
+        }
+    }
+
+    /// Tells language servers about every document that has changed since
+    /// they last heard. Large documents are left out.
+    fn sync_servers(&mut self) {
+        for document in std::iter::once(&mut self.document).chain(&mut self.background) {
+            let revision = document.history.revision();
+
+            if document.large || document.is_loading() || document.synced_revision == Some(revision) {
+                continue;
+            }
+
+            self.servers.sync(&document.path, &document.content, revision);
+            document.synced_revision = Some(revision);
+        }
+    }
+
+    /// Asks the visible document's language server about the cursor, the
+    /// answer coming back through `tick`.
+    fn ask_server(&mut self, request: fn(&mut LanguageServers, &Path, (usize, usize)) -> bool) {
+        self.sync_servers();
+
+        let path = self.document.path.clone();
+
+        if !request(&mut self.servers, &path, self.document.cursor) {
+            self.message = if self.servers.handles(&path) {
+                "The language server isn't ready yet".to_string()
+            } else {
+                "No language server for this file".to_string()
+            };
+        }
+    }
+
+    /// Acts on something a language server sent. Answers about anywhere
+    /// but where the cursor is now are out of date, and dropped.
+    fn handle_lsp_event(&mut self, event: LspEvent, bus: &mut Bus<Message>) {
+        let here = |path: &Path, position: (usize, usize)| {
+            self.document.path == path && self.document.cursor == position
+        };
+
+        match event {
+            LspEvent::Hover { path, position, text } if here(&path, position) => {
+                if text.is_empty() {
+                    self.message = "Nothing to show here".to_string();
+                } else {
+                    self.popups.push(Hover::new(&text).boxed());
+                }
+            },
+            LspEvent::Completion { path, position, items } if here(&path, position) => {
+                if items.is_empty() {
+                    self.message = "No completions".to_string();
+                } else {
+                    let word = self.word_before_cursor();
+                    self.popups.push(Completion::new(path, items, word).boxed());
+                }
+            },
+            LspEvent::Definition(locations) => {
+                match locations.into_iter().next() {
+                    Some(location) if location.path == self.document.path => {
+                        self.document.cursor = (location.position.0, location.position.1.min(self.last_line()));
+                        self.center_on_cursor();
+                    },
+                    Some(location) => bus.send(Message::Jump { path: location.path, position: location.position }),
+                    None => self.message = "No definition found".to_string(),
+                }
+            },
+            _ => { }
+        }
+    }
+
+    /// Where the word the cursor is at the end of starts and ends, as
+    /// `(column, line)`s. Both are the cursor if it isn't after a word.
+    fn word_before_cursor(&self) -> ((usize, usize), (usize, usize)) {
+        let (column, line) = self.document.cursor;
+        let text = line_content(self.document.content.line(line));
+        let column = column.min(text.chars().count());
+
+        let start = column - text.chars()
+            .take(column)
+            .collect::<Vec<char>>()
+            .iter()
+            .rev()
+            .take_while(|c| c.is_alphanumeric() || **c == '_')
+            .count();
+
+        ((start, line), (column, line))
+    }
+
+    /// Replaces the text between `start` and `end`, two `(column, line)`s,
+    /// in the document for `path`, as one change. The cursor ends up after
+    /// the new text.
+    fn edit(&mut self, path: &Path, start: (usize, usize), end: (usize, usize), text: &str) {
+        let document = match self.background.iter_mut().find(|d| d.path == path) {
+            Some(document) => document,
+            None if self.document.path == path => &mut self.document,
+            None => return,
+        };
+
+        if document.is_loading() {
+            return;
+        }
+
+        let char_idx = |content: &Rope, (column, line): (usize, usize)| {
+            let line = line.min(text::line_count(content).saturating_sub(1));
+            content.line_to_char(line) + column.min(line_content(content.line(line)).chars().count())
+        };
+
+        let range = char_idx(&document.content, start)..char_idx(&document.content, end);
+        let mut transaction = Transaction::new(document.cursor);
+
+        transaction.replace(&mut document.content, range, text);
+
+        document.cursor = end_of(start, text);
+
+        transaction.set_cursor_after(document.cursor);
+        document.history.commit(transaction);
+
+        if self.document.path == path {
+            self.scroll_to_cursor();
+        }
+    }
+
+    /// Replaces `range` of the visible document with `text` as one change,
+    /// then moves the cursor to wherever `cursor` says in the new content.
+    fn replace(&mut self, range: Range<usize>, text: &str, cursor: impl FnOnce(&Rope) -> (usize, usize)) {
+        self.replace_ranges(vec![ (range, text.to_string()) ], cursor);
+    }
+
+    /// Like `replace`, for ranges that don't overlap, all undone together.
+    fn replace_ranges(&mut self, mut edits: Vec<(Range<usize>, String)>, cursor: impl FnOnce(&Rope) -> (usize, usize)) {
+        let batched = self.batch.is_some();
+        let mut transaction = self.batch.take().unwrap_or_else(|| Transaction::new(self.document.cursor));
+
+        // Last first, so the ranges before each edit stay where they were
+        edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+
+        for (range, text) in edits {
+            transaction.replace(&mut self.document.content, range, &text);
+        }
+
+        self.document.cursor = cursor(&self.document.content);
+
+        if batched {
+            self.batch = Some(transaction);
+        } else {
+            transaction.set_cursor_after(self.document.cursor);
+            self.commit(transaction);
+        }
+
+        self.scroll_to_cursor();
+    }
+
+    /// Every cursor, with whether it's the document's own.
+    fn all_cursors(&self) -> Vec<Cursor> {
+        self.cursors.iter()
+            .map(|cursor| (*cursor, false))
+            .chain(std::iter::once((self.document.cursor, true)))
+            .collect()
+    }
+
+    /// Does `edit` at every cursor as one change.
+    fn at_every_cursor(&mut self, mut edit: impl FnMut(&mut Self)) {
+        if self.cursors.is_empty() {
+            edit(self);
+            return;
+        }
+
+        let cursors = self.all_cursors();
+        self.edit_at(cursors, |buffer, _| edit(buffer));
+    }
+
+    /// Has `act` act on what `span` finds at every cursor, as one change.
+    /// Cursors whose spans overlap become one first, so nothing is acted on
+    /// twice.
+    fn at_every_span(
+        &mut self,
+        span: impl Fn(&Rope, (usize, usize)) -> Option<Span>,
+        mut act: impl FnMut(&mut Self, Span),
+    ) {
+        if self.cursors.is_empty() {
+            if let Some(span) = span(&self.document.content, self.document.cursor) {
+                act(self, span);
+            }
+
+            return;
+        }
+
+        let content = &self.document.content;
+        let all = self.all_cursors();
+        let spans: Vec<Option<Span>> = all.iter().map(|(cursor, _)| span(content, *cursor)).collect();
+        let found: Vec<usize> = (0..all.len()).filter(|i| spans[*i].is_some()).collect();
+        let merged = cursors::merge_spans(content, &found.iter().filter_map(|i| spans[*i].clone()).collect::<Vec<_>>());
+
+        // Cursors with nothing to act on stay as they are, and of those
+        // sharing a span only one does, the document's own if it's there
+        let mut kept: Vec<(Cursor, Option<Span>)> = (0..all.len())
+            .filter(|i| spans[*i].is_none())
+            .map(|i| (all[i], None))
+            .collect();
+
+        for (span, members) in merged {
+            let members: Vec<usize> = members.into_iter().map(|m| found[m]).collect();
+            let keep = members.iter().copied().find(|i| all[*i].1).unwrap_or(members[0]);
+
+            kept.push((all[keep], Some(span)));
+        }
+
+        let (cursors, spans): (Vec<_>, Vec<_>) = kept.into_iter().unzip();
+
+        self.edit_at(cursors, |buffer, i| {
+            if let Some(span) = spans[i].clone() {
+                act(buffer, span);
+            }
+        });
+    }
+
+    /// Does `edit` at each of `cursors` as one change, from the last in the
+    /// text to the first, telling it which cursor it's at. The cursors move
+    /// with the text each edit changes, and replace the buffer's.
+    fn edit_at(&mut self, cursors: Vec<Cursor>, mut edit: impl FnMut(&mut Self, usize)) {
+        let register = self.register;
+        let content = &self.document.content;
+        let mut edits = CursorEdits::new(cursors.iter().map(|(cursor, _)| motion::char_index(content, *cursor)).collect());
+
+        self.batch = Some(Transaction::new(self.document.cursor));
+
+        while let Some((i, at)) = edits.next() {
+            let before = self.batch.as_ref().map_or(0, |batch| batch.edits().len());
+
+            self.document.cursor = motion::position(&self.document.content, at);
+            self.register = register;
+            edit(self, i);
+
+            let made = self.batch.as_ref().map_or(&[][..], |batch| &batch.edits()[before..]);
+            edits.edited(made, motion::char_index(&self.document.content, self.document.cursor));
+        }
+
+        let content = &self.document.content;
+        let len = content.len_chars();
+
+        self.cursors.clear();
+
+        for ((_, primary), at) in cursors.iter().zip(edits.positions()) {
+            let position = motion::position(content, (*at).min(len));
+
+            match primary {
+                true => self.document.cursor = position,
+                false => self.cursors.push(position),
+            }
+        }
+
+        if let Some(mut batch) = self.batch.take() {
+            batch.set_cursor_after(self.document.cursor);
+            self.commit(batch);
+        }
+
+        self.merge_cursors();
+        self.scroll_to_cursor();
+    }
+
+    /// Records a change. In insert mode, changes after the first are joined
+    /// to it, unless something else changed the text in between.
+    fn commit(&mut self, transaction: Transaction) {
+        if transaction.is_empty() {
+            return;
+        }
+
+        let history = &mut self.document.history;
+
+        match &mut self.insert {
+            Some(insert) => {
+                match insert.revision == Some(history.revision()) {
+                    true => history.join(transaction),
+                    false => history.commit(transaction),
+                }
+
+                insert.revision = Some(history.revision());
+            },
+            None => history.commit(transaction),
+        }
+    }
+
+    /// Keeps every cursor inside the text, and one of any on the same spot.
+    fn merge_cursors(&mut self) {
+        let last_line = self.last_line();
+        let primary = self.document.cursor;
+
+        for cursor in &mut self.cursors {
+            cursor.1 = cursor.1.min(last_line);
+        }
+
+        self.cursors.sort_by_key(|(column, line)| (*line, *column));
+        self.cursors.dedup();
+        self.cursors.retain(|cursor| *cursor != primary);
+    }
+
+    /// Moves every cursor but the document's own by `motion`.
+    fn move_cursors(&mut self, motion: Motion, count: Option<usize>) {
+        for cursor in &mut self.cursors {
+            if let Some(target) = motion.target(&self.document.content, *cursor, count) {
+                *cursor = target;
+            }
+        }
+
+        self.merge_cursors();
+    }
+
+    /// Adds a cursor on the line below the lowest cursor, or above the
+    /// highest, and makes it the one the view follows.
+    fn add_cursor_beside(&mut self, below: bool) {
+        let all = self.cursors.iter().chain(std::iter::once(&self.document.cursor));
+        let edge = match below {
+            true => all.max_by_key(|(_, line)| *line),
+            false => all.min_by_key(|(_, line)| *line),
+        };
+
+        let &(column, line) = edge.unwrap_or(&self.document.cursor);
+        let line = match below {
+            true if line < self.last_line() => line + 1,
+            false if line > 0 => line - 1,
+            _ => return,
+        };
+
+        self.cursors.push(self.document.cursor);
+        self.document.cursor = (column, line);
+        self.merge_cursors();
+        self.message = format!("{} cursors", self.cursors.len() + 1);
+    }
+
+    /// Adds a cursor at the next match of the word under the cursor, or of
+    /// the selected text, as far into it as the cursor is into its own, and
+    /// makes it the one the view follows.
+    fn add_cursor_at_next_match(&mut self) {
+        let content = &self.document.content;
+        let cursor = self.document.cursor;
+
+        // A word has to match whole, selected text anywhere
+        let (range, whole_word) = match self.selection.take() {
+            Some(selection) if selection.kind == SelectionKind::Char => {
+                (selection.ranges(content, cursor).remove(0), false)
+            },
+            _ => {
+                let word = TextObject::Word { around: false };
+
+                match word.span(content, cursor, None) {
+                    Some(Span::Chars(range)) => (range, true),
+                    _ => return,
+                }
+            },
+        };
+
+        let needle: Vec<char> = content.slice(range.clone()).chars().collect();
+
+        if needle.is_empty() || needle.iter().all(|c| c.is_whitespace()) {
+            return;
+        }
+
+        let text: Vec<char> = content.chars().collect();
+        let offset = motion::char_index(content, cursor).saturating_sub(range.start).min(needle.len() - 1);
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let taken: Vec<usize> = self.cursors.iter()
+            .map(|cursor| motion::char_index(content, *cursor))
+            .collect();
+
+        let matches = |at: usize| {
+            text[at..].starts_with(&needle)
+                && !taken.contains(&(at + offset))
+                && !(whole_word && at > 0 && is_word(text[at - 1]))
+                && !(whole_word && text.get(at + needle.len()).is_some_and(|c| is_word(*c)))
+        };
+
+        // From after the match under the cursor, around past the end
+        let found = (range.end..text.len()).chain(0..range.start).find(|at| matches(*at));
+
+        let Some(at) = found else {
+            self.message = format!("No other match for {}", needle.iter().collect::<String>());
+            return;
+        };
+
+        self.cursors.push(cursor);
+        self.document.cursor = motion::position(content, at + offset);
+        self.merge_cursors();
+        self.message = format!("{} cursors", self.cursors.len() + 1);
+    }
+
+    /// Puts a cursor on every line the selection covers, at the block's
+    /// left edge or the cursor's column, and stops selecting.
+    fn add_cursors_on_selection(&mut self, selection: Selection) {
+        let cursor = self.document.cursor;
+        let column = match selection.kind {
+            SelectionKind::Block => selection.anchor.0.min(cursor.0),
+            _ => cursor.0,
+        };
+
+        self.selection = None;
+        self.document.cursor = (column, cursor.1);
+        self.cursors.extend(selection.lines(cursor).map(|line| (column, line)));
+        self.merge_cursors();
+        self.message = format!("{} cursors", self.cursors.len() + 1);
+    }
+
+    /// Fills the register picked for this command, or the unnamed one.
+    fn yank(&mut self, bus: &mut Bus<Message>, text: String, kind: RegisterKind) {
+        let name = self.register.take();
+
+        if let Some('+' | '*') = name {
+            if let Err(e) = registers::copy_to_clipboard(&text) {
+                self.message = format!("Can't copy to the clipboard: {}", e);
+            }
+        }
+
+        bus.send(Message::SetRegister { name, register: Register::new(text, kind) });
+    }
+
+    /// The text of `count` lines from `line`, each ending in `\n`.
+    fn lines_text(&self, line: usize, count: usize) -> String {
+        let content = &self.document.content;
+        let end = (line + count).min(text::line_count(content));
+
+        (line..end).map(|i| line_content(content.line(i)) + "\n").collect()
+    }
+
+    fn yank_lines(&mut self, bus: &mut Bus<Message>, line: usize, count: usize) {
+        let text = self.lines_text(line, count);
+        let lines = text.matches('\n').count();
+
+        if lines > 2 {
+            self.message = format!("{} lines yanked", lines);
+        }
+
+        self.yank(bus, text, RegisterKind::Linewise);
+    }
+
+    fn delete_lines(&mut self, bus: &mut Bus<Message>, line: usize, count: usize) {
+        if self.document.is_loading() {
+            return;
+        }
+
+        let text = self.lines_text(line, count);
+        let lines = text.matches('\n').count();
+
+        let content = &self.document.content;
+        let end_line = line + lines;
+        let mut start = content.line_to_char(line);
+        let end = content.line_to_char(end_line.min(content.len_lines()));
+
+        // The last lines of a document that doesn't end in a line break take
+        // the one before them with them
+        if end == content.len_chars() && !ends_with_newline(content) && line > 0 {
+            start = content.line_to_char(line - 1) + line_content(content.line(line - 1)).chars().count();
+        }
+
+        self.replace(start..end, "", |content| {
+            let line = line.min(text::line_count(content).saturating_sub(1));
+            (first_non_blank(content, line), line)
+        });
+
+        if lines > 2 {
+            self.message = format!("{} fewer lines", lines);
+        }
+
+        self.yank(bus, text, RegisterKind::Linewise);
+    }
+
+    /// Puts the picked register's text after the cursor, or before it. Lines
+    /// go below or above the cursor's line.
+    fn put(&mut self, state: &AppState, after: bool) {
+        let name = self.register.take();
+
+        let Some(register) = state.registers.get(name) else {
+            self.message = format!("Nothing in register {}", name.unwrap_or('"'));
+            return;
+        };
+
+        if self.document.is_loading() {
+            return;
+        }
+
+        let text = self.document.line_ending.normalize(&register.text);
+        let newline = self.document.line_ending.normalize("\n");
+        let (column, line) = self.document.cursor;
+        let content = &self.document.content;
+
+        match register.kind {
+            RegisterKind::Linewise => {
+                let line = if after { line + 1 } else { line };
+
+                // Below the last line of a document that doesn't end in a
+                // line break, the break goes first
+                let (at, text) = match line < text::line_count(content) {
+                    true => (content.line_to_char(line), text),
+                    false if ends_with_newline(content) || content.len_chars() == 0 => (content.len_chars(), text),
+                    false => (content.len_chars(), format!("{}{}", newline, text.strip_suffix(&newline).unwrap_or(&text))),
+                };
+
+                self.replace(at..at, &text, |content| (first_non_blank(content, line), line));
+            },
+            RegisterKind::Charwise => {
+                let len = line_content(content.line(line)).chars().count();
+                let column = if after && len > 0 { column.min(len - 1) + 1 } else { column.min(len) };
+                let at = content.line_to_char(line) + column;
+                let (end_column, end_line) = end_of((column, line), &text);
+
+                self.replace(at..at, &text, |_| (end_column.saturating_sub(1), end_line));
+            },
+            RegisterKind::Blockwise => {
+                let len = line_len(content, line);
+                let column = if after && len > 0 { column.min(len - 1) + 1 } else { column.min(len) };
+                let line_count = text::line_count(content);
+                let mut edits = Vec::new();
+                let mut appended = Vec::new();
+
+                // Each line of the block goes in at the same column, padding
+                // lines too short to reach it, and adding lines past the end
+                for (i, piece) in register.text.split('\n').enumerate() {
+                    match line + i < line_count {
+                        true => {
+                            let len = line_len(content, line + i);
+                            let at = content.line_to_char(line + i) + column.min(len);
+
+                            edits.push((at..at, format!("{}{}", " ".repeat(column.saturating_sub(len)), piece)));
+                        },
+                        false => appended.push(format!("{}{}", " ".repeat(column), piece)),
+                    }
+                }
+
+                if !appended.is_empty() {
+                    let at = content.len_chars();
+                    let text = match ends_with_newline(content) || at == 0 {
+                        true => appended.iter().map(|l| format!("{}{}", l, newline)).collect(),
+                        false => appended.iter().map(|l| format!("{}{}", newline, l)).collect(),
+                    };
+
+                    edits.push((at..at, text));
+                }
+
+                self.replace_ranges(edits, |_| (column, line));
+            },
+        }
+    }
+
+    /// Starts selecting from the cursor, or switches to selecting a
+    /// different shape. Asking for the shape already selected stops.
+    fn toggle_selection(&mut self, kind: SelectionKind) {
+        self.selection = match self.selection {
+            Some(selection) if selection.kind == kind => None,
+            Some(selection) => Some(Selection { kind, ..selection }),
+            None => {
+                let (column, line) = self.document.cursor;
+                let line = line.min(self.last_line());
+
+                Some(Selection::new(kind, (column.min(line_len(&self.document.content, line)), line)))
+            },
+        };
+    }
+
+    /// Types into the text at every cursor, until Esc.
+    fn handle_insert_input(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        let motion = match code {
+            KeyCode::Left => Motion::Left,
+            KeyCode::Right => Motion::Right,
+            KeyCode::Up => Motion::Up,
+            KeyCode::Down => Motion::Down,
+            KeyCode::Esc => return self.stop_insert(),
+            KeyCode::Enter => return self.insert_line_break(),
+            KeyCode::Backspace => return self.delete_before_cursors(),
+            KeyCode::Tab => return self.insert_text("\t"),
+            KeyCode::Char(' ') if modifiers == KeyModifiers::CONTROL => return self.ask_server(LanguageServers::completion),
+            KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => return self.insert_text(&c.to_string()),
+            _ => return,
+        };
+
+        if let Some(target) = motion.target(&self.document.content, self.document.cursor, None) {
+            self.document.cursor = target;
+        }
+
+        self.move_cursors(motion, None);
+    }
+
+    /// Switches to insert mode, where keys type into the text.
+    fn begin_insert(&mut self) {
+        if !self.document.is_loading() {
+            self.insert = Some(Insert { revision: None });
+        }
+    }
+
+    /// Switches to insert mode, with every cursor moved to where `at` says.
+    fn start_insert(&mut self, at: InsertAt) {
+        self.begin_insert();
+
+        if self.insert.is_none() {
+            return;
+        }
+
+        if let InsertAt::LineBelow | InsertAt::LineAbove = at {
+            return self.open_lines(at == InsertAt::LineBelow);
+        }
+
+        let content = &self.document.content;
+        let last_line = text::line_count(content).saturating_sub(1);
+        let place = |(column, line): (usize, usize)| {
+            let line = line.min(last_line);
+            let len = line_len(content, line);
+
+            match at {
+                InsertAt::AfterCursor => ((column + 1).min(len), line),
+                InsertAt::LineStart => (first_non_blank(content, line), line),
+                InsertAt::LineEnd => (len, line),
+                _ => (column.min(len), line),
+            }
+        };
+
+        self.document.cursor = place(self.document.cursor);
+
+        for cursor in &mut self.cursors {
+            *cursor = place(*cursor);
+        }
+    }
+
+    /// Back to normal mode, each cursor onto the last char typed.
+    fn stop_insert(&mut self) {
+        self.insert = None;
+
+        for cursor in self.cursors.iter_mut().chain(std::iter::once(&mut self.document.cursor)) {
+            cursor.0 = cursor.0.saturating_sub(1);
+        }
+    }
+
+    /// Types `text` at every cursor, leaving each after it.
+    fn insert_text(&mut self, text: &str) {
+        if self.document.is_loading() {
+            return;
+        }
+
+        let text = self.document.line_ending.normalize(text);
+
+        self.at_every_cursor(|buffer| {
+            let content = &buffer.document.content;
+            let at = motion::char_index(content, buffer.document.cursor);
+            let cursor = end_of(motion::position(content, at), &text);
+
+            buffer.replace(at..at, &text, |_| cursor);
+        });
+    }
+
+    /// Breaks the line at every cursor, the new line starting with as much
+    /// of the old one's indent as was before the cursor.
+    fn insert_line_break(&mut self) {
+        if self.document.is_loading() {
+            return;
+        }
+
+        let newline = self.document.line_ending.normalize("\n");
+
+        self.at_every_cursor(|buffer| {
+            let content = &buffer.document.content;
+            let at = motion::char_index(content, buffer.document.cursor);
+            let (column, line) = motion::position(content, at);
+            let indent: String = content.line(line).chars().take(first_non_blank(content, line).min(column)).collect();
+            let cursor = (indent.chars().count(), line + 1);
+
+            buffer.replace(at..at, &format!("{}{}", newline, indent), |_| cursor);
+        });
+    }
+
+    /// Deletes the char before every cursor, joining lines at the start of
+    /// one.
+    fn delete_before_cursors(&mut self) {
+        if self.document.is_loading() {
+            return;
+        }
+
+        self.at_every_cursor(|buffer| {
+            let content = &buffer.document.content;
+            let at = motion::char_index(content, buffer.document.cursor);
+
+            let start = match motion::position(content, at) {
+                _ if at == 0 => return,
+                // The line break might be two chars
+                (0, line) => content.line_to_char(line - 1) + line_len(content, line - 1),
+                _ => at - 1,
+            };
+
+            let cursor = motion::position(content, start);
+            buffer.replace(start..at, "", |_| cursor);
+        });
+    }
+
+    /// Opens a line below every cursor's, or above, indented like it, and
+    /// moves the cursors onto them.
+    fn open_lines(&mut self, below: bool) {
+        let newline = self.document.line_ending.normalize("\n");
+
+        self.at_every_cursor(|buffer| {
+            let content = &buffer.document.content;
+            let line = buffer.document.cursor.1.min(text::line_count(content).saturating_sub(1));
+            let indent: String = content.line(line).chars().take(first_non_blank(content, line)).collect();
+            let column = indent.chars().count();
+
+            match below {
+                true => {
+                    let at = content.line_to_char(line) + line_len(content, line);
+                    buffer.replace(at..at, &format!("{}{}", newline, indent), |_| (column, line + 1));
+                },
+                false => {
+                    let at = content.line_to_char(line);
+                    buffer.replace(at..at, &format!("{}{}", indent, newline), |_| (column, line));
+                },
+            }
+        });
+    }
+
+    /// Does what a command typed in normal or visual mode asks.
+    fn execute(&mut self, state: &AppState, bus: &mut Bus<Message>, command: keys::Command) {
+        let keys::Command { action, count, .. } = command;
+        let Rect { width, .. } = self.bounds.unwrap_or_default();
+        let max_x = width.saturating_sub(2) as usize;
+        let (x, y) = self.document.cursor;
+        let times = count.unwrap_or(1);
+
+        match action {
+            // Plain movement keeps the column the cursor wants to be in
+            Action::Motion(Motion::Left) => {
+                self.document.cursor.0 = x.min(max_x).saturating_sub(times);
+                self.move_cursors(Motion::Left, Some(times));
+            },
+            Action::Motion(Motion::Down) => {
+                self.document.cursor.1 = (y + times).min(self.last_line());
+                self.move_cursors(Motion::Down, Some(times));
+            },
+            Action::Motion(Motion::Up) => {
+                self.document.cursor.1 = y.saturating_sub(times);
+                self.move_cursors(Motion::Up, Some(times));
+            },
+            Action::Motion(Motion::Right) => {
+                self.document.cursor.0 = (x + times).min(max_x);
+                self.move_cursors(Motion::Right, Some(times));
+            },
+            Action::Motion(motion) => {
+                if let Some(target) = motion.target(&self.document.content, self.document.cursor, count) {
+                    self.document.cursor = target;
+                }
+
+                self.move_cursors(motion, count);
+            },
+            Action::Operate(operator, target) => {
+                if operator == Operator::Change {
+                    self.begin_insert();
+                }
+
+                self.operate(bus, operator, target, count);
+            },
+            Action::SelectObject(object) => self.select_object(object, count),
+            Action::Select(kind) => self.toggle_selection(kind),
+            Action::Visual(action) => self.visual(bus, action),
+            Action::Insert(at) => self.start_insert(at),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Hover => self.ask_server(LanguageServers::hover),
+            Action::Definition => self.ask_server(LanguageServers::definition),
+            Action::Completion => self.ask_server(LanguageServers::completion),
+            Action::JumpToHunk { forward } => self.jump_to_hunk(state, forward),
+            Action::AddCursorAtNextMatch => self.add_cursor_at_next_match(),
+            Action::AddCursorBeside { below } => self.add_cursor_beside(below),
+            Action::YankLines => self.at_every_cursor(|buffer| buffer.yank_lines(bus, buffer.document.cursor.1, times)),
+            Action::DeleteChars => self.delete_chars(bus, times),
+            Action::Put { after } => self.at_every_cursor(|buffer| buffer.put(state, after)),
+            Action::Search(direction) => self.start_prompt(PromptKind::Search(direction)),
+            Action::CommandLine => self.start_prompt(PromptKind::Command),
+            Action::RepeatSearch { reverse } => self.repeat_search(reverse),
+            Action::Escape => {
+                self.highlight_search = false;
+                self.cursors.clear();
+            },
+        }
+    }
+
+    /// Does `action` to the selection.
+    fn visual(&mut self, bus: &mut Bus<Message>, action: VisualAction) {
+        let Some(selection) = self.selection else { return };
+
+        match action {
+            VisualAction::Exit => self.selection = None,
+            VisualAction::SwapEnds => {
+                self.selection = Some(Selection { anchor: self.document.cursor, ..selection });
+                self.document.cursor = selection.anchor;
+            },
+            VisualAction::AddCursors => self.add_cursors_on_selection(selection),
+            VisualAction::Yank => self.yank_selection(bus, selection),
+            VisualAction::Delete => self.delete_selection(bus, selection, false),
+            VisualAction::Change => {
+                self.begin_insert();
+                self.delete_selection(bus, selection, true);
+            },
+            VisualAction::Indent | VisualAction::Unindent => {
+                self.selection = None;
+                self.indent_lines(selection.lines(self.document.cursor), action == VisualAction::Indent);
+            },
+            VisualAction::ToggleCase => self.change_case(selection, toggle_case),
+            VisualAction::Lowercase => self.change_case(selection, |text| text.to_lowercase()),
+            VisualAction::Uppercase => self.change_case(selection, |text| text.to_uppercase()),
+        }
+    }
+
+    /// The top left of what the selection covers.
+    fn selection_start(&self, selection: Selection) -> (usize, usize) {
+        let cursor = self.document.cursor;
+        let (start, _) = selection.ends(cursor);
+
+        match selection.kind {
+            SelectionKind::Char => start,
+            SelectionKind::Line => (first_non_blank(&self.document.content, start.1), start.1),
+            SelectionKind::Block => (selection.anchor.0.min(cursor.0), start.1),
+        }
+    }
+
+    fn selected_text(&self, selection: Selection) -> (String, RegisterKind) {
+        let content = &self.document.content;
+        let cursor = self.document.cursor;
+        let lines = selection.lines(cursor);
+        let text = |range: Range<usize>| content.slice(range).to_string().replace("\r\n", "\n");
+
+        match selection.kind {
+            SelectionKind::Char => {
+                let range = selection.ranges(content, cursor).remove(0);
+                (text(range), RegisterKind::Charwise)
+            },
+            SelectionKind::Line => {
+                (self.lines_text(*lines.start(), lines.count()), RegisterKind::Linewise)
+            },
+            SelectionKind::Block => {
+                let pieces: Vec<String> = selection.ranges(content, cursor).into_iter().map(text).collect();
+                (pieces.join("\n"), RegisterKind::Blockwise)
+            },
+        }
+    }
+
+    fn yank_selection(&mut self, bus: &mut Bus<Message>, selection: Selection) {
+        let (text, kind) = self.selected_text(selection);
+        let lines = selection.lines(self.document.cursor).count();
+
+        if lines > 2 {
+            self.message = format!("{} lines yanked", lines);
+        }
+
+        self.document.cursor = self.selection_start(selection);
+        self.selection = None;
+        self.yank(bus, text, kind);
+    }
+
+    /// Deletes what is selected. Changing it instead leaves an empty line in
+    /// place of selected lines, and the cursor where the text was, with a
+    /// cursor on each line of a block long enough to have had some of it.
+    fn delete_selection(&mut self, bus: &mut Bus<Message>, selection: Selection, change: bool) {
+        if self.document.is_loading() {
+            return;
+        }
+
+        self.selection = None;
+
+        let mut ranges = selection.ranges(&self.document.content, self.document.cursor);
+        let lines = selection.lines(self.document.cursor);
+
+        match selection.kind {
+            SelectionKind::Line if change => return self.change_lines(bus, lines),
+            SelectionKind::Line => return self.delete_lines(bus, *lines.start(), lines.count()),
+            SelectionKind::Char => return self.delete_range(bus, ranges.remove(0), change),
+            SelectionKind::Block => { },
+        }
+
+        let (text, kind) = self.selected_text(selection);
+        let (column, line) = self.selection_start(selection);
+
+        let content = &self.document.content;
+        let below: Vec<(usize, usize)> = match change {
+            true => lines.skip(1).filter(|line| line_len(content, *line) > column).map(|line| (column, line)).collect(),
+            false => Vec::new(),
+        };
+
+        self.replace_ranges(ranges.into_iter().map(|r| (r, String::new())).collect(), |content| match change {
+            true => (column, line),
+            false => (column.min(line_len(content, line).saturating_sub(1)), line),
+        });
+
+        self.cursors.extend(below);
+        self.yank(bus, text, kind);
+    }
+
+    /// Indents `lines` by one level, or takes one level away. Blank lines
+    /// are left alone.
+    fn indent_lines(&mut self, lines: RangeInclusive<usize>, indent: bool) {
+        if self.document.is_loading() {
+            return;
+        }
+
+        let content = &self.document.content;
+        let count = lines.clone().count();
+        let mut edits = Vec::new();
+
+        for line in lines.clone() {
+            let start = content.line_to_char(line);
+
+            if indent && line_len(content, line) > 0 {
+                edits.push((start..start, INDENT.to_string()));
+            } else if !indent {
+                let text = content.line(line);
+                let width = match text.get_char(0) {
+                    Some('\t') => 1,
+                    _ => text.chars().take(INDENT.len()).take_while(|c| *c == ' ').count(),
+                };
+
+                edits.push((start..start + width, String::new()));
+            }
+        }
+
+        let top = *lines.start();
+        self.replace_ranges(edits, |content| (first_non_blank(content, top), top));
+
+        if count > 2 {
+            self.message = format!("{} lines {}", count, if indent { "indented" } else { "unindented" });
+        }
+    }
+
+    /// Deletes a range of chars. Changing it leaves the cursor where the
+    /// text was, even past the end of the line.
+    fn delete_range(&mut self, bus: &mut Bus<Message>, range: Range<usize>, change: bool) {
+        if self.document.is_loading() {
+            return;
+        }
+
+        let content = &self.document.content;
+        let text = content.slice(range.clone()).to_string().replace("\r\n", "\n");
+        let (column, line) = motion::position(content, range.start);
+
+        self.replace(range, "", |content| match change {
+            true => (column, line),
+            false => (column.min(line_len(content, line).saturating_sub(1)), line),
+        });
+
+        self.yank(bus, text, RegisterKind::Charwise);
+    }
+
+    /// Empties `lines` into one, keeping the first one's indent.
+    fn change_lines(&mut self, bus: &mut Bus<Message>, lines: RangeInclusive<usize>) {
+        if self.document.is_loading() {
+            return;
+        }
+
+        let (first, last) = (*lines.start(), *lines.end());
+        let text = self.lines_text(first, lines.count());
+
+        let content = &self.document.content;
+        let indent = first_non_blank(content, first);
+        let start = content.line_to_char(first) + indent;
+        let end = content.line_to_char(last) + line_len(content, last);
+
+        self.replace(start..end.max(start), "", |_| (indent, first));
+        self.yank(bus, text, RegisterKind::Linewise);
+    }
+
+    /// Has `operator` act on `target` at every cursor.
+    fn operate(&mut self, bus: &mut Bus<Message>, operator: Operator, target: Target, count: Option<usize>) {
+        self.at_every_span(|content, cursor| match target {
+            Target::Lines => {
+                let last_line = text::line_count(content).saturating_sub(1);
+                let line = cursor.1.min(last_line);
+                let last = (line + count.unwrap_or(1) - 1).min(last_line);
+
+                Some(Span::Lines(line..=last))
+            },
+            Target::Motion(motion) => {
+                // `cw` on a word changes only the word, like `ce`
+                let on_word = content.get_char(motion::char_index(content, cursor)).is_some_and(|c| !c.is_whitespace());
+                let motion = match motion {
+                    Motion::WordForward if operator == Operator::Change && on_word => Motion::WordEnd,
+                    motion => motion,
+                };
+
+                motion.span(content, cursor, count)
+            },
+            Target::Object(object) => object.span(content, cursor, count),
+        }, |buffer, span| buffer.apply_operator(bus, operator, span));
+    }
+
+    /// Deletes `count` characters from every cursor, not going past the end
+    /// of its line.
+    fn delete_chars(&mut self, bus: &mut Bus<Message>, count: usize) {
+        self.at_every_span(|content, (column, line)| {
+            let line = line.min(text::line_count(content).saturating_sub(1));
+            let len = line_len(content, line);
+            let column = column.min(len.checked_sub(1)?);
+            let start = content.line_to_char(line) + column;
+
+            Some(Span::Chars(start..start + count.min(len - column)))
+        }, |buffer, span| buffer.apply_operator(bus, Operator::Delete, span));
+    }
+
+    /// Selects `object` around the cursor.
+    fn select_object(&mut self, object: TextObject, count: Option<usize>) {
+        let Some(span) = object.span(&self.document.content, self.document.cursor, count) else { return };
+
+        let content = &self.document.content;
+
+        let (kind, anchor, cursor) = match span {
+            Span::Chars(range) if range.is_empty() => return,
+            Span::Chars(range) => {
+                (SelectionKind::Char, motion::position(content, range.start), motion::position(content, range.end - 1))
+            },
+            Span::Lines(lines) => (SelectionKind::Line, (0, *lines.start()), (0, *lines.end())),
+        };
+
+        self.selection = Some(Selection::new(kind, anchor));
+        self.document.cursor = cursor;
+    }
+
+    fn apply_operator(&mut self, bus: &mut Bus<Message>, operator: Operator, span: Span) {
+        let content = &self.document.content;
+
+        match (operator, span) {
+            (Operator::Indent | Operator::Unindent, Span::Lines(lines)) => {
+                self.indent_lines(lines, operator == Operator::Indent);
+            },
+            (Operator::Indent | Operator::Unindent, Span::Chars(range)) => {
+                let first = content.char_to_line(range.start);
+                let last = content.char_to_line(range.end.saturating_sub(1).max(range.start));
+
+                self.indent_lines(first..=last, operator == Operator::Indent);
+            },
+            (Operator::Yank, Span::Lines(lines)) => {
+                self.document.cursor.1 = *lines.start();
+                self.yank_lines(bus, *lines.start(), lines.count());
+            },
+            (Operator::Yank, Span::Chars(range)) => {
+                let text = content.slice(range.clone()).to_string().replace("\r\n", "\n");
+
+                self.document.cursor = motion::position(content, range.start);
+                self.yank(bus, text, RegisterKind::Charwise);
+            },
+            (Operator::Delete, Span::Lines(lines)) => self.delete_lines(bus, *lines.start(), lines.count()),
+            (Operator::Change, Span::Lines(lines)) => self.change_lines(bus, lines),
+            (Operator::Delete | Operator::Change, Span::Chars(range)) => {
+                self.delete_range(bus, range, operator == Operator::Change);
+            },
+        }
+    }
+
+    /// Runs the selected text through `change`, like making it uppercase.
+    fn change_case(&mut self, selection: Selection, change: fn(&str) -> String) {
+        if self.document.is_loading() {
+            return;
+        }
+
+        let content = &self.document.content;
+        let edits = selection.ranges(content, self.document.cursor)
+            .into_iter()
+            .map(|range| {
+                let text = change(&content.slice(range.clone()).to_string());
+                (range, text)
+            })
+            .collect();
+
+        let start = self.selection_start(selection);
+        self.selection = None;
+        self.replace_ranges(edits, |_| start);
+    }
+
+    /// Describes the diagnostic at the cursor, or the first on its line.
+    fn diagnostic_at_cursor(&self) -> Option<String> {
+        let (column, line) = self.document.cursor;
+        let on_line = self.document.diagnostics.iter().filter(|d| d.on_line(line));
+
+        let diagnostic = on_line.clone()
+            .find(|d| (d.start.1, d.start.0) <= (line, column) && (line, column) < (d.end.1, d.end.0))
+            .or(on_line.min_by_key(|d| d.severity))?;
+
+        Some(format!("{}: {}", diagnostic.severity, diagnostic.message))
+    }
+
+    pub fn file_status(&self) -> FileStatus {
+        FileStatus {
+            modified: self.document.history.is_modified() || self.substitution.is_some(),
+            ..self.document.status()
+        }
+    }
+
+    fn view_height(&self) -> usize {
+        self.bounds.map_or(1, |b| b.height.saturating_sub(1).max(1) as usize)
+    }
+
+    fn last_line(&self) -> usize {
+        self.document.last_line()
+    }
+
+    /// Scrolls just far enough to bring the cursor into view.
+    fn scroll_to_cursor(&mut self) {
+        let line = self.document.cursor.1;
+        let height = self.view_height();
+
+        if line < self.document.scroll {
+            self.document.scroll = line;
+        } else if line >= self.document.scroll + height {
+            self.document.scroll = line + 1 - height;
+        }
+    }
+
+    /// Scrolls so the cursor's line is in the middle of the view.
+    fn center_on_cursor(&mut self) {
+        self.document.scroll = self.document.cursor.1.saturating_sub(self.view_height() / 2);
+    }
+
+    fn move_to_match(&mut self, m: SearchMatch) {
+        self.document.cursor = (m.start, m.line);
+        self.scroll_to_cursor();
+    }
+
+    /// Describes where the cursor is among the matches of `search`, e.g. `[2/7]`.
+    fn match_count(&self, search: &Search) -> String {
+        // Counting would mean searching the whole file
+        if self.document.large {
+            return format!("[?/?] {}", search.pattern());
+        }
+
+        let matches = search.find_all(&self.document.content);
+
+        if matches.is_empty() {
+            return format!("Pattern not found: {}", search.pattern());
+        }
+
+        let (column, line) = self.document.cursor;
+        let current = matches.iter()
+            .position(|m| m.line == line && m.start == column)
+            .map_or("-".to_string(), |i| (i + 1).to_string());
+
+        format!("[{}/{}]", current, matches.len())
+    }
+
+    fn start_prompt(&mut self, kind: PromptKind) {
+        self.prompt = Some(Prompt {
+            kind,
+            text: String::new(),
+            origin: self.document.cursor,
+            origin_scroll: self.document.scroll,
+        });
+        self.message.clear();
+    }
+
+    /// Re-runs the search being typed from where it started, moving the
+    /// cursor to the first match.
+    fn update_search(&mut self) {
+        let Some(Prompt { kind: PromptKind::Search(direction), text, origin, origin_scroll }) = &self.prompt else {
+            return;
+        };
+
+        self.document.cursor = *origin;
+        self.document.scroll = *origin_scroll;
+
+        if text.is_empty() {
+            self.search = None;
+            self.message.clear();
+            return;
+        }
+
+        match Search::new(text, *direction, self.search_options) {
+            Ok(search) => {
+                if let Some(m) = search.find_next(&self.document.content, *origin, *direction) {
+                    self.move_to_match(m);
+                }
+
+                self.message = self.match_count(&search);
+                self.search = Some(search);
+                self.highlight_search = true;
+            },
+            Err(e) => {
+                self.search = None;
+                self.message = format!("Invalid pattern: {}", e.to_string().lines().last().unwrap_or_default());
+            }
+        }
+    }
+
+    fn handle_prompt_input(&mut self, state: &AppState, bus: &mut Bus<Message>, code: KeyCode) {
+        let Some(prompt) = &mut self.prompt else { return };
+        let is_search = matches!(prompt.kind, PromptKind::Search(_));
+
+        match code {
+            KeyCode::Char(c) => {
+                prompt.text.push(c);
+
+                if is_search {
+                    self.update_search();
+                }
+            },
+            KeyCode::Backspace => {
+                if prompt.text.pop().is_none() {
+                    self.cancel_prompt();
+                } else if is_search {
+                    self.update_search();
+                }
+            },
+            KeyCode::Enter => {
+                let prompt = self.prompt.take().unwrap();
+
+                if prompt.kind == PromptKind::Command {
+                    self.execute_command(state, bus, &prompt.text);
+                }
+            },
+            KeyCode::Esc => {
+                self.cancel_prompt();
+            },
+            _ => { }
+        }
+    }
+
+    fn cancel_prompt(&mut self) {
+        if let Some(prompt) = self.prompt.take() {
+            self.document.cursor = prompt.origin;
+            self.document.scroll = prompt.origin_scroll;
+
+            if let PromptKind::Search(_) = prompt.kind {
+                self.search = None;
+            }
+        }
+
+        self.message.clear();
+    }
+
+    fn execute_command(&mut self, state: &AppState, bus: &mut Bus<Message>, input: &str) {
+        if self.document.is_loading() {
+            self.message = "Can't run commands until the file has loaded".to_string();
+            return;
+        }
+
+        match command::parse(input) {
+            Ok(Command::Substitute { range, pattern, replacement, flags }) => {
+                self.substitute(range, pattern, replacement, flags);
+            },
+            Ok(Command::Write) => self.write(),
+            Ok(Command::Make(args)) => self.make(state, args),
+            Ok(Command::NextError) => self.step_quickfix(state, bus, true),
+            Ok(Command::PreviousError) => self.step_quickfix(state, bus, false),
+            Ok(Command::PreviewHunk) => self.preview_hunk(state),
+            Err(e) => {
+                self.message = e;
+            }
+        }
+    }
+
+    fn write(&mut self) {
+        let path = self.document.path.display().to_string();
+
+        self.message = match self.document.save() {
+            Ok(bytes) => {
+                self.servers.did_save(&self.document.path);
+                format!("\"{}\" {}L, {}B written", path, self.document.line_count(), bytes)
+            },
+            Err(e) => format!("Can't write \"{}\": {}", path, e),
+        };
+    }
+
+    /// Runs `cargo check` in the background, its problems replacing the
+    /// quickfix list and any it found before.
+    fn make(&mut self, state: &AppState, args: Vec<String>) {
+        let root = if state.root.as_os_str().is_empty() { PathBuf::from(".") } else { state.root.clone() };
+
+        self.jobs.push(JobRequest::new("cargo check", move |context| {
+            let found = match cargo::check(&root, &args, context) {
+                Ok(found) => found,
+                Err(e) => {
+                    context.send(Message::ShowMessage(e.to_string()));
+                    return;
+                }
+            };
+
+            if context.is_cancelled() {
+                return;
+            }
+
+            let mut reports: Vec<Report> = Vec::new();
+
+            for (path, diagnostic) in &found {
+                match reports.iter_mut().find(|r| r.path == *path) {
+                    Some(report) => report.diagnostics.push(diagnostic.clone()),
+                    None => reports.push(Report {
+                        source: "cargo".to_string(),
+                        path: path.clone(),
+                        diagnostics: vec![ diagnostic.clone() ],
+                    }),
+                }
+            }
+
+            context.send(Message::ClearDiagnostics("cargo".to_string()));
+
+            for report in reports {
+                context.send(Message::SetDiagnostics(report));
+            }
+
+            context.send(Message::SetQuickfix(found));
+        }));
+    }
+
+    /// Jumps to the quickfix entry after, or before, the last one jumped to.
+    fn step_quickfix(&mut self, state: &AppState, bus: &mut Bus<Message>, forward: bool) {
+        let index = match (state.quickfix_index, forward) {
+            _ if state.quickfix.is_empty() => None,
+            (None, _) => Some(0),
+            (Some(i), true) => Some(i + 1).filter(|i| *i < state.quickfix.len()),
+            (Some(i), false) => i.checked_sub(1),
+        };
+
+        match index {
+            Some(index) => bus.send(Message::SelectQuickfix(index)),
+            None if state.quickfix.is_empty() => self.message = "No errors".to_string(),
+            None => self.message = "No more items".to_string(),
+        }
+    }
+
+    fn undo(&mut self) {
+        match self.document.history.undo(&mut self.document.content) {
+            Some(cursor) => {
+                self.document.cursor = cursor;
+                self.message = "1 change undone".to_string();
+            },
+            None => self.message = "Already at oldest change".to_string(),
+        }
+
+        self.document.cursor.1 = self.document.cursor.1.min(self.last_line());
+    }
+
+    fn redo(&mut self) {
+        match self.document.history.redo(&mut self.document.content) {
+            Some(cursor) => {
+                self.document.cursor = cursor;
+                self.message = "1 change redone".to_string();
+            },
+            None => self.message = "Already at newest change".to_string(),
+        }
+
+        self.document.cursor.1 = self.document.cursor.1.min(self.last_line());
+    }
+
+    /// Jumps to the next match of the last search, `reverse` flipping the
+    /// direction it was made in.
+    fn repeat_search(&mut self, reverse: bool) {
+        let Some(search) = &self.search else {
+            self.message = "No previous search".to_string();
+            return;
+        };
+
+        let direction = if reverse {
+            search.direction().reverse()
+        } else {
+            search.direction()
+        };
+
+        match search.find_next(&self.document.content, self.document.cursor, direction) {
+            Some(m) => {
+                let search = search.clone();
+
+                self.move_to_match(m);
+                self.message = self.match_count(&search);
+                self.highlight_search = true;
+            },
+            None => {
+                self.message = format!("Pattern not found: {}", search.pattern());
+            }
+        }
+    }
+
+    fn screen_cursor(&self) -> (u16, u16) {
+        self.screen_position(self.document.cursor)
+    }
+
+    fn screen_position(&self, (column, line): (usize, usize)) -> (u16, u16) {
+        let Rect { width, .. } = self.bounds.unwrap_or_default();
+        let max_x = width.saturating_sub(2) as usize;
+
+        (column.min(max_x) as u16, line.saturating_sub(self.document.scroll) as u16)
+    }
+
+    /// The primary cursor on screen, and the others that are in view.
+    fn cursor_positions(&self) -> CursorPosition {
+        let visible = self.document.scroll..self.document.scroll + self.view_height();
+        let secondary = self.cursors.iter()
+            .filter(|(_, line)| visible.contains(line))
+            .map(|cursor| self.screen_position(*cursor))
+            .collect();
+
+        CursorPosition { primary: self.screen_cursor(), secondary }
+    }
+
+    /// The mode the buffer is in, and what it has typed into the command gutter.
+    fn mode(&self) -> (Mode, String) {
+        if let Some(conflict) = &self.conflict {
+            return (Mode::Command, conflict.prompt(&self.document.path));
+        }
+
+        match (&self.prompt, &self.substitution) {
+            (Some(prompt), _) => {
+                let (mode, prefix) = match prompt.kind {
+                    PromptKind::Search(SearchDirection::Forward) => (Mode::Search, '/'),
+                    PromptKind::Search(SearchDirection::Backward) => (Mode::Search, '?'),
+                    PromptKind::Command => (Mode::Command, ':'),
+                };
+
+                (mode, format!("{}{}", prefix, prompt.text))
+            },
+            (None, Some((_, pending))) => {
+                (Mode::Command, format!("replace with {} (y/n/a/q/l)?", pending.replacement))
+            },
+            (None, None) => match self.selection.map(|s| s.kind) {
+                Some(SelectionKind::Char) => (Mode::Visual, String::new()),
+                Some(SelectionKind::Line) => (Mode::VisualLine, String::new()),
+                Some(SelectionKind::Block) => (Mode::VisualBlock, String::new()),
+                None if self.insert.is_some() => (Mode::Insert, String::new()),
+                None => (Mode::Normal, String::new()),
+            },
+        }
+    }
+
+    /// Sends a message for everything about the buffer that `state` has out
+    /// of date.
+    fn publish(&self, state: &AppState, bus: &mut Bus<Message>) {
+        let (position, scroll) = (self.cursor_positions(), self.document.scroll);
+
+        if state.cursor_position != position || state.scroll_offset != scroll {
+            bus.send(Message::MoveCursor { position, scroll });
+        }
+
+        let file_status = self.file_status();
+
+        if state.file_status != file_status {
+            bus.send(Message::SetFileStatus(file_status));
+        }
+
+        if state.message != self.message {
+            bus.send(Message::ShowMessage(self.message.clone()));
+        }
+
+        let (mode, command_line) = self.mode();
+
+        if state.mode != mode {
+            bus.send(Message::SetMode(mode));
+        }
+
+        if state.command_line != command_line {
+            bus.send(Message::SetCommandLine(command_line));
+        }
+    }
+
+    /// Shows `path` with the cursor at `(column, line)`, or says why it can't.
+    fn jump(&mut self, path: &Path, (column, line): (usize, usize)) -> bool {
+        if !self.open(path) {
+            return false;
+        }
+
+        self.document.cursor = (column, line.min(self.last_line()));
+        self.center_on_cursor();
+
+        true
+    }
+
+    /// Shows `path`, or says why it can't.
+    fn open(&mut self, path: &Path) -> bool {
+        self.generation += 1;
+
+        match self.switch_to(path) {
+            Ok(()) => {
+                self.check_conflict();
+                true
+            },
+            Err(e) => {
+                self.message = format!("{}: {}", path.display(), e);
+                false
+            }
+        }
+    }
+}
+
+impl Window<AppState> for Buffer {
+    fn info(&self) -> WindowInfo {
+        WindowInfo {
+            selectable: true,
+            ..self.info
+        }
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        if let Some(lines) = self.conflict.as_ref().and_then(|c| c.visible_diff(self.view_height())) {
+            return lines;
+        }
+
+        let highlight = ContentStyle::default()
+            .with(Color::Black)
+            .on(Color::DarkYellow);
+        let current = ContentStyle::default()
+            .with(Color::Black)
+            .on(Color::Yellow)
+            .attribute(Attribute::Bold);
+        let selected = ContentStyle::default().on(Color::DarkGrey);
+        let cursor = ContentStyle::default().attribute(Attribute::Reverse);
+
+        // Highlighting is left off for large files, to keep drawing them cheap
+        let search = self.search.as_ref().filter(|_| self.highlight_search && !self.document.large);
+        let pending = self.substitution.as_ref().map(|(_, p)| p);
+        let last = (self.document.scroll + self.view_height()).min(self.document.line_count());
+
+        (self.document.scroll..last)
+            .map(|line| {
+                let text = line_content(self.document.content.line(line));
+                let mut styled = StyledContent::from(text.to_string());
+                let byte = |column: usize| text.char_indices().nth(column).map_or(text.len(), |(i, _)| i);
+
+                for diagnostic in self.document.diagnostics.iter().filter(|d| d.on_line(line)) {
+                    let start = if diagnostic.start.1 == line { diagnostic.start.0 } else { 0 };
+                    let end = if diagnostic.end.1 == line { diagnostic.end.0 } else { usize::MAX };
+                    // Diagnostics at a point still get a char underlined
+                    let end = end.max(start + 1);
+
+                    let style = ContentStyle::default().with(diagnostic.severity.color()).attribute(Attribute::Underlined);
+
+                    styled.style_range(byte(start)..byte(end), style);
+                }
+
+                if let Some(search) = search {
+                    for range in search.find_in_str(&text) {
+                        let column = text[..range.start].chars().count();
+                        let style = if (column, line) == self.document.cursor { current } else { highlight };
+
+                        styled.style_range(range, style);
+                    }
+                }
+
+                if let Some(pending) = pending.filter(|p| p.line == line) {
+                    styled.style_range(pending.range.clone(), current);
+                }
+
+                let columns = self.selection.and_then(|s| s.columns(&self.document.content, self.document.cursor, line));
+
+                if let Some(columns) = columns {
+                    styled.layer_range(byte(columns.start)..byte(columns.end), selected);
+
+                    // A selected line break shows as a selected space
+                    if columns.end > text.chars().count() {
+                        styled.push(" ".to_string(), selected);
+                    }
+                }
+
+                // The terminal only draws the primary cursor
+                let len = text.chars().count();
+                let columns: Vec<usize> = self.cursors.iter()
+                    .filter(|(_, l)| *l == line)
+                    .map(|(column, _)| (*column).min(len))
+                    .collect();
+
+                for column in columns.iter().filter(|column| **column < len) {
+                    styled.layer_range(byte(*column)..byte(column + 1), cursor);
+                }
+
+                if columns.contains(&len) {
+                    styled.push(" ".to_string(), cursor);
+                }
+
+                // The worst problem starting on the line is spelled out after it
+                let worst = self.document.diagnostics.iter()
+                    .filter(|d| d.start.1 == line)
+                    .min_by_key(|d| d.severity);
+
+                if let Some(diagnostic) = worst {
+                    let message = diagnostic.message.lines().next().unwrap_or_default();
+                    let style = ContentStyle::default().with(diagnostic.severity.color()).attribute(Attribute::Italic);
+
+                    styled.push(format!("  {}", message), style);
+                }
+
+                styled
+            })
+            .collect()
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+        self.scroll_to_cursor();
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn title(&self) -> &str {
+        self.document.path.to_str().unwrap()
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn handle_input(&mut self, state: &AppState, bus: &mut Bus<Message>, code: KeyCode, modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        // Nearly every key moves the cursor or changes the text
+        self.generation += 1;
+
+        if self.conflict.is_some() {
+            self.handle_conflict_input(code);
+            self.publish(state, bus);
+
+            return Ok(());
+        }
+
+        if self.prompt.is_some() {
+            self.handle_prompt_input(state, bus, code);
+            self.publish(state, bus);
+
+            return Ok(());
+        }
+
+        if self.substitution.is_some() {
+            self.handle_confirm_input(code);
+            self.publish(state, bus);
+
+            return Ok(());
+        }
+
+        self.message.clear();
+
+        if self.insert.is_some() {
+            self.handle_insert_input(code, modifiers);
+        } else if let Some(command) = self.keys.handle(code, modifiers, self.selection.is_some()) {
+            self.register = command.register;
+            self.execute(state, bus, command);
+            self.register = None;
+        }
+
+        self.merge_cursors();
+        self.scroll_to_cursor();
+
+        if self.message.is_empty() {
+            self.message = self.diagnostic_at_cursor().unwrap_or_default();
+        }
+
+        self.sync_servers();
+        self.update_hunks();
+        self.publish(state, bus);
+
+        Ok(())
+    }
+
+    fn handle_paste(&mut self, state: &AppState, bus: &mut Bus<Message>, text: &str) -> Result<(), Box<dyn Error>> {
+        self.generation += 1;
+
+        if let Some(prompt) = &mut self.prompt {
+            prompt.text.push_str(text.lines().next().unwrap_or_default());
+
+            if matches!(prompt.kind, PromptKind::Search(_)) {
+                self.update_search();
+            }
+
+            self.publish(state, bus);
+            return Ok(());
+        }
+
+        if self.conflict.is_some() || self.substitution.is_some() || self.document.is_loading() {
+            return Ok(());
+        }
+
+        // Terminals paste lines ending in `\r`
+        self.insert_text(&text.replace("\r\n", "\n").replace('\r', "\n"));
+
+        self.sync_servers();
+        self.update_hunks();
+        self.publish(state, bus);
+
+        Ok(())
+    }
+
+    fn cursor_position(&self) -> Option<(u16, u16)> {
+        Some(self.screen_cursor())
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+    fn tick(&mut self, state: &AppState, bus: &mut Bus<Message>) -> bool {
+        // Nothing gave us a waker, so this is the first chance to start
+        if !self.watching {
+            self.start_watching(None);
+        }
+
+        if self.waker.is_none() {
+            self.document.start_loading(None);
+        }
+
+        let loaded = self.poll_loaders();
+        let changed = match &self.watcher {
+            Some(watcher) => watcher.poll(),
+            None => Vec::new(),
+        };
+
+        // Newly opened documents are picked up here too
+        self.sync_servers();
+        self.update_hunks();
+        let events = self.servers.poll();
+        let mut reports = self.servers.poll_diagnostics();
+
+        for provider in &mut self.providers {
+            reports.extend(provider.poll_diagnostics());
+        }
+
+        for report in reports {
+            bus.send(Message::SetDiagnostics(report));
+        }
+
+        if changed.is_empty() && !loaded && events.is_empty() {
+            return false;
+        }
+
+        for event in events {
+            self.handle_lsp_event(event, bus);
+        }
+
+        for path in &changed {
+            self.file_changed(path);
+        }
+
+        // Ask again about the latest version
+        if changed.contains(&self.document.path) {
+            self.conflict = None;
+            self.check_conflict();
+        }
+
+        self.generation += 1;
+        self.publish(state, bus);
+
+        true
+    }
+
+    fn set_waker(&mut self, waker: Waker) {
+        self.waker = Some(waker.clone());
+        self.servers.set_waker(waker.clone());
+        self.start_loading();
+
+        for provider in &mut self.providers {
+            provider.set_waker(waker.clone());
+        }
+
+        if !self.watching {
+            self.start_watching(Some(waker));
+        }
+    }
+
+    fn update_state(&mut self, new_state: &AppState) {
+        self.servers.set_root(&new_state.root);
+
+        if let Some(path) = new_state.open_files.get(new_state.selected_file) {
+            self.open(path);
+        }
+
+        self.document.diagnostics = new_state.diagnostics.for_path(&self.document.path);
+        self.sync_servers();
+        self.update_hunks();
+    }
+
+    fn on_message(&mut self, state: &AppState, message: &Message, bus: &mut Bus<Message>) {
+        match message {
+            Message::OpenFile(path) => {
+                self.open(path);
+            },
+            Message::Jump { path, position } => {
+                self.jump(path, *position);
+            },
+            Message::SelectQuickfix(index) => {
+                let Some((path, diagnostic)) = state.quickfix.get(*index) else { return };
+
+                if self.jump(path, diagnostic.start) {
+                    self.message = format!(
+                        "({} of {}) {}: {}",
+                        index + 1, state.quickfix.len(), diagnostic.severity, diagnostic.message.lines().next().unwrap_or_default()
+                    );
+                }
+            },
+            Message::SetQuickfix(entries) => {
+                let count = |severity| entries.iter().filter(|(_, d)| d.severity == severity).count();
+
+                self.message = match entries.len() {
+                    0 => "cargo check: no problems".to_string(),
+                    _ => format!("cargo check: {} errors, {} warnings", count(Severity::Error), count(Severity::Warning)),
+                };
+            },
+            Message::ClearDiagnostics(_) => {
+                self.generation += 1;
+            },
+            Message::RevertHunk { path, hunk } => {
+                // Hunks that have changed since the preview are left alone
+                if state.hunks.get(path).is_some_and(|hunks| hunks.contains(hunk)) {
+                    self.generation += 1;
+                    self.revert_hunk(path, hunk);
+                }
+            },
+            Message::Edit { path, start, end, text } => {
+                self.generation += 1;
+                self.edit(path, *start, *end, text);
+            },
+            Message::SetDiagnostics(report) if report.path == self.document.path => {
+                self.generation += 1;
+            },
+            // Something may have been committed or checked out
+            Message::SetGitStatus(_) => {
+                self.head_texts = HeadTexts::default();
+
+                for document in std::iter::once(&mut self.document).chain(&mut self.background) {
+                    document.diffed_revision = None;
+                }
+            },
+            _ => return,
+        }
+
+        self.document.diagnostics = state.diagnostics.for_path(&self.document.path);
+
+        if self.message.is_empty() {
+            self.message = self.diagnostic_at_cursor().unwrap_or_default();
+        }
+
+        self.sync_servers();
+        self.update_hunks();
+        self.publish(state, bus);
+    }
+
+    fn take_popups(&mut self) -> Vec<Box<dyn Window<AppState>>> {
+        std::mem::take(&mut self.popups)
+    }
+
+    fn take_jobs(&mut self) -> Vec<JobRequest<AppState>> {
+        std::mem::take(&mut self.jobs)
+    }
+
+    fn take_timeouts(&mut self) -> Vec<Duration> {
+        std::mem::take(&mut self.timeouts)
+    }
+}
+
+/// Where the cursor ends up after typing `text` at `start`.
+fn end_of(start: (usize, usize), text: &str) -> (usize, usize) {
+    match text.rsplit_once('\n') {
+        Some((before, after)) => (after.chars().count(), start.1 + before.matches('\n').count() + 1),
+        None => (start.0 + text.chars().count(), start.1),
+    }
+}
+
+/// The length of `line` in chars, without its line break.
+fn line_len(content: &Rope, line: usize) -> usize {
+    line_content(content.line(line)).chars().count()
+}
+
+fn toggle_case(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c.is_uppercase() {
+            true => c.to_lowercase().collect::<Vec<_>>(),
+            false => c.to_uppercase().collect(),
+        })
+        .collect()
+}
+
+fn ends_with_newline(content: &Rope) -> bool {
+    let len = content.len_chars();
+    len > 0 && content.char(len - 1) == '\n'
+}
+
+/// The column of the first character on `line` that isn't a space or tab.
+fn first_non_blank(content: &Rope, line: usize) -> usize {
+    content.line(line).chars().take_while(|c| *c == ' ' || *c == '\t').count()
+}
@@ -0,0 +1,120 @@
+//! Running `:s` over the visible document, and asking about each match in
+//! turn when it's confirmed.
+
+use crossterm::event::KeyCode;
+
+use crate::command::{ LineRange, SubstituteFlags };
+use crate::search::{ Search, SearchDirection };
+use crate::substitute::Substitution;
+
+use super::Buffer;
+
+impl Buffer {
+    pub(super) fn substitute(&mut self, range: LineRange, pattern: String, replacement: String, flags: SubstituteFlags) {
+        let pattern = match (pattern.is_empty(), &self.search) {
+            (false, _) => pattern,
+            (true, Some(search)) => search.pattern().to_string(),
+            (true, None) => {
+                self.message = "No previous regular expression".to_string();
+                return;
+            }
+        };
+
+        let pattern = match flags.ignore_case {
+            Some(true) => format!("{}\\c", pattern),
+            Some(false) => format!("{}\\C", pattern),
+            None => pattern,
+        };
+
+        let search = match Search::new(&pattern, SearchDirection::Forward, self.search_options) {
+            Ok(search) => search,
+            Err(e) => {
+                self.message = format!("Invalid pattern: {}", e.to_string().lines().last().unwrap_or_default());
+                return;
+            }
+        };
+
+        let lines = range.resolve(self.document.cursor.1, self.last_line());
+        let mut substitution = Substitution::new(
+            search.regex().clone(),
+            replacement,
+            flags.global,
+            lines,
+            self.document.cursor
+        );
+
+        self.search = Some(search);
+        self.highlight_search = true;
+
+        if flags.confirm {
+            self.next_confirmation(substitution);
+        } else {
+            substitution.replace_all(&mut self.document.content);
+            self.finish_substitution(substitution);
+        }
+    }
+
+    /// Moves to the next match of a confirmed substitution, or finishes it if
+    /// there are none left.
+    fn next_confirmation(&mut self, mut substitution: Substitution) {
+        match substitution.next_match(&self.document.content) {
+            Some(pending) => {
+                let (start, _) = pending.columns(&self.document.content);
+
+                self.document.cursor = (start, pending.line);
+                self.scroll_to_cursor();
+                self.substitution = Some((substitution, pending));
+            },
+            None => self.finish_substitution(substitution),
+        }
+    }
+
+    pub(super) fn handle_confirm_input(&mut self, code: KeyCode) {
+        let Some((mut substitution, pending)) = self.substitution.take() else { return };
+
+        match code {
+            KeyCode::Char('y') => {
+                substitution.replace(&mut self.document.content, &pending);
+                self.next_confirmation(substitution);
+            },
+            KeyCode::Char('n') => {
+                substitution.skip(&self.document.content, &pending);
+                self.next_confirmation(substitution);
+            },
+            KeyCode::Char('a') => {
+                substitution.replace(&mut self.document.content, &pending);
+                substitution.replace_all(&mut self.document.content);
+                self.finish_substitution(substitution);
+            },
+            KeyCode::Char('l') => {
+                substitution.replace(&mut self.document.content, &pending);
+                self.finish_substitution(substitution);
+            },
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.finish_substitution(substitution);
+            },
+            _ => {
+                self.substitution = Some((substitution, pending));
+            }
+        }
+    }
+
+    pub(super) fn finish_substitution(&mut self, substitution: Substitution) {
+        let (replacements, lines) = (substitution.replacements(), substitution.lines_changed());
+
+        if let Some(cursor) = substitution.cursor() {
+            self.document.cursor = cursor;
+        }
+
+        self.document.history.commit(substitution.finish());
+        self.document.cursor.1 = self.document.cursor.1.min(self.last_line());
+        self.scroll_to_cursor();
+
+        self.message = match (replacements, &self.search) {
+            (0, Some(search)) => format!("Pattern not found: {}", search.pattern()),
+            (0, None) => String::new(),
+            (1, _) => "1 substitution on 1 line".to_string(),
+            (n, _) => format!("{} substitutions on {} line{}", n, lines, if lines == 1 { "" } else { "s" }),
+        };
+    }
+}
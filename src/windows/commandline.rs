@@ -0,0 +1,87 @@
+use crossterm::style::{ Attribute, ContentStyle, Color, Stylize };
+
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+use crate::{ AppState, Message };
+
+/// The command gutter: shows whatever is being typed at a prompt, and
+/// feedback such as match counts or errors below it.
+#[derive(Debug)]
+pub struct CommandLine {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    command_line: String,
+    message: String,
+    generation: u64,
+}
+
+impl CommandLine {
+    pub fn new(info: WindowInfo) -> Self {
+        Self {
+            info,
+            bounds: None,
+            command_line: String::new(),
+            message: String::new(),
+            generation: 0,
+        }
+    }
+}
+
+impl Window<AppState> for CommandLine {
+    fn info(&self) -> WindowInfo {
+        self.info
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        vec![
+            StyledContent::from(self.command_line.clone()),
+            StyledContent::from_styled(
+                self.message.clone(),
+                ContentStyle::default().with(Color::Grey)
+            ),
+        ]
+    }
+
+    fn title(&self) -> &str {
+        "[ COMMAND ]"
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn update_state(&mut self, new_state: &AppState) {
+        let AppState { command_line, message, .. } = new_state;
+
+        if self.command_line != *command_line || self.message != *message {
+            self.command_line = command_line.clone();
+            self.message = message.clone();
+            self.generation += 1;
+        }
+    }
+
+    fn on_message(&mut self, state: &AppState, message: &Message, _bus: &mut Bus<Message>) {
+        if let Message::SetCommandLine(_) | Message::ShowMessage(_) = message {
+            self.update_state(state);
+        }
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+}
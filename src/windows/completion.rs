@@ -0,0 +1,201 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use crossterm::{style::{ Attribute, ContentStyle, Color, Stylize }, event::{ KeyCode, KeyModifiers }};
+
+use crate::lsp::CompletionItem;
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+use crate::{ AppState, Message };
+
+const MAX_WIDTH: usize = 60;
+const MAX_HEIGHT: usize = 10;
+
+/// A popup menu by the cursor of ways to complete the word before it.
+/// Typing narrows the list down, and Enter or Tab puts the selected item
+/// in place of the word.
+#[derive(Debug)]
+pub struct Completion {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    path: PathBuf,
+    items: Vec<CompletionItem>,
+    // What items without a range of their own replace, as `(column, line)`s
+    range: ((usize, usize), (usize, usize)),
+    filter: String,
+    // Indices into `items`
+    matches: Vec<usize>,
+    selected: usize,
+    scroll: usize,
+    closed: bool,
+    generation: u64,
+}
+
+impl Completion {
+    pub fn new(path: PathBuf, items: Vec<CompletionItem>, range: ((usize, usize), (usize, usize))) -> Self {
+        let width = items.iter()
+            .map(|i| i.label.chars().count() + i.detail.as_ref().map_or(0, |d| d.chars().count() + 2))
+            .max()
+            .unwrap_or(0);
+
+        // Two columns for the selection marker, and one for the border
+        let info = WindowInfo::new()
+            .at_cursor()
+            .bounds((width + 2).min(MAX_WIDTH) as u16 + 1, items.len().clamp(1, MAX_HEIGHT) as u16 + 1)
+            .modal();
+
+        Self {
+            info,
+            bounds: None,
+            path,
+            matches: (0..items.len()).collect(),
+            items,
+            range,
+            filter: String::new(),
+            selected: 0,
+            scroll: 0,
+            closed: false,
+            generation: 0,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.bounds.map_or(1, |b| b.height.saturating_sub(1).max(1) as usize)
+    }
+
+    fn width(&self) -> usize {
+        self.bounds.map_or(0, |b| b.width.saturating_sub(1) as usize)
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.matches.len().saturating_sub(1));
+
+        let height = self.height();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    fn refilter(&mut self) {
+        let filter = self.filter.to_lowercase();
+
+        self.matches = (0..self.items.len())
+            .filter(|i| self.items[*i].label.to_lowercase().contains(&filter))
+            .collect();
+        self.select(0);
+    }
+
+    fn accept(&mut self, bus: &mut Bus<Message>) {
+        self.closed = true;
+
+        let Some(item) = self.matches.get(self.selected).map(|i| &self.items[*i]) else { return };
+        let (start, end) = item.range.unwrap_or(self.range);
+
+        bus.send(Message::Edit { path: self.path.clone(), start, end, text: item.text.clone() });
+    }
+}
+
+impl Window<AppState> for Completion {
+    fn info(&self) -> WindowInfo {
+        self.info
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        let width = self.width();
+
+        self.matches.iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.height())
+            .map(|(row, i)| {
+                let item = &self.items[*i];
+                let selected = row == self.selected;
+
+                let style = if selected {
+                    ContentStyle::default().with(Color::Blue).attribute(Attribute::Bold)
+                } else {
+                    ContentStyle::default()
+                };
+
+                let mut line = StyledContent::new();
+                line.push(if selected { "> " } else { "  " }.to_string(), style);
+                line.push(item.label.clone(), style);
+
+                if let Some(detail) = &item.detail {
+                    let used = 2 + item.label.chars().count();
+                    let room = width.saturating_sub(used + 2);
+
+                    if room > 0 {
+                        let detail: String = detail.chars().take(room).collect();
+                        let padding = width.saturating_sub(used + detail.chars().count());
+
+                        line.push(" ".repeat(padding), ContentStyle::default());
+                        line.push(detail, ContentStyle::default().with(Color::DarkGrey));
+                    }
+                }
+
+                line
+            })
+            .collect()
+    }
+
+    fn title(&self) -> &str {
+        &self.filter
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+        self.generation += 1;
+        self.select(self.selected);
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn handle_input(&mut self, _state: &AppState, bus: &mut Bus<Message>, code: KeyCode, modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        let control = modifiers.contains(KeyModifiers::CONTROL);
+        self.generation += 1;
+
+        match code {
+            KeyCode::Up => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Char('p') if control => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Down => self.select(self.selected + 1),
+            KeyCode::Char('n') if control => self.select(self.selected + 1),
+            KeyCode::Enter | KeyCode::Tab => self.accept(bus),
+            KeyCode::Char(c) if !control => {
+                self.filter.push(c);
+                self.refilter();
+            },
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.refilter();
+            },
+            KeyCode::Esc => self.closed = true,
+            _ => { }
+        }
+
+        Ok(())
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+    fn should_close(&self) -> bool {
+        self.closed
+    }
+}
@@ -0,0 +1,176 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use crossterm::{style::{ Attribute, ContentStyle, Color, Stylize }, event::{ KeyCode, KeyModifiers }};
+
+use crate::diagnostics::{ Diagnostic, Severity };
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+use crate::{ AppState, Message };
+
+/// Every diagnostic for every file, in the order they appear in each.
+/// j/k pick one and Enter jumps to it.
+#[derive(Debug)]
+pub struct Diagnostics {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    root: PathBuf,
+    entries: Vec<(PathBuf, Diagnostic)>,
+    selected: usize,
+    scroll: usize,
+    title: String,
+    generation: u64,
+}
+
+impl Diagnostics {
+    pub fn new(info: WindowInfo) -> Self {
+        Self {
+            info,
+            bounds: None,
+            root: PathBuf::from("."),
+            entries: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            title: "[ DIAGNOSTICS ]".to_string(),
+            generation: 0,
+        }
+    }
+
+    fn list_height(&self) -> usize {
+        self.bounds.map_or(1, |b| b.height.saturating_sub(1).max(1) as usize)
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len().saturating_sub(1));
+
+        let height = self.list_height();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    fn entry_line(&self, (path, diagnostic): &(PathBuf, Diagnostic), selected: bool) -> StyledContent {
+        let path = path.strip_prefix(&self.root).unwrap_or(path);
+        let marker = if selected { "> " } else { "  " };
+        let (column, line) = diagnostic.start;
+
+        let mut location = ContentStyle::default().with(Color::Blue);
+        if selected {
+            location = location.attribute(Attribute::Bold);
+        }
+
+        let mut styled = StyledContent::new();
+        styled.push(format!("{}{}:{}:{}: ", marker, path.display(), line + 1, column + 1), location);
+        styled.push(diagnostic.severity.to_string(), ContentStyle::default().with(diagnostic.severity.color()).attribute(Attribute::Bold));
+        // Only the first line of long compiler messages fits
+        styled.push(format!(": {}", diagnostic.message.lines().next().unwrap_or_default()), ContentStyle::default());
+
+        if let Some(source) = &diagnostic.source {
+            styled.push(format!(" [{}]", source), ContentStyle::default().with(Color::DarkGrey));
+        }
+
+        styled
+    }
+}
+
+impl Window<AppState> for Diagnostics {
+    fn info(&self) -> WindowInfo {
+        self.info.selectable()
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        if self.entries.is_empty() {
+            return vec![ StyledContent::from_styled("  No problems".to_string(), ContentStyle::default().with(Color::Grey)) ];
+        }
+
+        self.entries.iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.list_height())
+            .map(|(i, entry)| self.entry_line(entry, i == self.selected))
+            .collect()
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+        self.select(self.selected);
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn handle_input(&mut self, _state: &AppState, bus: &mut Bus<Message>, code: KeyCode, _modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        self.generation += 1;
+
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => self.select(self.selected + 1),
+            KeyCode::Char('k') | KeyCode::Up => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Char('g') | KeyCode::Home => self.select(0),
+            KeyCode::Char('G') | KeyCode::End => self.select(usize::MAX),
+            KeyCode::Enter => {
+                if let Some((path, diagnostic)) = self.entries.get(self.selected) {
+                    bus.send(Message::Jump { path: path.clone(), position: diagnostic.start });
+                }
+            },
+            _ => { }
+        }
+
+        Ok(())
+    }
+
+    fn update_state(&mut self, new_state: &AppState) {
+        if !new_state.root.as_os_str().is_empty() {
+            self.root = new_state.root.clone();
+        }
+
+        let entries: Vec<(PathBuf, Diagnostic)> = new_state.diagnostics.all()
+            .into_iter()
+            .map(|(path, diagnostic)| (path.to_path_buf(), diagnostic.clone()))
+            .collect();
+
+        if entries == self.entries {
+            return;
+        }
+
+        self.entries = entries;
+        self.title = match (new_state.diagnostics.count(Severity::Error), new_state.diagnostics.count(Severity::Warning)) {
+            (0, 0) if self.entries.is_empty() => "[ DIAGNOSTICS ]".to_string(),
+            (errors, warnings) => format!("[ DIAGNOSTICS: {} errors, {} warnings ]", errors, warnings),
+        };
+
+        self.select(self.selected);
+        self.generation += 1;
+    }
+
+    fn on_message(&mut self, state: &AppState, message: &Message, _bus: &mut Bus<Message>) {
+        if let Message::SetDiagnostics(_) | Message::ClearDiagnostics(_) = message {
+            self.update_state(state);
+        }
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+    fn cursor_position(&self) -> Option<(u16, u16)> {
+        Some((0, self.selected.saturating_sub(self.scroll) as u16))
+    }
+}
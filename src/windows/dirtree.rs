@@ -0,0 +1,470 @@
+use std::collections::{ BTreeSet, HashSet };
+use std::error::Error;
+use std::fs;
+use std::ops::Bound;
+use std::path::{ Path, PathBuf };
+use std::sync::{
+    Arc, RwLock,
+    atomic::{ AtomicBool, Ordering },
+    mpsc::{ self, Receiver },
+};
+
+use crossterm::{style::{ Attribute, ContentStyle, Color, Stylize }, event::{ KeyCode, KeyModifiers }};
+use notify::{ Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher, event::ModifyKind };
+
+use crate::events::Waker;
+use crate::git::{ self, RepoStatus, Status };
+use crate::jobs::JobRequest;
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+use crate::{ AppState, Message };
+
+/// Absolute paths of the files and directories git ignores, shared with
+/// the watcher so it can leave them out.
+type Ignored = Arc<RwLock<BTreeSet<PathBuf>>>;
+
+#[derive(Debug)]
+struct Entry {
+    /// Joined onto the root, so it matches the paths other windows open
+    path: PathBuf,
+    depth: usize,
+    dir: bool,
+    status: Option<Status>,
+}
+
+/// The files under the root, with what git thinks of each. j/k pick one,
+/// Enter opens it or folds a directory, and h/l fold and unfold.
+#[derive(Debug)]
+pub struct DirTree {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    root: PathBuf,
+    entries: Vec<Entry>,
+    expanded: HashSet<PathBuf>,
+    selected: usize,
+    scroll: usize,
+    status: RepoStatus,
+    watcher: Option<RecommendedWatcher>,
+    // Changed paths, and whether only their content changed
+    changes: Option<Receiver<(PathBuf, bool)>>,
+    ignored: Ignored,
+    // Set once the watcher has woken the main loop, until the changes are
+    // picked up, so a burst of them only wakes it once
+    woken: Arc<AtomicBool>,
+    watching: bool,
+    waker: Option<Waker>,
+    // Whether a `git status` job is running, and whether another is needed
+    // once it is done
+    refreshing: bool,
+    stale: bool,
+    jobs: Vec<JobRequest<AppState>>,
+    generation: u64,
+}
+
+impl DirTree {
+    pub fn new(info: WindowInfo) -> Self {
+        Self {
+            info,
+            bounds: None,
+            root: PathBuf::new(),
+            entries: Vec::new(),
+            expanded: HashSet::new(),
+            selected: 0,
+            scroll: 0,
+            status: RepoStatus::default(),
+            watcher: None,
+            changes: None,
+            ignored: Ignored::default(),
+            woken: Arc::default(),
+            watching: false,
+            waker: None,
+            refreshing: false,
+            stale: true,
+            jobs: Vec::new(),
+            generation: 0,
+        }
+    }
+
+    fn list_height(&self) -> usize {
+        self.bounds.map_or(1, |b| b.height.saturating_sub(1).max(1) as usize)
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len().saturating_sub(1));
+
+        let height = self.list_height();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    /// Lists the root again, keeping the same entry selected if it's still
+    /// there.
+    fn scan(&mut self) {
+        let selected = self.entries.get(self.selected).map(|e| e.path.clone());
+
+        self.entries.clear();
+        self.scan_dir(&self.root.clone(), 0);
+        self.update_statuses();
+
+        let index = selected
+            .and_then(|path| self.entries.iter().position(|e| e.path == path))
+            .unwrap_or(self.selected);
+
+        self.select(index);
+        self.generation += 1;
+    }
+
+    fn scan_dir(&mut self, dir: &Path, depth: usize) {
+        let Ok(read) = fs::read_dir(dir) else { return };
+
+        let mut children: Vec<(bool, String)> = read
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name() != ".git")
+            .map(|entry| (entry.path().is_dir(), entry.file_name().to_string_lossy().into_owned()))
+            .collect();
+
+        // Directories first
+        children.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+        for (is_dir, name) in children {
+            let path = dir.join(name);
+            let expanded = is_dir && self.expanded.contains(&path);
+
+            self.entries.push(Entry { path: path.clone(), depth, dir: is_dir, status: None });
+
+            if expanded {
+                self.scan_dir(&path, depth + 1);
+            }
+        }
+    }
+
+    fn update_statuses(&mut self) {
+        for entry in &mut self.entries {
+            entry.status = self.status.get(&entry.path);
+        }
+    }
+
+    /// Starts noticing files being created, changed and deleted under the
+    /// root, and git's own files changing, as they do on a commit.
+    fn start_watching(&mut self) {
+        self.watching = true;
+
+        let (sender, receiver) = mpsc::channel();
+        let waker = self.waker.clone();
+        let ignored = Arc::clone(&self.ignored);
+        let woken = Arc::clone(&self.woken);
+
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else { return };
+
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                return;
+            }
+
+            let content = matches!(event.kind, EventKind::Modify(ModifyKind::Data(_)));
+            let ignored = ignored.read().unwrap();
+            let mut sent = false;
+
+            for path in event.paths {
+                // Builds and git's object store churn through files that
+                // would only be thrown away on the main thread
+                if is_noise(&path, &ignored) {
+                    continue;
+                }
+
+                let _ = sender.send((path, content));
+                sent = true;
+            }
+
+            if sent && !woken.swap(true, Ordering::AcqRel) {
+                if let Some(waker) = &waker {
+                    waker.wake();
+                }
+            }
+        });
+
+        let root = self.root.canonicalize();
+        let result: Result<RecommendedWatcher, Box<dyn Error>> = match (watcher, root) {
+            (Ok(mut watcher), Ok(root)) => watcher.watch(&root, RecursiveMode::Recursive)
+                .map(|_| watcher)
+                .map_err(|e| e.into()),
+            (Err(e), _) => Err(e.into()),
+            (_, Err(e)) => Err(e.into()),
+        };
+
+        match result {
+            Ok(watcher) => {
+                self.watcher = Some(watcher);
+                self.changes = Some(receiver);
+            },
+            Err(e) => warn!("Can't watch {}: {}", self.root.display(), e),
+        }
+    }
+
+    /// Whether a change to `path` that got past the watcher could change the
+    /// listing or the status. Writing to a file that is already changed
+    /// leaves it changed.
+    fn is_relevant(&self, path: &Path, content: bool) -> bool {
+        if path.components().any(|c| c.as_os_str() == ".git") {
+            return true;
+        }
+
+        match self.status.get(path) {
+            Some(Status::Ignored) => false,
+            Some(Status::Modified | Status::Untracked) => !content,
+            _ => true,
+        }
+    }
+
+    /// Asks git about the root again in the background, once whatever it
+    /// is already doing is finished.
+    fn refresh_status(&mut self) {
+        self.stale = true;
+
+        if self.refreshing || self.root.as_os_str().is_empty() {
+            return;
+        }
+
+        self.refreshing = true;
+        self.stale = false;
+
+        let root = self.root.clone();
+
+        self.jobs.push(JobRequest::new("git status", move |context| {
+            let status = git::status(&root).unwrap_or_default();
+            context.send(Message::SetGitStatus(status));
+        }));
+    }
+
+    fn entry_line(&self, entry: &Entry, selected: bool) -> StyledContent {
+        let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+        let (marker, color) = match entry.status {
+            Some(status) => (status.marker(), status.color()),
+            None => (' ', Color::Reset),
+        };
+
+        let arrow = match (entry.dir, self.expanded.contains(&entry.path)) {
+            (true, true) => "▾ ",
+            (true, false) => "▸ ",
+            (false, _) => "  ",
+        };
+
+        let mut style = ContentStyle::default().with(color);
+        if entry.dir {
+            style = style.attribute(Attribute::Bold);
+        }
+        if selected {
+            style = style.attribute(Attribute::Reverse);
+        }
+
+        let mut styled = StyledContent::new();
+        styled.push(format!("{} ", marker), ContentStyle::default().with(color).attribute(Attribute::Bold));
+        styled.push(format!("{}{}", "  ".repeat(entry.depth), arrow), ContentStyle::default().with(Color::DarkGrey));
+        styled.push(format!("{}{}", name, if entry.dir { "/" } else { "" }), style);
+
+        styled
+    }
+
+    /// Opens the selected file, or folds or unfolds the selected directory.
+    fn activate(&mut self, bus: &mut Bus<Message>) {
+        let Some(entry) = self.entries.get(self.selected) else { return };
+
+        if !entry.dir {
+            bus.send(Message::OpenFile(entry.path.clone()));
+            return;
+        }
+
+        let path = entry.path.clone();
+
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+
+        self.scan();
+    }
+
+    fn expand(&mut self) {
+        let Some(entry) = self.entries.get(self.selected) else { return };
+
+        if entry.dir && self.expanded.insert(entry.path.clone()) {
+            self.scan();
+        }
+    }
+
+    /// Folds the selected directory, or selects the one the entry is in.
+    fn collapse(&mut self) {
+        let Some(entry) = self.entries.get(self.selected) else { return };
+
+        if entry.dir && self.expanded.remove(&entry.path) {
+            self.scan();
+            return;
+        }
+
+        let parent = entry.path.parent().map(Path::to_path_buf);
+
+        if let Some(index) = self.entries.iter().position(|e| Some(&e.path) == parent.as_ref()) {
+            self.select(index);
+        }
+    }
+}
+
+impl Window<AppState> for DirTree {
+    fn info(&self) -> WindowInfo {
+        self.info.selectable()
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        self.entries.iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.list_height())
+            .map(|(i, entry)| self.entry_line(entry, i == self.selected))
+            .collect()
+    }
+
+    fn title(&self) -> &str {
+        "[ DIR TREE ]"
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+        self.select(self.selected);
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn handle_input(&mut self, _state: &AppState, bus: &mut Bus<Message>, code: KeyCode, _modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        self.generation += 1;
+
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => self.select(self.selected + 1),
+            KeyCode::Char('k') | KeyCode::Up => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Char('g') | KeyCode::Home => self.select(0),
+            KeyCode::Char('G') | KeyCode::End => self.select(usize::MAX),
+            KeyCode::Char('l') | KeyCode::Right => self.expand(),
+            KeyCode::Char('h') | KeyCode::Left => self.collapse(),
+            KeyCode::Enter => self.activate(bus),
+            KeyCode::Char('r') => {
+                self.scan();
+                self.refresh_status();
+            },
+            _ => { }
+        }
+
+        Ok(())
+    }
+
+    fn update_state(&mut self, new_state: &AppState) {
+        let root = match &new_state.root {
+            root if root.as_os_str().is_empty() => PathBuf::from("."),
+            root => root.clone(),
+        };
+
+        if root != self.root {
+            self.root = root;
+            self.expanded.clear();
+            self.watcher = None;
+            self.changes = None;
+            self.ignored.write().unwrap().clear();
+            self.watching = false;
+            self.stale = true;
+            self.scan();
+        }
+    }
+
+    fn on_message(&mut self, _state: &AppState, message: &Message, _bus: &mut Bus<Message>) {
+        let Message::SetGitStatus(status) = message else { return };
+
+        self.refreshing = false;
+
+        if *status != self.status {
+            self.status = status.clone();
+            self.update_statuses();
+            self.generation += 1;
+
+            *self.ignored.write().unwrap() = status.files.iter()
+                .filter(|(_, status)| **status == Status::Ignored)
+                .map(|(path, _)| status.root.join(path))
+                .collect();
+        }
+
+        if self.stale {
+            self.refresh_status();
+        }
+    }
+
+    fn tick(&mut self, _state: &AppState, _bus: &mut Bus<Message>) -> bool {
+        if !self.watching {
+            self.start_watching();
+        }
+
+        // Anything sent after this wakes the loop again
+        self.woken.store(false, Ordering::Release);
+
+        let changed: Vec<(PathBuf, bool)> = match &self.changes {
+            Some(changes) => changes.try_iter().collect(),
+            None => Vec::new(),
+        };
+
+        let relevant = changed.iter().any(|(path, content)| self.is_relevant(path, *content));
+
+        if relevant {
+            self.scan();
+        }
+
+        if relevant || (self.stale && !self.refreshing) {
+            self.refresh_status();
+        }
+
+        relevant
+    }
+
+    fn set_waker(&mut self, waker: Waker) {
+        self.waker = Some(waker);
+
+        // Watch again, so changes wake the main loop
+        self.watching = false;
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+    fn cursor_position(&self) -> Option<(u16, u16)> {
+        Some((0, self.selected.saturating_sub(self.scroll) as u16))
+    }
+
+    fn take_jobs(&mut self) -> Vec<JobRequest<AppState>> {
+        std::mem::take(&mut self.jobs)
+    }
+}
+
+/// Whether a change to `path` can't matter to the listing or the status:
+/// git ignores it, or it's one of git's own files besides the index, `HEAD`
+/// and the refs.
+fn is_noise(path: &Path, ignored: &BTreeSet<PathBuf>) -> bool {
+    if path.components().any(|c| c.as_os_str() == ".git") {
+        let name = path.file_name().unwrap_or_default();
+        return !(name == "index" || name == "HEAD" || path.components().any(|c| c.as_os_str() == "refs"));
+    }
+
+    // Ignored directories are listed once, and sort right before anything
+    // in them
+    ignored.range::<Path, _>((Bound::Unbounded, Bound::Included(path))).next_back().is_some_and(|p| path.starts_with(p))
+}
@@ -1,6 +1,7 @@
 use crossterm::style::{ Attribute, ContentStyle, Color, Stylize };
 
 use crate::ui::{
+    bus::State,
     rect::Rect,
     window::{ WindowInfo, Window, StyledContent }
 };
@@ -22,7 +23,7 @@ impl Empty {
     }
 }
 
-impl<S> Window<S> for Empty {
+impl<S: State> Window<S> for Empty {
     fn info(&self) -> WindowInfo {
         self.info
     }
@@ -49,4 +50,8 @@ impl<S> Window<S> for Empty {
     fn get_bounds(&self) -> Rect {
         self.bounds.unwrap_or_default()
     }
+
+    fn generation(&self) -> Option<u64> {
+        Some(0)
+    }
 }
@@ -0,0 +1,357 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{ BufRead, BufReader };
+use std::path::{ Path, PathBuf };
+
+use crossterm::{style::{ Attribute, ContentStyle, Color, Stylize }, event::{ KeyCode, KeyModifiers }};
+
+use crate::events::Waker;
+use crate::finder::{ FileList, FuzzyMatch, fuzzy_match };
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+use crate::{ AppState, Message };
+
+/// Files with a NUL byte in this many leading bytes aren't previewed.
+const BINARY_CHECK_LEN: usize = 8192;
+
+/// A popup listing every file under `AppState::root`, narrowed down by a
+/// fuzzy query, with a preview of the selected one. Enter opens the file.
+#[derive(Debug)]
+pub struct Finder {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    root: PathBuf,
+    // `None` before the walk starts, and once it's finished
+    walk: Option<FileList>,
+    started: bool,
+    files: Vec<PathBuf>,
+    // `files`, relative to `root`, as matched against and displayed
+    names: Vec<String>,
+    query: String,
+    // Indices into `files`, best first
+    matches: Vec<(usize, FuzzyMatch)>,
+    selected: usize,
+    scroll: usize,
+    // The file `preview` was read from
+    previewed: Option<usize>,
+    preview: Vec<String>,
+    title: String,
+    closed: bool,
+    generation: u64,
+}
+
+impl Finder {
+    pub fn new(info: WindowInfo, root: PathBuf) -> Self {
+        let mut finder = Self {
+            info,
+            bounds: None,
+            walk: None,
+            started: false,
+            root,
+            files: Vec::new(),
+            names: Vec::new(),
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            previewed: None,
+            preview: Vec::new(),
+            title: String::new(),
+            closed: false,
+            generation: 0,
+        };
+
+        finder.update_title();
+        finder
+    }
+
+    fn start(&mut self, waker: Option<Waker>) {
+        self.walk = Some(FileList::spawn(self.root.clone(), waker));
+        self.started = true;
+        self.update_title();
+    }
+
+    fn list_height(&self) -> usize {
+        // One row for the query, one for the bottom border
+        self.bounds.map_or(1, |b| b.height.saturating_sub(2).max(1) as usize)
+    }
+
+    fn list_width(&self) -> usize {
+        self.bounds.map_or(0, |b| b.width.saturating_sub(1) as usize * 2 / 5)
+    }
+
+    fn update_title(&mut self) {
+        let searching = if self.walk.is_some() { ", searching..." } else { "" };
+
+        self.title = format!("[ FILES: {}/{}{} ]", self.matches.len(), self.files.len(), searching);
+    }
+
+    fn score(&self, index: usize) -> Option<(usize, FuzzyMatch)> {
+        fuzzy_match(&self.query, &self.names[index]).map(|m| (index, m))
+    }
+
+    fn sort_matches(&mut self) {
+        let names = &self.names;
+
+        self.matches.sort_by(|(a, ma), (b, mb)| {
+            mb.score.cmp(&ma.score)
+                .then(names[*a].len().cmp(&names[*b].len()))
+                .then(names[*a].cmp(&names[*b]))
+        });
+    }
+
+    /// Scores every file against the query again.
+    fn rematch(&mut self) {
+        self.matches = (0..self.files.len())
+            .filter_map(|i| self.score(i))
+            .collect();
+
+        self.sort_matches();
+        self.select(0);
+        self.update_title();
+    }
+
+    fn add_files(&mut self, paths: Vec<PathBuf>) {
+        let start = self.files.len();
+
+        for path in paths {
+            let name = path.strip_prefix(&self.root).unwrap_or(&path);
+
+            self.names.push(name.display().to_string());
+            self.files.push(path);
+        }
+
+        let selected = self.matches.get(self.selected).map(|(i, _)| *i);
+        let new_matches: Vec<_> = (start..self.files.len())
+            .filter_map(|i| self.score(i))
+            .collect();
+
+        self.matches.extend(new_matches);
+        self.sort_matches();
+
+        // Keep the same file selected as more arrive
+        let index = selected
+            .and_then(|s| self.matches.iter().position(|(i, _)| *i == s))
+            .unwrap_or(0);
+        self.select(index);
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.matches.len().saturating_sub(1));
+
+        let height = self.list_height();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+
+        let current = self.matches.get(self.selected).map(|(i, _)| *i);
+        if current != self.previewed {
+            self.previewed = current;
+            self.preview = match current {
+                Some(i) => preview(&self.files[i], self.list_height()),
+                None => Vec::new(),
+            };
+        }
+    }
+
+    fn result_line(&self, index: usize, width: usize) -> StyledContent {
+        let mut line = StyledContent::new();
+
+        let Some((file, found)) = self.matches.get(index) else {
+            line.push(" ".repeat(width), ContentStyle::default());
+            return line;
+        };
+
+        let selected = index == self.selected;
+        let name = &self.names[*file];
+
+        let mut style = ContentStyle::default();
+        if selected {
+            style = style.with(Color::Blue).attribute(Attribute::Bold);
+        }
+
+        line.push(if selected { "> " } else { "  " }.to_string(), style);
+
+        // Cut long paths from the left; the file name matters most
+        let room = width.saturating_sub(2);
+        let count = name.chars().count();
+        let skip = if count > room {
+            line.push("…".to_string(), ContentStyle::default().with(Color::Grey));
+            name.char_indices().nth(count - room + 1).map_or(name.len(), |(i, _)| i)
+        } else {
+            0
+        };
+
+        let offset = line.len();
+        line.push(name[skip..].to_string(), style);
+
+        let highlight = style.with(Color::Yellow).attribute(Attribute::Bold);
+        for &i in found.indices.iter().filter(|&&i| i >= skip) {
+            let len = name[i..].chars().next().map_or(1, |c| c.len_utf8());
+            let start = offset + i - skip;
+
+            line.style_range(start..start + len, highlight);
+        }
+
+        let padding = width.saturating_sub(2 + count.min(room));
+        line.push(" ".repeat(padding), ContentStyle::default());
+
+        line
+    }
+}
+
+/// The first `lines` lines of `path`, with tabs expanded.
+fn preview(path: &Path, lines: usize) -> Vec<String> {
+    let read = || -> std::io::Result<Vec<String>> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        if reader.fill_buf()?.iter().take(BINARY_CHECK_LEN).any(|b| *b == 0) {
+            return Ok(vec![ "(binary file)".to_string() ]);
+        }
+
+        let mut preview = Vec::with_capacity(lines);
+        let mut bytes = Vec::new();
+
+        while preview.len() < lines && reader.read_until(b'\n', &mut bytes)? > 0 {
+            let text = String::from_utf8_lossy(&bytes);
+            preview.push(text.trim_end_matches(['\n', '\r']).replace('\t', "    "));
+
+            bytes.clear();
+        }
+
+        Ok(preview)
+    };
+
+    read().unwrap_or_else(|e| vec![ format!("({})", e) ])
+}
+
+impl Window<AppState> for Finder {
+    fn info(&self) -> WindowInfo {
+        self.info.selectable()
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        let mut query = StyledContent::new();
+        query.push("> ".to_string(), ContentStyle::default().with(Color::Grey));
+        query.push(self.query.clone(), ContentStyle::default());
+
+        let width = self.list_width();
+        let separator = ContentStyle::default().with(Color::DarkGrey);
+
+        let rows = (0..self.list_height()).map(|row| {
+            let mut line = self.result_line(self.scroll + row, width);
+            line.push("│ ".to_string(), separator);
+
+            if let Some(text) = self.preview.get(row) {
+                line.push(text.clone(), ContentStyle::default());
+            }
+
+            line
+        });
+
+        std::iter::once(query).chain(rows).collect()
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+        self.previewed = None;
+        self.generation += 1;
+        self.select(self.selected);
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn handle_input(&mut self, _state: &AppState, bus: &mut Bus<Message>, code: KeyCode, modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        let control = modifiers.contains(KeyModifiers::CONTROL);
+        self.generation += 1;
+
+        match code {
+            KeyCode::Up => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Char('p') | KeyCode::Char('k') if control => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Down => self.select(self.selected + 1),
+            KeyCode::Char('n') | KeyCode::Char('j') if control => self.select(self.selected + 1),
+            KeyCode::Char(c) if !control => {
+                self.query.push(c);
+                self.rematch();
+            },
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.rematch();
+            },
+            KeyCode::Enter => {
+                if let Some((i, _)) = self.matches.get(self.selected) {
+                    bus.send(Message::OpenFile(self.files[*i].clone()));
+                    self.closed = true;
+                }
+            },
+            KeyCode::Esc => self.closed = true,
+            _ => { }
+        }
+
+        Ok(())
+    }
+
+    fn tick(&mut self, _state: &AppState, _bus: &mut Bus<Message>) -> bool {
+        // Nothing gave us a waker, so this is the first chance to start
+        if !self.started {
+            self.start(None);
+        }
+
+        let Some(walk) = &mut self.walk else { return false };
+
+        let paths = walk.poll();
+        let done = walk.is_done();
+
+        if paths.is_empty() && !done {
+            return false;
+        }
+
+        if done {
+            self.walk = None;
+        }
+
+        self.add_files(paths);
+        self.update_title();
+        self.generation += 1;
+
+        true
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+
+    fn cursor_position(&self) -> Option<(u16, u16)> {
+        Some(((2 + self.query.chars().count()) as u16, 0))
+    }
+
+    fn set_waker(&mut self, waker: Waker) {
+        if !self.started {
+            self.start(Some(waker));
+        }
+    }
+
+    fn should_close(&self) -> bool {
+        self.closed
+    }
+}
@@ -0,0 +1,281 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use crossterm::{style::{ Attribute, ContentStyle, Color, Stylize }, event::{ KeyCode, KeyModifiers }};
+use regex::Regex;
+
+use crate::events::Waker;
+use crate::grep::{ GrepEvent, GrepMatch, GrepSearch };
+use crate::search::{ Search, SearchDirection, SearchOptions };
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+use crate::{ AppState, Message };
+
+/// Results past this many are dropped, and the search stopped.
+const MAX_RESULTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Query,
+    Results,
+}
+
+/// Searches every file under `AppState::root` and lists the matching lines.
+/// Typing edits the query and Enter runs it; in the list, j/k pick a result
+/// and Enter opens it in the buffer.
+#[derive(Debug)]
+pub struct Grep {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    root: PathBuf,
+    options: SearchOptions,
+    focus: Focus,
+    query: String,
+    regex: Option<Regex>,
+    search: Option<GrepSearch>,
+    results: Vec<GrepMatch>,
+    selected: usize,
+    scroll: usize,
+    title: String,
+    waker: Option<Waker>,
+    generation: u64,
+}
+
+impl Grep {
+    pub fn new(info: WindowInfo) -> Self {
+        Self {
+            info,
+            bounds: None,
+            root: PathBuf::from("."),
+            options: SearchOptions::default(),
+            focus: Focus::Query,
+            query: String::new(),
+            regex: None,
+            search: None,
+            results: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            title: "[ GREP ]".to_string(),
+            waker: None,
+            generation: 0,
+        }
+    }
+
+    pub fn search_options(self, options: SearchOptions) -> Self {
+        Self { options, ..self }
+    }
+
+    fn list_height(&self) -> usize {
+        // One row for the query, one for the bottom border
+        self.bounds.map_or(1, |b| b.height.saturating_sub(2).max(1) as usize)
+    }
+
+    fn start(&mut self) {
+        self.search = None;
+        self.results.clear();
+        self.selected = 0;
+        self.scroll = 0;
+
+        if self.query.is_empty() {
+            self.regex = None;
+            self.title = "[ GREP ]".to_string();
+            return;
+        }
+
+        match Search::new(&self.query, SearchDirection::Forward, self.options) {
+            Ok(search) => {
+                let regex = search.regex().clone();
+
+                self.search = Some(GrepSearch::spawn(self.root.clone(), regex.clone(), self.waker.clone()));
+                self.regex = Some(regex);
+                self.title = "[ GREP: searching... ]".to_string();
+            },
+            Err(e) => {
+                self.regex = None;
+                self.title = format!("[ GREP: {} ]", e.to_string().lines().last().unwrap_or_default());
+            }
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.results.len().saturating_sub(1));
+
+        let height = self.list_height();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    fn result_line(&self, result: &GrepMatch, selected: bool) -> StyledContent {
+        let path = result.path.strip_prefix(&self.root).unwrap_or(&result.path);
+        let marker = if selected { "> " } else { "  " };
+        let location = format!("{}{}:{}: ", marker, path.display(), result.line + 1);
+        let text = result.text.trim_start();
+        let indent = result.text.len() - text.len();
+
+        let mut style = ContentStyle::default().with(Color::Blue);
+        if selected {
+            style = style.attribute(Attribute::Bold);
+        }
+
+        let mut line = StyledContent::new();
+        line.push(location, style);
+        line.push(text.to_string(), ContentStyle::default());
+
+        if let Some(regex) = &self.regex {
+            let offset = line.len() - text.len();
+            let highlight = ContentStyle::default()
+                .with(Color::Black)
+                .on(Color::DarkYellow);
+
+            for m in regex.find_iter(&result.text).filter(|m| m.start() >= indent) {
+                let start = offset + m.start() - indent;
+                line.style_range(start..start + m.len(), highlight);
+            }
+        }
+
+        line
+    }
+}
+
+impl Window<AppState> for Grep {
+    fn info(&self) -> WindowInfo {
+        self.info.selectable()
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        let mut query = StyledContent::new();
+        query.push("/ ".to_string(), ContentStyle::default().with(Color::Grey));
+        query.push(self.query.clone(), ContentStyle::default());
+
+        let results = self.results.iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.list_height())
+            .map(|(i, r)| self.result_line(r, self.focus == Focus::Results && i == self.selected));
+
+        std::iter::once(query).chain(results).collect()
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn handle_input(&mut self, _state: &AppState, bus: &mut Bus<Message>, code: KeyCode, _modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        self.generation += 1;
+
+        match self.focus {
+            Focus::Query => match code {
+                KeyCode::Char(c) => self.query.push(c),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                },
+                KeyCode::Enter => {
+                    self.start();
+                    self.focus = Focus::Results;
+                },
+                KeyCode::Esc => self.focus = Focus::Results,
+                _ => { }
+            },
+            Focus::Results => match code {
+                KeyCode::Char('j') | KeyCode::Down => self.select(self.selected + 1),
+                KeyCode::Char('k') | KeyCode::Up => self.select(self.selected.saturating_sub(1)),
+                KeyCode::Char('g') | KeyCode::Home => self.select(0),
+                KeyCode::Char('G') | KeyCode::End => self.select(usize::MAX),
+                KeyCode::Char('/') | KeyCode::Char('i') => self.focus = Focus::Query,
+                KeyCode::Enter => {
+                    if let Some(result) = self.results.get(self.selected) {
+                        bus.send(Message::Jump {
+                            path: result.path.clone(),
+                            position: (result.column, result.line),
+                        });
+                    }
+                },
+                _ => { }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn update_state(&mut self, new_state: &AppState) {
+        if !new_state.root.as_os_str().is_empty() && self.root != new_state.root {
+            self.root = new_state.root.clone();
+        }
+    }
+
+    fn tick(&mut self, _state: &AppState, _bus: &mut Bus<Message>) -> bool {
+        let Some(search) = &self.search else { return false };
+        let events = search.poll();
+
+        if events.is_empty() {
+            return false;
+        }
+
+        let mut done = false;
+
+        for event in events {
+            match event {
+                GrepEvent::Match(found) => {
+                    if self.results.len() < MAX_RESULTS {
+                        self.results.push(found);
+                    } else {
+                        search.cancel();
+                    }
+                },
+                GrepEvent::Done { files } => {
+                    let limited = if self.results.len() >= MAX_RESULTS { " (limited)" } else { "" };
+
+                    self.title = format!("[ GREP: {} matches in {} files{} ]", self.results.len(), files, limited);
+                    done = true;
+                }
+            }
+        }
+
+        if done {
+            self.search = None;
+        } else {
+            self.title = format!("[ GREP: {} matches, searching... ]", self.results.len());
+        }
+
+        self.generation += 1;
+        true
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+
+    fn set_waker(&mut self, waker: Waker) {
+        self.waker = Some(waker);
+    }
+
+    fn cursor_position(&self) -> Option<(u16, u16)> {
+        match self.focus {
+            Focus::Query => Some(((2 + self.query.chars().count()) as u16, 0)),
+            Focus::Results => Some((0, (self.selected.saturating_sub(self.scroll) + 1) as u16)),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::error::Error;
+
+use crossterm::{style::{ Attribute, ContentStyle, Color, Stylize }, event::{ KeyCode, KeyModifiers }};
+
+use crate::ui::{
+    bus::{ Bus, State },
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+
+/// Text wider than this is wrapped.
+const MAX_WIDTH: usize = 80;
+const MAX_HEIGHT: usize = 12;
+
+/// A popup by the cursor showing what a language server said about the
+/// code under it. j and k scroll; any other key closes it.
+#[derive(Debug)]
+pub struct Hover {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    lines: Vec<String>,
+    scroll: usize,
+    closed: bool,
+    generation: u64,
+}
+
+impl Hover {
+    pub fn new(text: &str) -> Self {
+        let lines: Vec<String> = text.lines().flat_map(|line| wrap(line, MAX_WIDTH)).collect();
+
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0).max(1);
+        let height = lines.len().clamp(1, MAX_HEIGHT);
+
+        // The border takes a column and a row
+        let info = WindowInfo::new()
+            .at_cursor()
+            .bounds(width as u16 + 1, height as u16 + 1)
+            .modal();
+
+        Self {
+            info,
+            bounds: None,
+            lines,
+            scroll: 0,
+            closed: false,
+            generation: 0,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.bounds.map_or(1, |b| b.height.saturating_sub(1).max(1) as usize)
+    }
+}
+
+/// `line` split into pieces of at most `width` chars, at spaces where it can be.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut rest: Vec<char> = line.chars().collect();
+
+    while rest.len() > width {
+        let split = rest[..=width].iter().rposition(|c| *c == ' ').filter(|i| *i > 0).unwrap_or(width);
+
+        lines.push(rest[..split].iter().collect());
+        rest.drain(..split);
+
+        if rest.first() == Some(&' ') {
+            rest.remove(0);
+        }
+    }
+
+    lines.push(rest.into_iter().collect());
+    lines
+}
+
+impl<S: State> Window<S> for Hover {
+    fn info(&self) -> WindowInfo {
+        self.info
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        self.lines.iter()
+            .skip(self.scroll)
+            .take(self.height())
+            .map(|line| StyledContent::from(line.clone()))
+            .collect()
+    }
+
+    fn title(&self) -> &str {
+        if self.lines.len() > self.height() { "[ j/k ]" } else { "" }
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+        self.generation += 1;
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn handle_input(&mut self, _state: &S, _bus: &mut Bus<S::Message>, code: KeyCode, _modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        let last = self.lines.len().saturating_sub(self.height());
+        self.generation += 1;
+
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => self.scroll = (self.scroll + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            _ => self.closed = true,
+        }
+
+        Ok(())
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+    fn should_close(&self) -> bool {
+        self.closed
+    }
+}
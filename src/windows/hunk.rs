@@ -0,0 +1,125 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use crossterm::{style::{ Attribute, ContentStyle, Color, Stylize }, event::{ KeyCode, KeyModifiers }};
+
+use crate::git::Hunk;
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+use crate::{ AppState, Message };
+
+const MAX_WIDTH: usize = 80;
+const MAX_HEIGHT: usize = 12;
+
+/// A popup by the cursor showing what a hunk changed since `HEAD`. r puts
+/// back what was there, j and k scroll, and any other key closes it.
+#[derive(Debug)]
+pub struct HunkPreview {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    path: PathBuf,
+    hunk: Hunk,
+    lines: Vec<StyledContent>,
+    scroll: usize,
+    closed: bool,
+    generation: u64,
+}
+
+impl HunkPreview {
+    /// `new` is what the working copy has in place of the hunk's old lines.
+    pub fn new(path: PathBuf, hunk: Hunk, new: &[String]) -> Self {
+        let removed = ContentStyle::default().with(Color::Red);
+        let added = ContentStyle::default().with(Color::Green);
+
+        let lines: Vec<StyledContent> = hunk.old.iter()
+            .map(|line| StyledContent::from_styled(format!("- {}", line), removed))
+            .chain(new.iter().map(|line| StyledContent::from_styled(format!("+ {}", line), added)))
+            .collect();
+
+        let width = hunk.old.iter().chain(new).map(|l| l.chars().count() + 2).max().unwrap_or(0);
+
+        // The border takes a column and a row
+        let info = WindowInfo::new()
+            .at_cursor()
+            .bounds(width.clamp(12, MAX_WIDTH) as u16 + 1, lines.len().clamp(1, MAX_HEIGHT) as u16 + 1)
+            .modal();
+
+        Self {
+            info,
+            bounds: None,
+            path,
+            hunk,
+            lines,
+            scroll: 0,
+            closed: false,
+            generation: 0,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.bounds.map_or(1, |b| b.height.saturating_sub(1).max(1) as usize)
+    }
+}
+
+impl Window<AppState> for HunkPreview {
+    fn info(&self) -> WindowInfo {
+        self.info
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        self.lines.iter()
+            .skip(self.scroll)
+            .take(self.height())
+            .cloned()
+            .collect()
+    }
+
+    fn title(&self) -> &str {
+        "[ (r)evert ]"
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+        self.generation += 1;
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn handle_input(&mut self, _state: &AppState, bus: &mut Bus<Message>, code: KeyCode, _modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        let last = self.lines.len().saturating_sub(self.height());
+        self.generation += 1;
+
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => self.scroll = (self.scroll + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Char('r') => {
+                bus.send(Message::RevertHunk { path: self.path.clone(), hunk: self.hunk.clone() });
+                self.closed = true;
+            },
+            _ => self.closed = true,
+        }
+
+        Ok(())
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+    fn should_close(&self) -> bool {
+        self.closed
+    }
+}
@@ -1,36 +1,82 @@
-use crossterm::style::{ ContentStyle, Color, Stylize };
+use std::collections::BTreeMap;
 
+use crossterm::style::{ Attribute, ContentStyle, Color, Stylize };
+
+use crate::diagnostics::Severity;
+use crate::git::HunkKind;
 use crate::ui::{
+    bus::Bus,
     rect::Rect,
     window::{ WindowInfo, Window, StyledContent }
 };
+use crate::{ AppState, Message };
 
+/// The line numbers of the selected file, each with a sign for the worst
+/// diagnostic starting on its line, and a mark for lines changed since
+/// `HEAD`.
 #[derive(Debug)]
 pub struct LineNumbers {
     info: WindowInfo,
-    bounds: Option<Rect>
+    bounds: Option<Rect>,
+    scroll_offset: usize,
+    line_count: usize,
+    signs: BTreeMap<usize, Severity>,
+    changes: BTreeMap<usize, HunkKind>,
+    generation: u64,
 }
 
 impl LineNumbers {
     pub fn new(info: WindowInfo) -> Self {
-        LineNumbers { info, bounds: None }
+        LineNumbers { info, bounds: None, scroll_offset: 0, line_count: 0, signs: BTreeMap::new(), changes: BTreeMap::new(), generation: 0 }
     }
 }
 
-impl<STATE> Window<STATE> for LineNumbers {
+fn sign(severity: Option<Severity>) -> (char, ContentStyle) {
+    let sign = match severity {
+        Some(Severity::Error) => 'E',
+        Some(Severity::Warning) => 'W',
+        Some(Severity::Info) => 'I',
+        Some(Severity::Hint) => 'H',
+        None => ' ',
+    };
+
+    let color = severity.map_or(Color::Grey, Severity::color);
+
+    (sign, ContentStyle::default().with(color).attribute(Attribute::Bold))
+}
+
+fn change_mark(kind: Option<HunkKind>) -> (char, ContentStyle) {
+    let (mark, color) = match kind {
+        Some(HunkKind::Added) => ('+', Color::Green),
+        Some(HunkKind::Modified) => ('~', Color::Blue),
+        Some(HunkKind::Deleted) => ('_', Color::Red),
+        None => (' ', Color::Grey),
+    };
+
+    (mark, ContentStyle::default().with(color))
+}
+
+impl Window<AppState> for LineNumbers {
     fn info(&self) -> WindowInfo {
         self.info
     }
 
     fn lines(&self) -> Vec<StyledContent> {
         let mut lines = Vec::new();
-        let height = self.bounds.map(|r| r.height).unwrap();
+        let height = self.bounds.map(|r| r.height).unwrap() as usize;
+        let last = (self.scroll_offset + height).min(self.line_count + 1);
+
+        for i in self.scroll_offset + 1..last {
+            let (sign, style) = sign(self.signs.get(&(i - 1)).copied());
+
+            let mut line = StyledContent::new();
+            line.push(sign.to_string(), style);
+            line.push(format!("{:3}", i), ContentStyle::default().with(Color::Grey));
 
-        for i in 1..height {
-            lines.push(StyledContent::from_styled(
-                format!("{:3}\n", i), 
-                ContentStyle::default().with(Color::Grey)
-            ));
+            let (mark, style) = change_mark(self.changes.get(&(i - 1)).copied());
+            line.push(mark.to_string(), style);
+
+            lines.push(line);
         }
 
         lines
@@ -42,4 +88,51 @@ impl<STATE> Window<STATE> for LineNumbers {
     fn get_bounds(&self) -> Rect {
         self.bounds.unwrap_or_default()
     }
+
+    fn update_state(&mut self, new_state: &AppState) {
+        let AppState { scroll_offset, file_status, open_files, selected_file, diagnostics, hunks, .. } = new_state;
+
+        let mut signs = BTreeMap::new();
+        let mut changes = BTreeMap::new();
+
+        if let Some(path) = open_files.get(*selected_file) {
+            for diagnostic in diagnostics.for_path(path) {
+                let severity = signs.entry(diagnostic.start.1).or_insert(diagnostic.severity);
+                *severity = diagnostic.severity.min(*severity);
+            }
+
+            for hunk in hunks.get(path).into_iter().flatten() {
+                if hunk.kind == HunkKind::Deleted {
+                    // Marked on the line above, or the first if there's none
+                    changes.insert(hunk.start.saturating_sub(1), hunk.kind);
+                } else {
+                    for line in hunk.start..hunk.start + hunk.len {
+                        changes.insert(line, hunk.kind);
+                    }
+                }
+            }
+        }
+
+        let unchanged = (self.scroll_offset, self.line_count, &self.signs, &self.changes)
+            == (*scroll_offset, file_status.line_count, &signs, &changes);
+
+        if !unchanged {
+            self.scroll_offset = *scroll_offset;
+            self.line_count = file_status.line_count;
+            self.signs = signs;
+            self.changes = changes;
+            self.generation += 1;
+        }
+    }
+
+    fn on_message(&mut self, state: &AppState, message: &Message, _bus: &mut Bus<Message>) {
+        if let Message::MoveCursor { .. } | Message::SetFileStatus(_) | Message::SetDiagnostics(_) | Message::ClearDiagnostics(_) | Message::SetHunks { .. } = message {
+            self.update_state(state);
+        }
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
 }
@@ -13,3 +13,30 @@ pub use tabs::*;
 mod empty;
 pub use empty::*;
 
+
+mod statusline;
+pub use statusline::*;
+
+mod commandline;
+pub use commandline::*;
+
+mod grep;
+pub use grep::*;
+
+mod finder;
+pub use finder::*;
+
+mod diagnostics;
+pub use diagnostics::*;
+
+mod hover;
+pub use hover::*;
+
+mod completion;
+pub use completion::*;
+
+mod hunk;
+pub use hunk::*;
+
+mod terminal;
+pub use terminal::*;
@@ -0,0 +1,239 @@
+use std::path::PathBuf;
+
+use crossterm::style::{ Attribute, ContentStyle, Color, Stylize };
+
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+use crate::jobs::JobStatus;
+use crate::{ AppState, FileStatus, Message, Mode };
+
+#[derive(Debug, Clone)]
+pub enum StatusSegment {
+    Mode,
+    /// The git branch the root is on
+    Branch,
+    FilePath,
+    Modified,
+    Position,
+    Percentage,
+    LineEnding,
+    Encoding,
+    /// Every running background job and its progress
+    Jobs,
+    Text(String),
+}
+
+#[derive(Debug)]
+pub struct StatusLine {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    left: Vec<StatusSegment>,
+    right: Vec<StatusSegment>,
+    filepath: Option<PathBuf>,
+    cursor_position: (u16, u16),
+    scroll_offset: usize,
+    mode: Mode,
+    file_status: FileStatus,
+    jobs: Vec<JobStatus>,
+    branch: Option<String>,
+    generation: u64,
+}
+
+impl StatusLine {
+    pub fn new(info: WindowInfo) -> Self {
+        Self {
+            info,
+            bounds: None,
+            left: vec![ StatusSegment::Mode, StatusSegment::Branch, StatusSegment::FilePath, StatusSegment::Modified ],
+            right: vec![
+                StatusSegment::Jobs,
+                StatusSegment::Encoding,
+                StatusSegment::LineEnding,
+                StatusSegment::Percentage,
+                StatusSegment::Position,
+            ],
+            filepath: None,
+            cursor_position: (0, 0),
+            scroll_offset: 0,
+            mode: Mode::default(),
+            file_status: FileStatus::default(),
+            jobs: Vec::new(),
+            branch: None,
+            generation: 0,
+        }
+    }
+
+    /// Segments drawn from the left edge, in order.
+    pub fn left(self, segments: Vec<StatusSegment>) -> Self {
+        Self { left: segments, ..self }
+    }
+
+    /// Segments drawn against the right edge, in order.
+    pub fn right(self, segments: Vec<StatusSegment>) -> Self {
+        Self { right: segments, ..self }
+    }
+
+    fn line(&self) -> usize {
+        self.scroll_offset + self.cursor_position.1 as usize + 1
+    }
+
+    fn segment(&self, segment: &StatusSegment) -> Option<(String, ContentStyle)> {
+        let plain = ContentStyle::default().with(Color::Grey);
+
+        let text = match segment {
+            StatusSegment::Mode => {
+                let style = ContentStyle::default()
+                    .with(Color::Black)
+                    .on(Color::Blue)
+                    .attribute(Attribute::Bold);
+
+                return Some((format!(" {} ", self.mode), style));
+            },
+            StatusSegment::Branch => {
+                let branch = self.branch.as_ref()?;
+                let style = ContentStyle::default().with(Color::Magenta);
+
+                return Some((branch.clone(), style));
+            },
+            StatusSegment::FilePath => {
+                let path = self.filepath.as_ref()?;
+                let style = ContentStyle::default().with(Color::Blue);
+
+                return Some((path.display().to_string(), style));
+            },
+            StatusSegment::Modified => {
+                if !self.file_status.modified {
+                    return None;
+                }
+
+                "[+]".to_string()
+            },
+            StatusSegment::Position => {
+                format!("{}:{}", self.line(), self.cursor_position.0 + 1)
+            },
+            StatusSegment::Percentage => {
+                let total = self.file_status.line_count.max(1);
+                format!("{}%", self.line().min(total) * 100 / total)
+            },
+            StatusSegment::LineEnding => self.file_status.line_ending.to_string(),
+            StatusSegment::Encoding => self.file_status.encoding.to_string(),
+            StatusSegment::Jobs => {
+                if self.jobs.is_empty() {
+                    return None;
+                }
+
+                let jobs: Vec<String> = self.jobs.iter().map(|j| j.to_string()).collect();
+                let style = ContentStyle::default().with(Color::Yellow);
+
+                return Some((jobs.join(", "), style));
+            },
+            StatusSegment::Text(text) => text.clone(),
+        };
+
+        Some((text, plain))
+    }
+
+    fn segments(&self, segments: &[StatusSegment]) -> Vec<(String, ContentStyle)> {
+        segments.iter()
+            .filter_map(|s| self.segment(s))
+            .collect()
+    }
+}
+
+impl Window<AppState> for StatusLine {
+    fn info(&self) -> WindowInfo {
+        self.info.borderless()
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        let width = self.bounds.map(|r| r.width).unwrap_or_default().saturating_sub(1) as usize;
+
+        let left = self.segments(&self.left);
+        let right = self.segments(&self.right);
+
+        let text_width = |segments: &[(String, ContentStyle)]| segments.iter()
+            .map(|(s, _)| s.chars().count() + 1)
+            .sum::<usize>();
+
+        // Every segment, including the padding, is followed by a space
+        let padding = width.saturating_sub(text_width(&left) + text_width(&right) + 1);
+
+        let mut line = StyledContent::new();
+        let mut used = 0;
+
+        let segments = left.into_iter()
+            .chain(std::iter::once((" ".repeat(padding), ContentStyle::default())))
+            .chain(right);
+
+        for (text, style) in segments {
+            let text: String = text.chars().take(width.saturating_sub(used)).collect();
+            used += text.chars().count();
+
+            line.push(text, style);
+
+            if used < width {
+                line.push(" ".to_string(), ContentStyle::default());
+                used += 1;
+            }
+        }
+
+        vec![ line ]
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn update_state(&mut self, new_state: &AppState) {
+        let AppState {
+            open_files, selected_file, cursor_position, scroll_offset, mode, file_status, jobs, git_status, ..
+        } = new_state;
+
+        let filepath = open_files.get(*selected_file);
+
+        let unchanged = self.filepath.as_ref() == filepath
+            && self.cursor_position == cursor_position.primary
+            && self.scroll_offset == *scroll_offset
+            && self.mode == *mode
+            && self.file_status == *file_status
+            && self.jobs == *jobs
+            && self.branch == git_status.branch;
+
+        if unchanged {
+            return;
+        }
+
+        self.filepath = filepath.cloned();
+        self.cursor_position = cursor_position.primary;
+        self.scroll_offset = *scroll_offset;
+        self.mode = *mode;
+        self.file_status = *file_status;
+        self.jobs = jobs.clone();
+        self.branch = git_status.branch.clone();
+        self.generation += 1;
+    }
+
+    fn on_message(&mut self, state: &AppState, message: &Message, _bus: &mut Bus<Message>) {
+        match message {
+            Message::OpenFile(_)
+            | Message::Jump { .. }
+            | Message::MoveCursor { .. }
+            | Message::SetMode(_)
+            | Message::SetFileStatus(_)
+            | Message::SetJobs(_)
+            | Message::SetGitStatus(_) => self.update_state(state),
+            _ => { }
+        }
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+}
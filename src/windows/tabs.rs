@@ -1,12 +1,14 @@
 use std::path::PathBuf;
 
-use crossterm::style::{ContentStyle, Stylize, Color};
+use crossterm::style::{ Attribute, ContentStyle, Stylize, Color };
 
 use crate::ui::{
+    bus::Bus,
     rect::Rect,
     window::{ WindowInfo, Window, StyledContent }
 };
-use crate::AppState;
+use crate::git::Status;
+use crate::{ AppState, Message };
 
 #[derive(Debug)]
 pub struct Tabs {
@@ -14,6 +16,9 @@ pub struct Tabs {
     bounds: Option<Rect>,
     open_files: Vec<PathBuf>,
     selected_file: usize,
+    /// What git thinks of each open file
+    statuses: Vec<Option<Status>>,
+    generation: u64,
 }
 
 impl Tabs {
@@ -22,7 +27,9 @@ impl Tabs {
             info,
             bounds: None,
             open_files: Vec::new(),
-            selected_file: 0
+            selected_file: 0,
+            statuses: Vec::new(),
+            generation: 0
         }
     }
 }
@@ -33,48 +40,28 @@ impl Window<AppState> for Tabs {
     }
 
     fn lines(&self) -> Vec<StyledContent> {
-        let before = {
-            let mut acc = String::new();
-
-            for i in 0..self.selected_file {
-                acc += &format!(
-                    "{} //", 
-                    self.open_files[i]
-                        .file_name().unwrap()
-                        .to_str().unwrap());
+        let mut line = StyledContent::new();
+
+        for (i, (path, status)) in self.open_files.iter().zip(&self.statuses).enumerate() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+            let mut style = ContentStyle::default();
+            if i == self.selected_file {
+                style = style.with(Color::Blue).attribute(Attribute::Bold);
             }
 
-            acc
-        };
-
-        let selected = format!(
-            " {}", 
-            self.open_files[self.selected_file]
-                .file_name().unwrap()
-                .to_str().unwrap()
-        );
-
-        let after = {
-            let mut acc = String::new();
-
-            for i in self.selected_file+1..self.open_files.len() {
-                acc += &format!(
-                    "{} //", 
-                    self.open_files[i]
-                        .file_name().unwrap()
-                        .to_str().unwrap());
+            if i > 0 {
+                line.push(" //".to_string(), ContentStyle::default().with(Color::DarkGrey));
             }
 
-            acc
-        };
+            line.push(format!(" {}", name), style);
 
-        debug!("{before}{selected} // {after}");
+            if let Some(status) = status {
+                line.push(format!(" {}", status.marker()), ContentStyle::default().with(status.color()));
+            }
+        }
 
-        vec![
-            // StyledContent::from(before),
-            StyledContent::from_styled(selected, ContentStyle::default().with(Color::Blue)),
-            StyledContent::from(after),
-        ]
+        vec![ line ]
     }
 
     fn set_bounds(&mut self, new_bounds: Rect) {
@@ -85,9 +72,25 @@ impl Window<AppState> for Tabs {
     }
 
     fn update_state(&mut self, new_state: &AppState) {
-        let AppState { open_files, selected_file, .. } = new_state;
+        let AppState { open_files, selected_file, git_status, .. } = new_state;
+        let statuses: Vec<Option<Status>> = open_files.iter().map(|p| git_status.get(p)).collect();
+
+        if self.open_files != *open_files || self.selected_file != *selected_file || self.statuses != statuses {
+            self.open_files = open_files.clone();
+            self.selected_file = *selected_file;
+            self.statuses = statuses;
+            self.generation += 1;
+        }
+    }
 
-        self.open_files = open_files.clone();
-        self.selected_file = *selected_file;
+    fn on_message(&mut self, state: &AppState, message: &Message, _bus: &mut Bus<Message>) {
+        if let Message::OpenFile(_) | Message::Jump { .. } | Message::SetGitStatus(_) = message {
+            self.update_state(state);
+        }
     }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
 }
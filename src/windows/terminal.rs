@@ -0,0 +1,287 @@
+use std::error::Error;
+use std::io::{ Read, Write };
+use std::sync::mpsc::{ self, Receiver, TryRecvError };
+use std::thread;
+
+use crossterm::{style::{ Attribute, ContentStyle, Color, Stylize }, event::{ KeyCode, KeyModifiers }};
+use portable_pty::{ native_pty_system, Child, CommandBuilder, MasterPty, PtySize };
+
+use crate::events::Waker;
+use crate::ui::{
+    bus::Bus,
+    rect::Rect,
+    window::{ WindowInfo, Window, StyledContent }
+};
+use crate::vt::Screen;
+use crate::{ AppState, Message };
+
+/// A shell running in a pseudo terminal, and the thread reading its output.
+struct Pty {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    output: Receiver<Vec<u8>>,
+}
+
+impl Pty {
+    fn spawn(shell: &str, (width, height): (usize, usize), waker: Option<Waker>) -> Result<Self, Box<dyn Error>> {
+        let size = PtySize { rows: height as u16, cols: width as u16, pixel_width: 0, pixel_height: 0 };
+        let pair = native_pty_system().openpty(size)?;
+
+        let mut command = CommandBuilder::new(shell);
+        command.cwd(".");
+        command.env("TERM", "xterm-256color");
+
+        let child = pair.slave.spawn_command(command)?;
+        let mut reader = pair.master.try_clone_reader()?;
+        let writer = pair.master.take_writer()?;
+        let (sender, output) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut buffer = [ 0; 4096 ];
+
+            // Stops once the shell and everything it started have exited
+            while let Ok(read @ 1..) = reader.read(&mut buffer) {
+                if sender.send(buffer[..read].to_vec()).is_err() {
+                    break;
+                }
+
+                if let Some(waker) = &waker {
+                    waker.wake();
+                }
+            }
+
+            drop(sender);
+
+            if let Some(waker) = &waker {
+                waker.wake();
+            }
+        });
+
+        Ok(Self { master: pair.master, writer, child, output })
+    }
+}
+
+/// Runs a shell, showing what it draws. Every key goes to the shell while
+/// the window has focus. Once the shell exits, Enter starts another.
+pub struct Terminal {
+    info: WindowInfo,
+    bounds: Option<Rect>,
+    shell: String,
+    screen: Screen,
+    pty: Option<Pty>,
+    waker: Option<Waker>,
+    /// Why the last shell stopped, or couldn't start
+    exited: Option<String>,
+    title: String,
+    generation: u64,
+}
+
+impl Terminal {
+    pub fn new(info: WindowInfo) -> Self {
+        Self {
+            info,
+            bounds: None,
+            shell: std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string()),
+            screen: Screen::new(80, 24),
+            pty: None,
+            waker: None,
+            exited: None,
+            title: "[ TERMINAL ]".to_string(),
+            generation: 0,
+        }
+    }
+
+    /// The program to run instead of `$SHELL`.
+    pub fn shell(self, shell: &str) -> Self {
+        Self { shell: shell.to_string(), ..self }
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.bounds.map_or((80, 24), |b| (
+            b.width.saturating_sub(1).max(1) as usize,
+            b.height.saturating_sub(1).max(1) as usize,
+        ))
+    }
+
+    fn start(&mut self) {
+        let size = self.size();
+
+        self.screen = Screen::new(size.0, size.1);
+        self.exited = None;
+
+        match Pty::spawn(&self.shell, size, self.waker.clone()) {
+            Ok(pty) => self.pty = Some(pty),
+            Err(e) => self.exited = Some(format!("Can't start {}: {}", self.shell, e)),
+        }
+
+        self.update_title();
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let Some(pty) = &mut self.pty else { return };
+
+        if let Err(e) = pty.writer.write_all(bytes).and_then(|_| pty.writer.flush()) {
+            warn!("terminal: {}", e);
+        }
+    }
+
+    fn update_title(&mut self) {
+        self.title = match (&self.exited, self.screen.title()) {
+            (Some(exited), _) => format!("[ TERMINAL: {} ]", exited),
+            (None, "") => "[ TERMINAL ]".to_string(),
+            (None, title) => format!("[ TERMINAL: {} ]", title),
+        };
+    }
+}
+
+impl Window<AppState> for Terminal {
+    fn info(&self) -> WindowInfo {
+        self.info.selectable()
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        self.screen.lines()
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn title_style(&self) -> Option<ContentStyle> {
+        Some(
+            ContentStyle::default()
+                .with(Color::Blue)
+                .attribute(Attribute::Bold)
+        )
+    }
+
+    fn set_bounds(&mut self, new_bounds: Rect) {
+        self.bounds = Some(new_bounds);
+
+        // Hidden windows aren't given bounds, so the shell starts the first
+        // time the window is shown
+        if self.pty.is_none() && self.exited.is_none() {
+            self.start();
+            return;
+        }
+
+        let (width, height) = self.size();
+
+        if self.screen.size() != (width, height) {
+            self.screen.resize(width, height);
+            self.generation += 1;
+
+            if let Some(pty) = &self.pty {
+                let size = PtySize { rows: height as u16, cols: width as u16, pixel_width: 0, pixel_height: 0 };
+
+                if let Err(e) = pty.master.resize(size) {
+                    warn!("terminal: can't resize: {}", e);
+                }
+            }
+        }
+    }
+    fn get_bounds(&self) -> Rect {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn handle_input(&mut self, _state: &AppState, _bus: &mut Bus<Message>, code: KeyCode, modifiers: KeyModifiers)
+    -> Result<(), Box<dyn Error>> {
+        if self.pty.is_none() {
+            if code == KeyCode::Enter {
+                self.start();
+                self.generation += 1;
+            }
+
+            return Ok(());
+        }
+
+        let bytes = self.screen.key_bytes(code, modifiers);
+        self.write(&bytes);
+
+        Ok(())
+    }
+
+    fn handle_paste(&mut self, _state: &AppState, _bus: &mut Bus<Message>, text: &str) -> Result<(), Box<dyn Error>> {
+        let bytes = self.screen.paste_bytes(text);
+        self.write(&bytes);
+
+        Ok(())
+    }
+
+    fn tick(&mut self, _state: &AppState, _bus: &mut Bus<Message>) -> bool {
+        let Some(pty) = &mut self.pty else { return false };
+
+        let mut changed = false;
+        let mut closed = false;
+
+        loop {
+            match pty.output.try_recv() {
+                Ok(bytes) => {
+                    self.screen.process(&bytes);
+                    changed = true;
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    closed = true;
+                    break;
+                },
+            }
+        }
+
+        let replies = self.screen.take_replies();
+        if !replies.is_empty() {
+            self.write(&replies);
+        }
+
+        if closed {
+            let mut pty = self.pty.take().unwrap();
+
+            let status = match pty.child.wait() {
+                Ok(status) if status.success() => "exited".to_string(),
+                Ok(status) => format!("exited with {}", status.exit_code()),
+                Err(e) => e.to_string(),
+            };
+
+            self.exited = Some(format!("{}, Enter restarts", status));
+            changed = true;
+        }
+
+        if changed {
+            self.update_title();
+            self.generation += 1;
+        }
+
+        changed
+    }
+
+    fn set_waker(&mut self, waker: Waker) {
+        self.waker = Some(waker);
+    }
+
+    fn generation(&self) -> Option<u64> {
+        Some(self.generation)
+    }
+
+    fn cursor_position(&self) -> Option<(u16, u16)> {
+        let (column, row) = self.screen.cursor()?;
+        Some((column as u16, row as u16))
+    }
+}
+
+impl std::fmt::Debug for Terminal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Terminal")
+            .field("shell", &self.shell)
+            .field("screen", &self.screen)
+            .field("running", &self.pty.is_some())
+            .field("exited", &self.exited)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
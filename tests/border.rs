@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+
+use gof_lib::ui::border::{ Border, BorderStyle };
+use gof_lib::ui::rect::Rect;
+
+fn rect(x: u16, y: u16, width: u16, height: u16) -> Rect {
+    Rect { x, y, width, height }
+}
+
+/// Four windows in a grid, with the top left one's border emphasized.
+fn split() -> Border {
+    let left = rect(0, 0, 4, 2);
+    let mut border = Border::new();
+
+    for bounds in [ left, rect(4, 0, 4, 2), rect(0, 2, 4, 2), rect(4, 2, 4, 2) ] {
+        border.append(bounds.border_points());
+    }
+
+    border.set_emphasis(left.border_points());
+    border
+}
+
+#[test]
+fn heavy_and_light_borders_meet_in_mixed_glyphs() {
+    let border = split();
+    let glyph = |x, y| border.glyph_at((x, y), BorderStyle::Light).unwrap();
+
+    // The heavy rect's own edges and corners
+    assert_eq!(glyph(0, 0), "┏");
+    assert_eq!(glyph(2, 0), "━");
+    assert_eq!(glyph(0, 1), "┃");
+
+    // Where it meets its neighbours, only the arms along it are heavy
+    assert_eq!(glyph(4, 0), "┱");
+    assert_eq!(glyph(4, 1), "┃");
+    assert_eq!(glyph(4, 2), "╃");
+    assert_eq!(glyph(0, 2), "┡");
+    assert_eq!(glyph(2, 2), "━");
+
+    // The others stay light
+    assert_eq!(glyph(6, 0), "─");
+    assert_eq!(glyph(8, 0), "┐");
+    assert_eq!(glyph(8, 2), "┤");
+    assert_eq!(glyph(4, 4), "┴");
+    assert_eq!(border.glyph_at((9, 0), BorderStyle::Light), None);
+}
+
+#[test]
+fn emphasis_only_applies_to_light_borders() {
+    let border = split();
+
+    assert_eq!(border.glyph_at((4, 0), BorderStyle::Double), Some("╦"));
+    assert_eq!(border.glyph_at((4, 2), BorderStyle::Heavy), Some("╋"));
+    assert_eq!(border.glyph_at((4, 2), BorderStyle::Ascii), Some("+"));
+}
+
+#[test]
+fn a_lone_cell_is_blank() {
+    let mut border = Border::new();
+    border.append(vec![ (3, 3) ]);
+
+    assert_eq!(border.glyph_at((3, 3), BorderStyle::Light), Some(" "));
+}
+
+#[test]
+fn border_points_are_listed_once() {
+    for (width, height) in [ (0, 0), (1, 0), (0, 1), (1, 1), (2, 1), (5, 3), (80, 24) ] {
+        let bounds = rect(2, 3, width, height);
+        let points = bounds.border_points();
+        let unique: HashSet<_> = points.iter().collect();
+
+        assert_eq!(unique.len(), points.len(), "{}", bounds);
+        // Every cell on the outline
+        assert_eq!(points.len(), match (width, height) {
+            (0, 0) => 1,
+            (0, h) | (h, 0) => h as usize + 1,
+            (w, h) => 2 * (w + h) as usize,
+        }, "{}", bounds);
+
+        for (x, y) in points {
+            assert!(x == 2 || x == 2 + width || y == 3 || y == 3 + height, "({}, {}) in {}", x, y, bounds);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::path::PathBuf;
+
+use crossterm::event::{ KeyCode, KeyModifiers };
+
+use gof_lib::ui::bus::{ Bus, State };
+use gof_lib::ui::rect::Rect;
+use gof_lib::ui::window::{ Window, WindowInfo };
+use gof_lib::windows::Buffer;
+use gof_lib::AppState;
+
+/// A buffer editing a file holding `text`, in a directory of its own for
+/// each test.
+fn open(name: &str, text: &str) -> (Buffer, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("gof-buffer-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(name);
+    std::fs::write(&path, text).unwrap();
+
+    let mut buffer = Buffer::new(WindowInfo::new(), path.display().to_string()).unwrap();
+    buffer.set_bounds(Rect { x: 0, y: 0, width: 80, height: 24 });
+
+    (buffer, path)
+}
+
+/// Types `keys` into `buffer`, `\n` being Enter and `\x1b` Esc.
+fn type_keys(buffer: &mut Buffer, keys: &str) {
+    let state = AppState::new();
+    let mut bus = Bus::new();
+
+    for c in keys.chars() {
+        let code = match c {
+            '\n' => KeyCode::Enter,
+            '\x1b' => KeyCode::Esc,
+            c => KeyCode::Char(c),
+        };
+
+        buffer.handle_input(&state, &mut bus, code, KeyModifiers::NONE).unwrap();
+    }
+}
+
+/// What `buffer` holds, by writing it out.
+fn saved(buffer: &mut Buffer, path: &PathBuf) -> String {
+    type_keys(buffer, ":w\n");
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn deletes_at_neighbouring_cursors_overlap() {
+    // Cursors on the first three lines, each deleting its line and the next
+    let (mut buffer, path) = open("dj.txt", "a\nb\nc\nd\ne\n");
+    type_keys(&mut buffer, "gjgjdj");
+
+    assert_eq!(saved(&mut buffer, &path), "e\n");
+
+    let (mut buffer, path) = open("dd.txt", "a\nb\nc\nd\n");
+    type_keys(&mut buffer, "jgjdd");
+
+    assert_eq!(saved(&mut buffer, &path), "a\nd\n");
+
+    // Both cursors are in the same paragraph, which goes once
+    let (mut buffer, path) = open("dap.txt", "one\ntwo\n\nthree\n");
+    type_keys(&mut buffer, "gjdap");
+
+    assert_eq!(saved(&mut buffer, &path), "three\n");
+}
+
+#[test]
+fn deletes_on_one_line_keep_the_other_cursors_in_place() {
+    let (mut buffer, path) = open("dw.txt", "one two\nthree four\nfive\n");
+
+    // A cursor on each line, deleting a word at each
+    type_keys(&mut buffer, "gjgjdw");
+    assert_eq!(saved(&mut buffer, &path), "two\nfour\n\n");
+
+    // The empty line has nothing to delete, and its cursor stays
+    type_keys(&mut buffer, "x");
+    assert_eq!(saved(&mut buffer, &path), "wo\nour\n\n");
+
+    type_keys(&mut buffer, "kx");
+    assert_eq!(saved(&mut buffer, &path), "o\nur\n\n");
+
+    let (mut buffer, path) = open("x.txt", "abc\nabc\n");
+    type_keys(&mut buffer, "lgj5x");
+
+    assert_eq!(saved(&mut buffer, &path), "a\na\n");
+}
+
+#[test]
+fn an_edit_at_every_cursor_is_one_undo_step() {
+    let text = "a\nb\nc\nd\ne\n";
+    let (mut buffer, path) = open("undo.txt", text);
+
+    type_keys(&mut buffer, "gjgjdj");
+    assert_eq!(saved(&mut buffer, &path), "e\n");
+
+    type_keys(&mut buffer, "u");
+    assert_eq!(saved(&mut buffer, &path), text);
+
+    // Nothing older to undo
+    type_keys(&mut buffer, "u");
+    assert_eq!(saved(&mut buffer, &path), text);
+
+    let state = AppState::new();
+    buffer.handle_input(&state, &mut Bus::new(), KeyCode::Char('r'), KeyModifiers::CONTROL).unwrap();
+
+    assert_eq!(saved(&mut buffer, &path), "e\n");
+}
+
+#[test]
+fn changing_types_in_place_of_the_text() {
+    let (mut buffer, path) = open("cw.txt", "one two\n    three four\n");
+    type_keys(&mut buffer, "cwsix\x1b");
+
+    assert_eq!(saved(&mut buffer, &path), "six two\n    three four\n");
+
+    // `cc` keeps the indent
+    type_keys(&mut buffer, "jccfive\x1b");
+    assert_eq!(saved(&mut buffer, &path), "six two\n    five\n");
+
+    // The change and everything typed after it are one step
+    type_keys(&mut buffer, "u");
+    assert_eq!(saved(&mut buffer, &path), "six two\n    three four\n");
+}
+
+#[test]
+fn typing_breaks_and_joins_lines() {
+    let (mut buffer, path) = open("breaks.txt", "  ab\n");
+
+    // Enter keeps the indent before the cursor, Backspace joins lines back
+    type_keys(&mut buffer, "A!\nx\x1b");
+    assert_eq!(saved(&mut buffer, &path), "  ab!\n  x\n");
+
+    let state = AppState::new();
+    let mut bus = Bus::new();
+
+    type_keys(&mut buffer, "a");
+
+    for _ in 0..4 {
+        buffer.handle_input(&state, &mut bus, KeyCode::Backspace, KeyModifiers::NONE).unwrap();
+    }
+
+    type_keys(&mut buffer, "\x1b");
+    assert_eq!(saved(&mut buffer, &path), "  ab!\n");
+}
+
+#[test]
+fn typing_at_every_cursor() {
+    let (mut buffer, path) = open("insert.txt", "ab\ncd\n");
+
+    // Enter keeps the indent before the cursor, Backspace joins lines back
+    type_keys(&mut buffer, "gjA!\n  x\x1b");
+    assert_eq!(saved(&mut buffer, &path), "ab!\n  x\ncd!\n  x\n");
+
+    let state = AppState::new();
+    let mut bus = Bus::new();
+
+    type_keys(&mut buffer, "a");
+
+    for _ in 0..4 {
+        buffer.handle_input(&state, &mut bus, KeyCode::Backspace, KeyModifiers::NONE).unwrap();
+    }
+
+    type_keys(&mut buffer, "\x1b");
+    assert_eq!(saved(&mut buffer, &path), "ab!\ncd!\n");
+}
+
+#[test]
+fn opening_lines_and_undoing_an_insert() {
+    let text = "  a\nb\n";
+    let (mut buffer, path) = open("o.txt", text);
+
+    type_keys(&mut buffer, "ox\x1bjOy\x1b");
+    assert_eq!(saved(&mut buffer, &path), "  a\n  x\ny\nb\n");
+
+    type_keys(&mut buffer, "uu");
+    assert_eq!(saved(&mut buffer, &path), text);
+}
+
+#[test]
+fn changing_a_selection() {
+    let (mut buffer, path) = open("vc.txt", "one\ntwo\nthree\n");
+    type_keys(&mut buffer, "Vjcnew\x1b");
+
+    assert_eq!(saved(&mut buffer, &path), "new\nthree\n");
+
+    // A block is typed over on each of its lines that reached into it
+    let text = "abc\nabc\na\n";
+    let (mut buffer, path) = open("block.txt", text);
+    let state = AppState::new();
+
+    type_keys(&mut buffer, "l");
+    buffer.handle_input(&state, &mut Bus::new(), KeyCode::Char('v'), KeyModifiers::CONTROL).unwrap();
+    type_keys(&mut buffer, "jjlcZ\x1b");
+
+    assert_eq!(saved(&mut buffer, &path), "aZ\naZ\na\n");
+
+    type_keys(&mut buffer, "u");
+    assert_eq!(saved(&mut buffer, &path), text);
+}
+
+#[test]
+fn switching_files_keeps_confirmed_substitutions() {
+    let text = "one one\n";
+    let (mut buffer, path) = open("confirm.txt", text);
+    let (_, other) = open("other.txt", "other\n");
+
+    // The first is replaced, and the prompt is still up for the second
+    type_keys(&mut buffer, ":s/one/two/gc\ny");
+    buffer.switch_to(&other).unwrap();
+    buffer.switch_to(&path).unwrap();
+
+    assert_eq!(saved(&mut buffer, &path), "two one\n");
+
+    type_keys(&mut buffer, "u");
+    assert_eq!(saved(&mut buffer, &path), text);
+}
+
+#[test]
+fn pasting_then_undoing() {
+    let text = "one two\nthree\n";
+    let (mut buffer, path) = open("paste.txt", text);
+    let mut state = AppState::new();
+    let mut bus = Bus::new();
+
+    let mut keys = |buffer: &mut Buffer, state: &mut AppState, keys: &str| {
+        for c in keys.chars() {
+            buffer.handle_input(state, &mut bus, KeyCode::Char(c), KeyModifiers::NONE).unwrap();
+        }
+
+        // Registers are set the way UI does it
+        for message in bus.take() {
+            state.apply(&message);
+        }
+    };
+
+    keys(&mut buffer, &mut state, "\"ayy");
+    keys(&mut buffer, &mut state, "j\"ap");
+    assert_eq!(saved(&mut buffer, &path), "one two\nthree\none two\n");
+
+    keys(&mut buffer, &mut state, "u");
+    assert_eq!(saved(&mut buffer, &path), text);
+
+    keys(&mut buffer, &mut state, "gg\"byw");
+    keys(&mut buffer, &mut state, "$\"bP");
+    assert_eq!(saved(&mut buffer, &path), "one twone o\nthree\n");
+
+    keys(&mut buffer, &mut state, "u");
+    assert_eq!(saved(&mut buffer, &path), text);
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+
+use crossterm::event::{ KeyCode, KeyModifiers };
+
+use gof_lib::ui::bus::{ Bus, State };
+use gof_lib::ui::rect::Rect;
+use gof_lib::ui::window::{ StyledContent, Window, WindowInfo };
+use gof_lib::ui::{ UI, MAX_MESSAGES };
+
+/// Every message applied, in order.
+#[derive(Default)]
+struct Log {
+    applied: Vec<u32>,
+}
+
+impl State for Log {
+    type Message = u32;
+
+    fn apply(&mut self, message: &u32) {
+        self.applied.push(*message);
+    }
+
+    fn show_message(text: String) -> u32 {
+        text.len() as u32
+    }
+}
+
+/// Sends 1, 2 and 3 on any key, fails to take pastes, and answers each
+/// message with `answer`.
+#[derive(Debug)]
+struct Echo {
+    answer: fn(u32) -> Option<u32>,
+}
+
+impl Window<Log> for Echo {
+    fn info(&self) -> WindowInfo {
+        WindowInfo::new()
+    }
+
+    fn lines(&self) -> Vec<StyledContent> {
+        Vec::new()
+    }
+
+    fn get_bounds(&self) -> Rect {
+        Rect { x: 0, y: 0, width: 0, height: 0 }
+    }
+
+    fn set_bounds(&mut self, _: Rect) { }
+
+    fn handle_input(&mut self, _: &Log, bus: &mut Bus<u32>, _: KeyCode, _: KeyModifiers) -> Result<(), Box<dyn Error>> {
+        for message in [ 1, 2, 3 ] {
+            bus.send(message);
+        }
+
+        Ok(())
+    }
+
+    fn handle_paste(&mut self, _: &Log, _: &mut Bus<u32>, _: &str) -> Result<(), Box<dyn Error>> {
+        Err("no pasting".into())
+    }
+
+    fn on_message(&mut self, _: &Log, message: &u32, bus: &mut Bus<u32>) {
+        if let Some(answer) = (self.answer)(*message) {
+            bus.send(answer);
+        }
+    }
+}
+
+fn ui(answer: fn(u32) -> Option<u32>) -> UI<Log> {
+    UI::new(vec![ Echo { answer }.boxed() ], Log::default())
+}
+
+#[test]
+fn messages_apply_in_order() {
+    let mut ui = ui(|m| (m < 10).then_some(m * 10));
+    ui.pass_input_to_selected(KeyCode::Enter, KeyModifiers::NONE);
+
+    // What was sent first, then the answers to it in the same order
+    assert_eq!(ui.state().applied, [ 1, 2, 3, 10, 20, 30 ]);
+}
+
+#[test]
+fn a_cascade_stops_at_the_cap() {
+    let mut ui = ui(|m| Some(m + 3));
+    ui.pass_input_to_selected(KeyCode::Enter, KeyModifiers::NONE);
+
+    let applied = &ui.state().applied;
+    assert_eq!(applied.len(), MAX_MESSAGES);
+    assert!(applied.iter().copied().eq(1..=MAX_MESSAGES as u32));
+
+    // The next message starts over
+    ui.send(0);
+    assert_eq!(ui.state().applied.len(), 2 * MAX_MESSAGES);
+}
+
+#[test]
+fn input_errors_are_shown() {
+    let mut ui = ui(|_| None);
+    ui.pass_paste_to_selected("text");
+
+    assert_eq!(ui.state().applied, [ "no pasting".len() as u32 ]);
+}
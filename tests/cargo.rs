@@ -0,0 +1,70 @@
+use std::path::{ Path, PathBuf };
+
+use serde_json::Value;
+
+use gof_lib::cargo;
+use gof_lib::diagnostics::{ Diagnostic, Severity };
+
+// Lines printed by `cargo check --message-format=json` in a workspace at
+// /tmp/ws, checking its member crates/app
+
+const ERROR: &str = r#"{"reason":"compiler-message","package_id":"path+file:///tmp/ws/crates/app#0.1.0","manifest_path":"/tmp/ws/crates/app/Cargo.toml","target":{"kind":["bin"],"crate_types":["bin"],"name":"app","src_path":"/tmp/ws/crates/app/src/main.rs","edition":"2021","doc":true,"doctest":false,"test":true},"message":{"rendered":"error[E0308]: mismatched types\n --> crates/app/src/main.rs:3:18\n  |\n3 |     let x: u32 = \"a\";\n  |            ---   ^^^ expected `u32`, found `&str`\n  |            |\n  |            expected due to this\n\n","$message_type":"diagnostic","children":[],"level":"error","message":"mismatched types","spans":[{"byte_end":62,"byte_start":59,"column_end":21,"column_start":18,"expansion":null,"file_name":"crates/app/src/main.rs","is_primary":true,"label":"expected `u32`, found `&str`","line_end":3,"line_start":3,"suggested_replacement":null,"suggestion_applicability":null,"text":[{"highlight_end":21,"highlight_start":18,"text":"    let x: u32 = \"a\";"}]},{"byte_end":56,"byte_start":53,"column_end":15,"column_start":12,"expansion":null,"file_name":"crates/app/src/main.rs","is_primary":false,"label":"expected due to this","line_end":3,"line_start":3,"suggested_replacement":null,"suggestion_applicability":null,"text":[{"highlight_end":15,"highlight_start":12,"text":"    let x: u32 = \"a\";"}]}],"code":{"code":"E0308","explanation":"Expected type did not match the received type.\n\nErroneous code examples:\n\n```compile_fail,E0308\nfn plus_one(x: i32) -> i32 {\n    x + 1\n}\n\nplus_one(\"Not a number\");\n//       ^^^^^^^^^^^^^^ expected `i32`, found `&str`\n\nif \"Not a bool\" {\n// ^^^^^^^^^^^^ expected `bool`, found `&str`\n}\n\nlet x: f32 = \"Not a float\";\n//     ---   ^^^^^^^^^^^^^ expected `f32`, found `&str`\n//     |\n//     expected due to this\n```\n\nThis error occurs when an expression was used in a place where the compiler\nexpected an expression of a different type. It can occur in several cases, the\nmost common being when calling a function and passing an argument which has a\ndifferent type than the matching type in the function declaration.\n"}}}"#;
+
+const WARNING: &str = r#"{"reason":"compiler-message","package_id":"path+file:///tmp/ws/crates/app#0.1.0","manifest_path":"/tmp/ws/crates/app/Cargo.toml","target":{"kind":["bin"],"crate_types":["bin"],"name":"app","src_path":"/tmp/ws/crates/app/src/main.rs","edition":"2021","doc":true,"doctest":false,"test":true},"message":{"rendered":"warning: unused variable: `unused`\n --> crates/app/src/main.rs:2:9\n  |\n2 |     let unused = util::one();\n  |         ^^^^^^ help: if this is intentional, prefix it with an underscore: `_unused`\n  |\n  = note: `#[warn(unused_variables)]` (part of `#[warn(unused)]`) on by default\n\n","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"`#[warn(unused_variables)]` (part of `#[warn(unused)]`) on by default","rendered":null,"spans":[]},{"children":[],"code":null,"level":"help","message":"if this is intentional, prefix it with an underscore","rendered":null,"spans":[{"byte_end":26,"byte_start":20,"column_end":15,"column_start":9,"expansion":null,"file_name":"crates/app/src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"_unused","suggestion_applicability":"MachineApplicable","text":[{"highlight_end":15,"highlight_start":9,"text":"    let unused = util::one();"}]}]}],"level":"warning","message":"unused variable: `unused`","spans":[{"byte_end":26,"byte_start":20,"column_end":15,"column_start":9,"expansion":null,"file_name":"crates/app/src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[{"highlight_end":15,"highlight_start":9,"text":"    let unused = util::one();"}]}],"code":{"code":"unused_variables","explanation":null}}}"#;
+
+// Passed `-W no_such_lint` on the command line, so nowhere in the source
+const NO_SPANS: &str = r#"{"reason":"compiler-message","package_id":"path+file:///tmp/ws/crates/util#0.1.0","manifest_path":"/tmp/ws/crates/util/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"util","src_path":"/tmp/ws/crates/util/src/lib.rs","edition":"2021","doc":true,"doctest":true,"test":true},"message":{"rendered":"warning[E0602]: unknown lint: `no_such_lint`\n  |\n  = note: requested on the command line with `-W no_such_lint`\n  = note: `#[warn(unknown_lints)]` on by default\n\n","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"requested on the command line with `-W no_such_lint`","rendered":null,"spans":[]},{"children":[],"code":null,"level":"note","message":"`#[warn(unknown_lints)]` on by default","rendered":null,"spans":[]}],"level":"warning","message":"unknown lint: `no_such_lint`","spans":[],"code":{"code":"E0602","explanation":"An unknown or invalid lint was used on the command line.\n\nErroneous code example:\n\n```sh\nrustc -D bogus rust_file.rs\n```\n\nMaybe you just misspelled the lint name or the lint doesn't exist anymore.\nEither way, try to update/remove it in order to fix the error.\n"}}}"#;
+
+// From a path dependency at /tmp/ext
+const OUTSIDE: &str = r#"{"reason":"compiler-message","package_id":"path+file:///tmp/ext#0.1.0","manifest_path":"/tmp/ext/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"ext","src_path":"/tmp/ext/src/lib.rs","edition":"2021","doc":true,"doctest":true,"test":true},"message":{"rendered":"warning: unused variable: `unused`\n --> /tmp/ext/src/lib.rs:1:27\n  |\n1 | pub fn two() -> u32 { let unused = 2; 2 }\n  |                           ^^^^^^ help: if this is intentional, prefix it with an underscore: `_unused`\n  |\n  = note: `#[warn(unused_variables)]` (part of `#[warn(unused)]`) on by default\n\n","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"`#[warn(unused_variables)]` (part of `#[warn(unused)]`) on by default","rendered":null,"spans":[]},{"children":[],"code":null,"level":"help","message":"if this is intentional, prefix it with an underscore","rendered":null,"spans":[{"byte_end":32,"byte_start":26,"column_end":33,"column_start":27,"expansion":null,"file_name":"/tmp/ext/src/lib.rs","is_primary":true,"label":null,"line_end":1,"line_start":1,"suggested_replacement":"_unused","suggestion_applicability":"MachineApplicable","text":[{"highlight_end":33,"highlight_start":27,"text":"pub fn two() -> u32 { let unused = 2; 2 }"}]}]}],"level":"warning","message":"unused variable: `unused`","spans":[{"byte_end":32,"byte_start":26,"column_end":33,"column_start":27,"expansion":null,"file_name":"/tmp/ext/src/lib.rs","is_primary":true,"label":null,"line_end":1,"line_start":1,"suggested_replacement":null,"suggestion_applicability":null,"text":[{"highlight_end":33,"highlight_start":27,"text":"pub fn two() -> u32 { let unused = 2; 2 }"}]}],"code":{"code":"unused_variables","explanation":null}}}"#;
+
+const ARTIFACT: &str = r#"{"reason":"compiler-artifact","package_id":"path+file:///tmp/ws/crates/util#0.1.0","manifest_path":"/tmp/ws/crates/util/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"util","src_path":"/tmp/ws/crates/util/src/lib.rs","edition":"2021","doc":true,"doctest":true,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":false},"features":[],"filenames":["/tmp/ws/target/debug/deps/libutil-5f1e75d0910377d7.rmeta"],"executable":null,"fresh":false}"#;
+
+fn parse(workspace: &str, line: &str) -> Option<(PathBuf, Diagnostic)> {
+    let line: Value = serde_json::from_str(line).unwrap();
+    cargo::parse_compiler_message(Path::new(workspace), &line)
+}
+
+#[test]
+fn an_error_at_its_primary_span() {
+    let (path, diagnostic) = parse("/tmp/ws", ERROR).unwrap();
+
+    assert_eq!(path, PathBuf::from("/tmp/ws/crates/app/src/main.rs"));
+    assert_eq!(diagnostic, Diagnostic {
+        start: (17, 2),
+        end: (20, 2),
+        severity: Severity::Error,
+        message: "mismatched types [E0308]".to_string(),
+        source: Some("rustc".to_string()),
+    });
+}
+
+#[test]
+fn a_warning() {
+    let (path, diagnostic) = parse("/tmp/ws", WARNING).unwrap();
+
+    assert_eq!(path, PathBuf::from("/tmp/ws/crates/app/src/main.rs"));
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!((diagnostic.start, diagnostic.end), ((8, 1), (14, 1)));
+    assert_eq!(diagnostic.message, "unused variable: `unused` [unused_variables]");
+}
+
+#[test]
+fn paths_outside_the_workspace_stay_absolute() {
+    let (path, _) = parse("/tmp/ws", OUTSIDE).unwrap();
+    assert_eq!(path, PathBuf::from("/tmp/ext/src/lib.rs"));
+}
+
+#[test]
+fn the_workspace_of_a_directory_inside_it() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    assert_eq!(cargo::workspace_root(&root.join("src/windows")).unwrap(), root);
+}
+
+#[test]
+fn nothing_to_show_for_the_rest() {
+    assert_eq!(parse("/tmp/ws", NO_SPANS), None);
+    assert_eq!(parse("/tmp/ws", ARTIFACT), None);
+    assert_eq!(parse("/tmp/ws", r#"{"reason":"build-finished","success":false}"#), None);
+}
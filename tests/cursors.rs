@@ -0,0 +1,76 @@
+use ropey::Rope;
+
+use gof_lib::cursors::{ self, CursorEdits };
+use gof_lib::history::Edit;
+use gof_lib::motion::Span;
+
+fn edit(char_idx: usize, removed: &str, inserted: &str) -> Edit {
+    Edit { char_idx, removed: removed.to_string(), inserted: inserted.to_string() }
+}
+
+#[test]
+fn positions_follow_the_edited_range() {
+    let edit = edit(4, "abc", "x");
+
+    assert_eq!(cursors::map_position(3, &edit), 3);
+    // Inside what was removed
+    assert_eq!(cursors::map_position(4, &edit), 4);
+    assert_eq!(cursors::map_position(6, &edit), 4);
+    // After it, moved by the difference
+    assert_eq!(cursors::map_position(7, &edit), 5);
+    assert_eq!(cursors::map_position(20, &edit), 18);
+
+    let insert = self::edit(4, "", "yy");
+    assert_eq!(cursors::map_position(4, &insert), 6);
+}
+
+#[test]
+fn overlapping_spans_are_merged() {
+    let content = Rope::from_str("a\nb\nc\nd\ne\n");
+    let spans = [
+        Span::Lines(2..=3),
+        Span::Lines(0..=1),
+        Span::Lines(1..=2),
+        Span::Lines(4..=4),
+    ];
+
+    assert_eq!(cursors::merge_spans(&content, &spans), [
+        (Span::Lines(0..=3), vec![ 1, 2, 0 ]),
+        (Span::Lines(4..=4), vec![ 3 ]),
+    ]);
+
+    // Touching isn't overlapping, and lines merged with chars become chars
+    let spans = [ Span::Chars(0..2), Span::Chars(2..3), Span::Lines(1..=1), Span::Chars(5..7) ];
+
+    assert_eq!(cursors::merge_spans(&content, &spans), [
+        (Span::Chars(0..2), vec![ 0 ]),
+        (Span::Chars(2..4), vec![ 1, 2 ]),
+        (Span::Chars(5..7), vec![ 3 ]),
+    ]);
+}
+
+#[test]
+fn cursors_are_visited_last_first_and_moved_by_each_edit() {
+    // "one two three", deleting the word at each cursor
+    let mut edits = CursorEdits::new(vec![ 4, 0, 8 ]);
+
+    assert_eq!(edits.next(), Some((2, 8)));
+    edits.edited(&[ edit(8, "three", "") ], 8);
+
+    assert_eq!(edits.next(), Some((0, 4)));
+    edits.edited(&[ edit(4, "two ", "") ], 4);
+
+    assert_eq!(edits.next(), Some((1, 0)));
+    edits.edited(&[ edit(0, "one ", "") ], 0);
+
+    assert_eq!(edits.next(), None);
+    assert_eq!(edits.positions(), [ 0, 0, 0 ]);
+
+    // An edit reaching over a cursor still to come leaves it at its start
+    let mut edits = CursorEdits::new(vec![ 2, 6 ]);
+
+    assert_eq!(edits.next(), Some((1, 6)));
+    edits.edited(&[ edit(1, "bcdefg", "") ], 1);
+
+    assert_eq!(edits.next(), Some((0, 1)));
+}
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use gof_lib::document::Document;
+use gof_lib::text::{ Encoding, LineEnding };
+
+/// A file holding `bytes`, in a directory of its own for each test.
+fn file(name: &str, bytes: &[u8]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gof-document-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(name);
+    std::fs::write(&path, bytes).unwrap();
+
+    path
+}
+
+#[test]
+fn unencodable_chars_leave_the_file_alone() {
+    let original = b"caf\xe9\nna\xefve\n";
+    let path = file("latin1.txt", original);
+    let mut document = Document::open(&path).unwrap();
+
+    assert_eq!(document.encoding, Encoding::Latin1);
+    assert_eq!(document.content, "café\nnaïve\n");
+
+    document.content.insert(0, "5 € ");
+    let e = document.save().unwrap_err();
+
+    assert!(e.to_string().contains("latin-1"), "{}", e);
+    assert_eq!(std::fs::read(&path).unwrap(), original);
+
+    // Still the same file once the char is gone
+    document.content.remove(0..4);
+    document.content.insert(0, "ÿ ");
+    document.save().unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"\xff caf\xe9\nna\xefve\n");
+}
+
+#[test]
+fn saving_keeps_encoding_and_line_endings() {
+    let utf16: Vec<u8> = b"\xff\xfe".iter().copied()
+        .chain("a\r\nb €\r\n".encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+
+    let files = [
+        ("plain.txt", "a\nb €\n".as_bytes().to_vec(), Encoding::Utf8, LineEnding::Lf),
+        ("bom.txt", b"\xef\xbb\xbfa\r\nb\r\n".to_vec(), Encoding::Utf8Bom, LineEnding::Crlf),
+        ("mixed.txt", b"a\r\nb\nc".to_vec(), Encoding::Utf8, LineEnding::Mixed),
+        ("utf16.txt", utf16, Encoding::Utf16Le, LineEnding::Crlf),
+    ];
+
+    for (name, bytes, encoding, line_ending) in files {
+        let path = file(name, &bytes);
+        let mut document = Document::open(&path).unwrap();
+
+        assert_eq!((document.encoding, document.line_ending), (encoding, line_ending), "{}", name);
+        assert_eq!(document.save().unwrap(), bytes.len());
+        assert_eq!(std::fs::read(&path).unwrap(), bytes, "{}", name);
+    }
+}
+
+#[test]
+fn new_lines_get_the_file_line_ending() {
+    let path = file("crlf.txt", b"a\r\nb\r\n");
+    let mut document = Document::open(&path).unwrap();
+
+    let end = document.content.len_chars();
+    document.content.insert(end, "c\nd\n");
+    document.save().unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"a\r\nb\r\nc\r\nd\r\n");
+}
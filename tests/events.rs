@@ -0,0 +1,81 @@
+use std::time::{ Duration, Instant };
+
+use gof_lib::application::Event;
+use gof_lib::events::{ TimerId, Timers };
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn timeouts_fire_once() {
+    let start = Instant::now();
+    let mut timers = Timers::new();
+
+    let late = timers.set_deadline(start + ms(20));
+    let early = timers.set_deadline(start + ms(10));
+
+    assert_eq!(timers.next_deadline(), Some(start + ms(10)));
+    assert_eq!(timers.expired(start + ms(9)), Vec::<TimerId>::new());
+    assert_eq!(timers.expired(start + ms(10)), [ early ]);
+    assert_eq!(timers.expired(start + ms(15)), Vec::<TimerId>::new());
+
+    // Both at once, if the loop slept through one
+    let other = timers.set_deadline(start + ms(18));
+    assert_eq!(timers.expired(start + ms(30)), [ late, other ]);
+    assert_eq!(timers.next_deadline(), None);
+}
+
+#[test]
+fn intervals_are_rescheduled() {
+    let start = Instant::now();
+    let mut timers = Timers::new();
+    let id = timers.set_interval_from(start, ms(10));
+
+    assert_eq!(timers.expired(start + ms(5)), Vec::<TimerId>::new());
+    assert_eq!(timers.expired(start + ms(10)), [ id ]);
+    assert_eq!(timers.next_deadline(), Some(start + ms(20)));
+
+    // Missed ticks fire once, and the next is still on the beat
+    assert_eq!(timers.expired(start + ms(45)), [ id ]);
+    assert_eq!(timers.next_deadline(), Some(start + ms(50)));
+
+    // Too short a period would spin
+    let fast = timers.set_interval_from(start, Duration::ZERO);
+    assert_eq!(timers.expired(start + ms(1)), [ fast ]);
+}
+
+#[test]
+fn cancelled_timers_never_fire() {
+    let start = Instant::now();
+    let mut timers = Timers::new();
+
+    let timeout = timers.set_deadline(start + ms(10));
+    let interval = timers.set_interval_from(start, ms(10));
+    let kept = timers.set_deadline(start + ms(30));
+
+    timers.cancel(timeout);
+    assert_eq!(timers.expired(start + ms(10)), [ interval ]);
+
+    timers.cancel(interval);
+    assert_eq!(timers.next_deadline(), Some(start + ms(30)));
+    assert_eq!(timers.expired(start + ms(100)), [ kept ]);
+
+    // Cancelling twice, or after firing, does nothing
+    timers.cancel(interval);
+    timers.cancel(kept);
+}
+
+#[test]
+fn the_strongest_event_wins() {
+    let events = [ Event::Sleep, Event::Draw, Event::RecalculateUI, Event::Exit ];
+
+    for (i, a) in events.iter().enumerate() {
+        for (j, b) in events.iter().enumerate() {
+            assert_eq!(a.max(*b), events[i.max(j)], "{:?} and {:?}", a, b);
+        }
+    }
+
+    let merged = [ Event::Draw, Event::Sleep, Event::RecalculateUI, Event::Draw ].into_iter().fold(Event::Sleep, Event::max);
+    assert_eq!(merged, Event::RecalculateUI);
+}
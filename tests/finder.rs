@@ -0,0 +1,67 @@
+use gof_lib::finder::fuzzy_match;
+
+fn score(pattern: &str, candidate: &str) -> i64 {
+    fuzzy_match(pattern, candidate).unwrap_or_else(|| panic!("{} doesn't match {}", pattern, candidate)).score
+}
+
+fn indices(pattern: &str, candidate: &str) -> Vec<usize> {
+    fuzzy_match(pattern, candidate).unwrap().indices
+}
+
+/// `candidates` from the best match for `pattern` to the worst.
+fn ranked<'a>(pattern: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+    let mut ranked = candidates.to_vec();
+    ranked.sort_by_key(|candidate| std::cmp::Reverse(score(pattern, candidate)));
+    ranked
+}
+
+#[test]
+fn file_names_rank_above_directories() {
+    assert_eq!(ranked("mod", &[ "src/model/x.rs", "src/mod.rs" ]), [ "src/mod.rs", "src/model/x.rs" ]);
+    assert_eq!(ranked("main", &[ "main/lib.rs", "src/main.rs" ]), [ "src/main.rs", "main/lib.rs" ]);
+}
+
+#[test]
+fn segment_and_word_starts_rank_higher() {
+    assert_eq!(ranked("fb", &[ "xfoobar", "foo/bar" ]), [ "foo/bar", "xfoobar" ]);
+    assert_eq!(ranked("fb", &[ "foobar", "foo_bar" ]), [ "foo_bar", "foobar" ]);
+    assert_eq!(ranked("fb", &[ "foobar", "fooBar" ]), [ "fooBar", "foobar" ]);
+    assert_eq!(ranked("sr", &[ "users.rs", "src/x.rs" ]), [ "src/x.rs", "users.rs" ]);
+}
+
+#[test]
+fn consecutive_matches_rank_higher() {
+    assert_eq!(ranked("abc", &[ "a_b_c.rs", "abc.rs" ]), [ "abc.rs", "a_b_c.rs" ]);
+    assert_eq!(ranked("ui", &[ "src/u/i.rs", "src/ui.rs" ]), [ "src/ui.rs", "src/u/i.rs" ]);
+}
+
+#[test]
+fn shorter_gaps_rank_higher() {
+    assert!(score("ab", "axb") > score("ab", "axxxxb"));
+}
+
+#[test]
+fn smart_case() {
+    assert!(fuzzy_match("foo", "FOO.rs").is_some());
+    assert!(fuzzy_match("Foo", "foo.rs").is_none());
+    assert!(fuzzy_match("Foo", "Foo.rs").is_some());
+    assert!(fuzzy_match("ÉT", "été").is_none());
+    assert!(fuzzy_match("ét", "ÉTÉ").is_some());
+}
+
+#[test]
+fn indices_are_byte_offsets_of_the_best_placement() {
+    assert_eq!(indices("mr", "src/main.rs"), [ 4, 9 ]);
+    // The `m` of `main` is a better start than the `m` in `mum`
+    assert_eq!(indices("main", "mum/main"), [ 4, 5, 6, 7 ]);
+    assert_eq!(indices("éb", "café/bar"), [ 3, 6 ]);
+    assert_eq!(indices("🦀s", "🦀/s"), [ 0, 5 ]);
+}
+
+#[test]
+fn whitespace_and_empty_patterns() {
+    assert_eq!(fuzzy_match("ma in", "src/main.rs"), fuzzy_match("main", "src/main.rs"));
+    assert_eq!(fuzzy_match(" ", "anything").map(|m| m.score), Some(0));
+    assert!(fuzzy_match("xyz", "src/main.rs").is_none());
+    assert!(fuzzy_match("ba", "ab").is_none());
+}
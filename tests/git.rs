@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use gof_lib::git::{ self, RepoStatus, Status };
+
+/// The status `git status --porcelain=v1 -z --branch` printing `entries`
+/// would give, as `(path, status)` pairs in path order.
+fn parse(entries: &[&str]) -> (Option<String>, Vec<(String, Status)>) {
+    let output: Vec<u8> = entries.iter().flat_map(|e| e.bytes().chain([ 0 ])).collect();
+    let RepoStatus { branch, files, .. } = git::parse_status(PathBuf::from("/repo"), &output);
+    let files = files.into_iter().map(|(path, status)| (path.display().to_string(), status)).collect();
+
+    (branch, files)
+}
+
+#[test]
+fn renames_take_two_fields() {
+    // As printed after `git mv old.txt new.txt`
+    let (_, files) = parse(&[ "## main", " M kept.txt", "R  new.txt", "old.txt", " M other.txt" ]);
+
+    assert_eq!(files, [
+        ("kept.txt".to_string(), Status::Modified),
+        ("new.txt".to_string(), Status::Staged),
+        ("other.txt".to_string(), Status::Modified),
+    ]);
+
+    // Renamed, then changed again
+    let (_, files) = parse(&[ "RM new.txt", "old.txt" ]);
+    assert_eq!(files, [ ("new.txt".to_string(), Status::Modified) ]);
+}
+
+#[test]
+fn untracked_and_ignored() {
+    let (_, files) = parse(&[ "## main", "?? untracked.txt", "?? new/", "!! target/" ]);
+
+    assert_eq!(files, [
+        ("new".to_string(), Status::Untracked),
+        ("target".to_string(), Status::Ignored),
+        ("untracked.txt".to_string(), Status::Untracked),
+    ]);
+}
+
+#[test]
+fn branch_headers() {
+    let branch = |header: &str| parse(&[ header, " M a.txt" ]).0;
+
+    assert_eq!(branch("## main"), Some("main".to_string()));
+    assert_eq!(branch("## main...origin/main"), Some("main".to_string()));
+    assert_eq!(branch("## feature/x...origin/feature/x [ahead 1, behind 2]"), Some("feature/x".to_string()));
+    assert_eq!(branch("## No commits yet on main"), Some("main".to_string()));
+}
+
+#[test]
+fn detached_head_has_no_branch() {
+    let (branch, files) = parse(&[ "## HEAD (no branch)", " M kept.txt" ]);
+
+    assert_eq!(branch, None);
+    assert_eq!(files, [ ("kept.txt".to_string(), Status::Modified) ]);
+}
@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use regex::Regex;
+
+use gof_lib::grep::{ GrepEvent, GrepMatch, GrepSearch };
+
+/// A directory of its own for each test, holding `files`.
+fn tree(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gof-grep-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    for (path, content) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    dir
+}
+
+/// Everything found for `pattern` under `root`, in path then line order,
+/// and how many files were searched.
+fn grep(root: PathBuf, pattern: &str) -> (Vec<GrepMatch>, usize) {
+    let search = GrepSearch::spawn(root, Regex::new(pattern).unwrap(), None);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut found = Vec::new();
+
+    while Instant::now() < deadline {
+        for event in search.poll() {
+            match event {
+                GrepEvent::Match(m) => found.push(m),
+                GrepEvent::Done { files } => {
+                    found.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+                    return (found, files);
+                },
+            }
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    panic!("Timed out waiting for the search");
+}
+
+#[test]
+fn finds_the_first_match_on_each_line() {
+    let root = tree("lines", &[
+        ("a.txt", b"one two\nnothing\ntwo two\n"),
+        ("sub/b.txt", b"two\r\n"),
+    ]);
+
+    let (found, files) = grep(root.clone(), "two");
+    let found: Vec<_> = found.iter().map(|m| (m.path.strip_prefix(&root).unwrap().to_owned(), m.line, m.column, m.text.as_str())).collect();
+
+    assert_eq!(files, 2);
+    assert_eq!(found, [
+        (PathBuf::from("a.txt"), 0, 4, "one two"),
+        (PathBuf::from("a.txt"), 2, 0, "two two"),
+        // Without the line break
+        (PathBuf::from("sub/b.txt"), 0, 0, "two"),
+    ]);
+}
+
+#[test]
+fn columns_are_in_chars() {
+    let root = tree("columns", &[ ("a.txt", "héllo wörld 🦀 crab\n".as_bytes()) ]);
+
+    let (found, _) = grep(root.clone(), "wörld");
+    assert_eq!(found[0].column, 6);
+
+    let (found, _) = grep(root, "crab");
+    assert_eq!(found[0].column, 14);
+}
+
+#[test]
+fn binary_files_are_skipped() {
+    let mut binary = b"match\0".to_vec();
+    binary.extend(b"match\n".repeat(10));
+
+    // A NUL past the bytes checked doesn't make a file binary
+    let mut late = b"match\n".to_vec();
+    late.extend(vec![ b'x'; 10_000 ]);
+    late.push(0);
+
+    let root = tree("binary", &[
+        ("binary.bin", &binary),
+        ("late.txt", &late),
+        ("text.txt", b"match\n"),
+    ]);
+
+    let (found, files) = grep(root.clone(), "match");
+    let paths: Vec<_> = found.iter().map(|m| m.path.strip_prefix(&root).unwrap().to_owned()).collect();
+
+    assert_eq!(files, 3);
+    assert_eq!(paths, [ PathBuf::from("late.txt"), PathBuf::from("text.txt") ]);
+}
+
+#[test]
+fn invalid_utf8_is_still_searched() {
+    let root = tree("lossy", &[ ("a.txt", b"\xff\xfe match\n") ]);
+
+    let (found, _) = grep(root, "match");
+    assert_eq!(found[0].column, 3);
+}
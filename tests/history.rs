@@ -0,0 +1,31 @@
+use ropey::Rope;
+
+use gof_lib::history::{ History, Transaction };
+
+#[test]
+fn joined_changes_are_undone_together() {
+    let mut text = Rope::from_str("a");
+    let mut history = History::new();
+
+    for (at, inserted) in [ (1, "b"), (2, "c") ] {
+        let mut transaction = Transaction::new((at, 0));
+        transaction.insert(&mut text, at, inserted);
+
+        match at {
+            1 => history.commit(transaction),
+            _ => history.join(transaction),
+        }
+    }
+
+    history.mark_saved();
+    let saved = history.revision();
+
+    let mut transaction = Transaction::new((3, 0));
+    transaction.insert(&mut text, 3, "d");
+    history.join(transaction);
+
+    // Joining to what was saved makes it a change again
+    assert!(history.is_modified() && history.revision() > saved);
+    assert_eq!(history.undo(&mut text), Some((1, 0)));
+    assert_eq!(text, "a");
+}
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use ropey::Rope;
+
+use gof_lib::git::{ self, HunkKind };
+use gof_lib::history::{ History, Transaction };
+use gof_lib::ui::bus::State;
+use gof_lib::{ AppState, Message };
+
+#[test]
+fn hunks_of_a_change() {
+    let hunks = git::hunks(&[ "a", "b", "c", "d" ], &[ "a", "B", "c", "new", "d" ]);
+    let found: Vec<_> = hunks.iter().map(|h| (h.kind, h.start, h.len)).collect();
+
+    assert_eq!(found, [ (HunkKind::Modified, 1, 1), (HunkKind::Added, 3, 1) ]);
+    assert_eq!(hunks[0].old, [ "b" ]);
+
+    let hunks = git::hunks(&[ "a", "b", "c" ], &[ "a", "c" ]);
+    assert_eq!((hunks[0].kind, hunks[0].start, hunks[0].len), (HunkKind::Deleted, 1, 0));
+    assert!(hunks[0].on_line(0) && hunks[0].on_line(1) && !hunks[0].on_line(2));
+}
+
+#[test]
+fn older_diffs_are_dropped() {
+    let path = PathBuf::from("src/main.rs");
+    let hunks = |old: &[&str]| git::hunks(old, &[ "a" ]);
+    let mut state = AppState::new();
+
+    state.apply(&Message::SetHunks { path: path.clone(), revision: 5, hunks: hunks(&[ "b" ]) });
+    // Finishing late
+    state.apply(&Message::SetHunks { path: path.clone(), revision: 4, hunks: hunks(&[]) });
+
+    assert_eq!(state.hunks[&path], hunks(&[ "b" ]));
+
+    state.apply(&Message::SetHunks { path: path.clone(), revision: 6, hunks: hunks(&[ "a" ]) });
+    assert!(state.hunks[&path].is_empty());
+}
+
+#[test]
+fn revisions_are_never_reused() {
+    let mut text = Rope::from_str("a");
+    let mut history = History::new();
+
+    let mut transaction = Transaction::new((0, 0));
+    transaction.replace(&mut text, 0..1, "b");
+    history.commit(transaction);
+
+    let edited = history.revision();
+    history.undo(&mut text);
+
+    assert!(history.revision() > edited);
+
+    // A file opened again, or reloaded, doesn't start over
+    let reopened = History::new();
+    assert!(reopened.revision() > history.revision());
+}
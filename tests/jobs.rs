@@ -0,0 +1,99 @@
+use std::sync::mpsc;
+use std::time::{ Duration, Instant };
+
+use gof_lib::jobs::{ JobRequest, JobStatus, Jobs };
+use gof_lib::ui::bus::{ Bus, State };
+
+/// State whose messages are plain text, recording nothing.
+struct Log;
+
+impl State for Log {
+    type Message = String;
+
+    fn apply(&mut self, _: &String) { }
+
+    fn show_message(text: String) -> String {
+        text
+    }
+}
+
+/// Drains `jobs` into `messages` until `done` says so, failing the test if
+/// that takes too long.
+fn drain_until(jobs: &mut Jobs<Log>, messages: &mut Vec<String>, done: impl Fn(&Jobs<Log>, &[String]) -> bool) {
+    let start = Instant::now();
+    let mut bus = Bus::new();
+
+    while !done(jobs, messages) {
+        assert!(start.elapsed() < Duration::from_secs(5), "still waiting, with {:?}", messages);
+
+        jobs.drain(&mut bus);
+        messages.extend(bus.take());
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn a_job_that_finishes() {
+    let mut jobs = Jobs::<Log>::new();
+    let mut messages = Vec::new();
+
+    jobs.on_change(|statuses| format!("{} running", statuses.len()));
+    let id = jobs.spawn("count", |context| context.send("counted".to_string()));
+
+    drain_until(&mut jobs, &mut messages, |jobs, _| !jobs.is_running(id));
+
+    assert!(jobs.is_empty());
+    assert!(messages.contains(&"counted".to_string()), "{:?}", messages);
+    assert_eq!(messages.last().map(String::as_str), Some("0 running"));
+}
+
+#[test]
+fn a_job_cancelled_through_its_handle() {
+    let mut jobs = Jobs::<Log>::new();
+    let mut messages = Vec::new();
+
+    let request = JobRequest::new("wait", |context| {
+        while !context.is_cancelled() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        context.send("stopped".to_string());
+    });
+
+    let handle = request.handle();
+    let id = jobs.spawn_request(request);
+
+    assert!(jobs.is_running(id) && !handle.is_cancelled());
+
+    handle.cancel();
+    drain_until(&mut jobs, &mut messages, |jobs, _| !jobs.is_running(id));
+
+    assert_eq!(messages, [ "stopped" ]);
+}
+
+#[test]
+fn a_job_reporting_progress() {
+    let mut jobs = Jobs::<Log>::new();
+    let mut messages = Vec::new();
+    let (finish, finished) = mpsc::channel::<()>();
+
+    jobs.on_change(|statuses| statuses.iter().map(JobStatus::to_string).collect::<Vec<_>>().join(", "));
+
+    let id = jobs.spawn("read", move |context| {
+        context.progress(1, Some(4));
+        context.message("a.txt");
+        let _ = finished.recv();
+    });
+
+    drain_until(&mut jobs, &mut messages, |jobs, _| jobs.statuses().first().is_some_and(|s| !s.message.is_empty()));
+
+    let status = &jobs.statuses()[0];
+    assert_eq!((status.id, status.done, status.total), (id, 1, Some(4)));
+    assert_eq!(messages.last().map(String::as_str), Some("read 25% (a.txt)"));
+
+    finish.send(()).unwrap();
+    drain_until(&mut jobs, &mut messages, |jobs, _| jobs.is_empty());
+
+    // Nothing left running
+    assert_eq!(messages.last().map(String::as_str), Some(""));
+}
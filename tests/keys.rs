@@ -0,0 +1,117 @@
+use crossterm::event::{ KeyCode, KeyModifiers };
+
+use gof_lib::keys::{ Action, Command, InsertAt, Keys, Target, VisualAction };
+use gof_lib::motion::{ Motion, Operator, TextObject };
+use gof_lib::search::SearchDirection;
+use gof_lib::selection::SelectionKind;
+
+/// The commands `typed` finishes, one key at a time.
+fn commands(typed: &str, visual: bool) -> Vec<Command> {
+    let mut keys = Keys::new();
+
+    typed.chars()
+        .filter_map(|c| {
+            let code = match c {
+                '\x1b' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+
+            keys.handle(code, KeyModifiers::NONE, visual)
+        })
+        .collect()
+}
+
+fn command(action: Action, count: Option<usize>, register: Option<char>) -> Command {
+    Command { action, count, register }
+}
+
+#[test]
+fn counts_multiply_across_operators() {
+    let delete = |target| Action::Operate(Operator::Delete, target);
+
+    assert_eq!(commands("2d3w", false), [ command(delete(Target::Motion(Motion::WordForward)), Some(6), None) ]);
+    assert_eq!(commands("d3w", false), [ command(delete(Target::Motion(Motion::WordForward)), Some(3), None) ]);
+    assert_eq!(commands("12dd", false), [ command(delete(Target::Lines), Some(12), None) ]);
+
+    // `0` is a motion until a count is started
+    assert_eq!(commands("0", false), [ command(Action::Motion(Motion::LineStart), None, None) ]);
+    assert_eq!(commands("10j", false), [ command(Action::Motion(Motion::Down), Some(10), None) ]);
+}
+
+#[test]
+fn registers_and_prefixes() {
+    assert_eq!(commands("\"add", false), [ command(Action::Operate(Operator::Delete, Target::Lines), None, Some('a')) ]);
+    assert_eq!(commands("\"b2p", false), [ command(Action::Put { after: true }, Some(2), Some('b')) ]);
+    assert_eq!(commands("gg", false), [ command(Action::Motion(Motion::FirstLine), None, None) ]);
+    assert_eq!(commands("yfx", false), [ command(Action::Operate(Operator::Yank, Target::Motion(Motion::FindForward('x'))), None, None) ]);
+    assert_eq!(commands("]c[c", false), [
+        command(Action::JumpToHunk { forward: true }, None, None),
+        command(Action::JumpToHunk { forward: false }, None, None),
+    ]);
+    assert_eq!(commands("gjgn", false), [
+        command(Action::AddCursorBeside { below: true }, None, None),
+        command(Action::AddCursorAtNextMatch, None, None),
+    ]);
+}
+
+#[test]
+fn text_objects_and_inserting() {
+    let inner_word = TextObject::Word { around: false };
+
+    assert_eq!(commands("ciw", false), [ command(Action::Operate(Operator::Change, Target::Object(inner_word)), None, None) ]);
+    assert_eq!(commands("iw", true), [ command(Action::SelectObject(inner_word), None, None) ]);
+
+    // Without an operator or a selection, `i` starts inserting
+    assert_eq!(commands("iw", false), [
+        command(Action::Insert(InsertAt::Cursor), None, None),
+        command(Action::Motion(Motion::WordForward), None, None),
+    ]);
+    assert_eq!(commands("o", false), [ command(Action::Insert(InsertAt::LineBelow), None, None) ]);
+    assert_eq!(commands("o", true), [ command(Action::Visual(VisualAction::SwapEnds), None, None) ]);
+}
+
+#[test]
+fn nonsense_drops_what_was_typed() {
+    let mut keys = Keys::new();
+
+    for c in "3\"a".chars() {
+        assert_eq!(keys.handle(KeyCode::Char(c), KeyModifiers::NONE, false), None);
+    }
+
+    assert!(keys.is_pending());
+
+    // An unknown register, a different operator, or an unknown key after `g`
+    assert_eq!(commands("\"!x", false), [ command(Action::DeleteChars, None, None) ]);
+    assert_eq!(commands("dyw", false), [ command(Action::Motion(Motion::WordForward), None, None) ]);
+    assert_eq!(commands("2gqj", false), [ command(Action::Motion(Motion::Down), None, None) ]);
+
+    let mut keys = Keys::new();
+    keys.handle(KeyCode::Char('d'), KeyModifiers::NONE, false);
+    keys.handle(KeyCode::Char('z'), KeyModifiers::NONE, false);
+
+    assert!(!keys.is_pending());
+}
+
+#[test]
+fn visual_keys() {
+    assert_eq!(commands("d", true), [ command(Action::Visual(VisualAction::Delete), None, None) ]);
+    assert_eq!(commands("c~U\x1b", true), [
+        command(Action::Visual(VisualAction::Change), None, None),
+        command(Action::Visual(VisualAction::ToggleCase), None, None),
+        command(Action::Visual(VisualAction::Uppercase), None, None),
+        command(Action::Visual(VisualAction::Exit), None, None),
+    ]);
+    assert_eq!(commands("V", true), [ command(Action::Select(SelectionKind::Line), None, None) ]);
+
+    // The same keys mean something else with nothing selected
+    assert_eq!(commands("u~/\x1b", false), [
+        command(Action::Undo, None, None),
+        command(Action::Search(SearchDirection::Forward), None, None),
+        command(Action::Escape, None, None),
+    ]);
+
+    let mut keys = Keys::new();
+    let block = keys.handle(KeyCode::Char('v'), KeyModifiers::CONTROL, true);
+
+    assert_eq!(block, Some(command(Action::Select(SelectionKind::Block), None, None)));
+}
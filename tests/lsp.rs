@@ -0,0 +1,131 @@
+use std::path::{ Path, PathBuf };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use ropey::Rope;
+
+use gof_lib::diagnostics::Severity;
+use gof_lib::lsp::{ LanguageClient, LspEvent, ServerConfig };
+
+const TEXT: &str = "fn main() {\n    // TODO: ünïcode 🦀 before FIXME\n    let answer = 42;\n    ans\n}\n";
+
+fn mock_server() -> ServerConfig {
+    ServerConfig::new("mock", env!("CARGO_BIN_EXE_mock-lsp")).extensions(&[ "mock" ])
+}
+
+/// A file for the client to name; the mock server only sees what it's sent.
+fn file(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gof-lsp-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(name);
+    std::fs::write(&path, TEXT).unwrap();
+
+    path
+}
+
+/// Polls until an event `matching` arrives, or gives up after a few seconds.
+fn wait_for<F>(client: &mut LanguageClient, matching: F) -> LspEvent
+where F: Fn(&LspEvent) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    while Instant::now() < deadline {
+        if let Some(event) = client.poll().into_iter().find(&matching) {
+            return event;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    panic!("Timed out waiting for the server");
+}
+
+fn start(path: &Path) -> (LanguageClient, Rope) {
+    let text = Rope::from_str(TEXT);
+    let mut client = LanguageClient::spawn(&mock_server(), path.parent().unwrap(), None).unwrap();
+
+    client.did_open(path, &text, 0);
+
+    (client, text)
+}
+
+#[test]
+fn diagnostics_are_converted_to_char_columns() {
+    let path = file("diagnostics.mock");
+    let (mut client, _) = start(&path);
+
+    let LspEvent::Diagnostics { path: reported, diagnostics } = wait_for(&mut client, |e| matches!(e, LspEvent::Diagnostics { .. })) else {
+        unreachable!()
+    };
+
+    assert_eq!(reported, path);
+    assert_eq!(diagnostics.len(), 2);
+
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!((diagnostics[0].start, diagnostics[0].end), ((7, 1), (11, 1)));
+
+    // Past a char taking two UTF-16 units, so the server's columns are one
+    // more than the client's
+    assert_eq!(diagnostics[1].severity, Severity::Error);
+    assert_eq!((diagnostics[1].start, diagnostics[1].end), ((30, 1), (35, 1)));
+    assert_eq!(diagnostics[1].message, "found FIXME");
+}
+
+#[test]
+fn edits_are_synced() {
+    let path = file("sync.mock");
+    let (mut client, mut text) = start(&path);
+
+    wait_for(&mut client, |e| matches!(e, LspEvent::Diagnostics { .. }));
+
+    text.remove(0..text.len_chars());
+    text.insert(0, "all fixed\n");
+    client.did_change(&path, &text, 1);
+
+    let event = wait_for(&mut client, |e| matches!(e, LspEvent::Diagnostics { .. }));
+    assert_eq!(event, LspEvent::Diagnostics { path, diagnostics: Vec::new() });
+}
+
+#[test]
+fn hover_completion_and_definition() {
+    let path = file("requests.mock");
+    let (mut client, _) = start(&path);
+
+    // Requests wait for the server to be ready
+    wait_for(&mut client, |e| matches!(e, LspEvent::Diagnostics { .. }));
+    assert!(client.is_initialized());
+
+    assert!(client.hover(&path, (4, 0)));
+    let LspEvent::Hover { text, position, .. } = wait_for(&mut client, |e| matches!(e, LspEvent::Hover { .. })) else {
+        unreachable!()
+    };
+    assert_eq!(position, (4, 0));
+    assert_eq!(text, "main\nA word of 4 letters");
+
+    assert!(client.completion(&path, (7, 3)));
+    let LspEvent::Completion { items, .. } = wait_for(&mut client, |e| matches!(e, LspEvent::Completion { .. })) else {
+        unreachable!()
+    };
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert_eq!(labels, vec![ "answer" ]);
+    assert_eq!(items[0].text, "answer");
+
+    assert!(client.definition(&path, (10, 2)));
+    let LspEvent::Definition(locations) = wait_for(&mut client, |e| matches!(e, LspEvent::Definition(_))) else {
+        unreachable!()
+    };
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations[0].path, path);
+    assert_eq!(locations[0].position, (8, 2));
+}
+
+#[test]
+fn exiting_is_noticed() {
+    let config = ServerConfig::new("mock", "sh").args(&[ "-c", "exit 0" ]);
+    let mut client = LanguageClient::spawn(&config, Path::new("."), None).unwrap();
+
+    let event = wait_for(&mut client, |e| matches!(e, LspEvent::Exited { .. }));
+
+    assert_eq!(event, LspEvent::Exited { language_id: "mock".to_string() });
+    assert!(!client.is_running());
+}
@@ -0,0 +1,198 @@
+use ropey::Rope;
+
+use gof_lib::motion::{ Motion, Span, TextObject };
+
+const TEXT: &str = "fn main() {\n    let (a, b) = (1, foo(2));\n    call(\"some text\", x);\n\n    done.now();\n}\n";
+
+fn target(motion: Motion, cursor: (usize, usize), count: Option<usize>) -> Option<(usize, usize)> {
+    motion.target(&Rope::from_str(TEXT), cursor, count)
+}
+
+/// The text an operator with `motion` would act on.
+fn spanned(motion: Motion, cursor: (usize, usize), count: Option<usize>) -> String {
+    let content = Rope::from_str(TEXT);
+    text_of(&content, motion.span(&content, cursor, count))
+}
+
+fn object(keys: &str, cursor: (usize, usize), count: Option<usize>) -> String {
+    let content = Rope::from_str(TEXT);
+    let mut keys = keys.chars();
+    let object = TextObject::from_keys(keys.next().unwrap(), keys.next().unwrap()).unwrap();
+
+    text_of(&content, object.span(&content, cursor, count))
+}
+
+fn text_of(content: &Rope, span: Option<Span>) -> String {
+    match span {
+        Some(Span::Chars(range)) => content.slice(range).to_string(),
+        Some(Span::Lines(lines)) => {
+            let start = content.line_to_char(*lines.start());
+            let end = content.line_to_char(*lines.end() + 1);
+            content.slice(start..end).to_string()
+        },
+        None => "<none>".to_string(),
+    }
+}
+
+#[test]
+fn word_forward() {
+    assert_eq!(target(Motion::WordForward, (0, 0), None), Some((3, 0)));
+    assert_eq!(target(Motion::WordForward, (3, 0), None), Some((7, 0)));
+    assert_eq!(target(Motion::WordForward, (3, 0), Some(2)), Some((10, 0)));
+    // Over the end of a line, and stopping at an empty one
+    assert_eq!(target(Motion::WordForward, (10, 0), None), Some((4, 1)));
+    assert_eq!(target(Motion::WordForward, (21, 2), Some(3)), Some((0, 3)));
+    // Never past the last char
+    assert_eq!(target(Motion::WordForward, (0, 5), Some(5)), Some((0, 5)));
+
+    assert_eq!(spanned(Motion::WordForward, (3, 0), None), "main");
+    assert_eq!(spanned(Motion::WordForward, (3, 0), Some(2)), "main() ");
+    // The last word on a line stops at its end
+    assert_eq!(spanned(Motion::WordForward, (8, 2), Some(6)), "(\"some text\", x);");
+}
+
+#[test]
+fn word_backward() {
+    assert_eq!(target(Motion::WordBackward, (7, 0), None), Some((3, 0)));
+    assert_eq!(target(Motion::WordBackward, (5, 0), None), Some((3, 0)));
+    assert_eq!(target(Motion::WordBackward, (4, 1), None), Some((10, 0)));
+    assert_eq!(target(Motion::WordBackward, (4, 4), None), Some((0, 3)));
+    assert_eq!(target(Motion::WordBackward, (0, 0), None), Some((0, 0)));
+
+    assert_eq!(spanned(Motion::WordBackward, (7, 0), Some(2)), "fn main");
+}
+
+#[test]
+fn word_end() {
+    assert_eq!(target(Motion::WordEnd, (0, 0), None), Some((1, 0)));
+    assert_eq!(target(Motion::WordEnd, (1, 0), None), Some((6, 0)));
+    assert_eq!(target(Motion::WordEnd, (10, 0), None), Some((6, 1)));
+    assert_eq!(target(Motion::WordEnd, (3, 0), Some(2)), Some((8, 0)));
+
+    assert_eq!(spanned(Motion::WordEnd, (3, 0), None), "main");
+    assert_eq!(spanned(Motion::WordEnd, (4, 1), Some(2)), "let (");
+}
+
+#[test]
+fn line_start_and_end() {
+    assert_eq!(target(Motion::LineStart, (9, 1), None), Some((0, 1)));
+    assert_eq!(target(Motion::LineEnd, (3, 0), None), Some((10, 0)));
+    assert_eq!(target(Motion::LineEnd, (3, 0), Some(2)), Some((28, 1)));
+    assert_eq!(target(Motion::LineEnd, (0, 3), None), Some((0, 3)));
+
+    assert_eq!(spanned(Motion::LineStart, (4, 1), None), "    ");
+    assert_eq!(spanned(Motion::LineEnd, (7, 0), None), "() {");
+    // Nothing to take on an empty line, not even its line break
+    assert_eq!(spanned(Motion::LineEnd, (0, 3), None), "");
+}
+
+#[test]
+fn first_and_last_line() {
+    assert_eq!(target(Motion::FirstLine, (8, 4), None), Some((0, 0)));
+    assert_eq!(target(Motion::LastLine, (8, 0), None), Some((0, 5)));
+    assert_eq!(target(Motion::LastLine, (0, 0), Some(2)), Some((4, 1)));
+    assert_eq!(target(Motion::FirstLine, (0, 0), Some(5)), Some((4, 4)));
+    assert_eq!(target(Motion::FirstLine, (0, 0), Some(99)), Some((0, 5)));
+
+    assert_eq!(spanned(Motion::LastLine, (3, 4), None), "    done.now();\n}\n");
+    assert_eq!(spanned(Motion::FirstLine, (3, 1), None), "fn main() {\n    let (a, b) = (1, foo(2));\n");
+}
+
+#[test]
+fn find_and_till() {
+    assert_eq!(target(Motion::FindForward('('), (0, 1), None), Some((8, 1)));
+    assert_eq!(target(Motion::FindForward('('), (0, 1), Some(2)), Some((17, 1)));
+    assert_eq!(target(Motion::TillForward('('), (0, 1), None), Some((7, 1)));
+    assert_eq!(target(Motion::FindBackward('('), (28, 1), None), Some((24, 1)));
+    assert_eq!(target(Motion::TillBackward('('), (28, 1), None), Some((25, 1)));
+    // Only on the cursor's line
+    assert_eq!(target(Motion::FindForward('z'), (0, 1), None), None);
+    assert_eq!(target(Motion::FindForward('f'), (0, 1), Some(2)), None);
+
+    assert_eq!(spanned(Motion::FindForward(','), (9, 1), None), "a,");
+    assert_eq!(spanned(Motion::TillForward(','), (9, 1), None), "a");
+    assert_eq!(spanned(Motion::FindBackward('('), (11, 1), None), "(a,");
+    assert_eq!(spanned(Motion::TillBackward('('), (11, 1), None), "a,");
+}
+
+#[test]
+fn matching_bracket() {
+    assert_eq!(target(Motion::MatchingBracket, (17, 1), None), Some((27, 1)));
+    assert_eq!(target(Motion::MatchingBracket, (27, 1), None), Some((17, 1)));
+    // The first bracket after the cursor, across lines
+    assert_eq!(target(Motion::MatchingBracket, (0, 0), None), Some((8, 0)));
+    assert_eq!(target(Motion::MatchingBracket, (10, 0), None), Some((0, 5)));
+    assert_eq!(target(Motion::MatchingBracket, (0, 5), None), Some((10, 0)));
+    assert_eq!(target(Motion::MatchingBracket, (0, 3), None), None);
+
+    assert_eq!(spanned(Motion::MatchingBracket, (17, 1), None), "(1, foo(2))");
+}
+
+#[test]
+fn left_right_up_down() {
+    assert_eq!(target(Motion::Left, (5, 1), Some(2)), Some((3, 1)));
+    assert_eq!(target(Motion::Right, (5, 1), Some(99)), Some((28, 1)));
+    assert_eq!(target(Motion::Down, (5, 1), Some(2)), Some((5, 3)));
+    assert_eq!(target(Motion::Up, (5, 1), Some(9)), Some((5, 0)));
+
+    assert_eq!(spanned(Motion::Left, (8, 1), Some(4)), "let ");
+    assert_eq!(spanned(Motion::Right, (4, 1), Some(3)), "let");
+    assert_eq!(spanned(Motion::Down, (4, 2), None), "    call(\"some text\", x);\n\n");
+    assert_eq!(spanned(Motion::Up, (4, 1), None), "fn main() {\n    let (a, b) = (1, foo(2));\n");
+}
+
+#[test]
+fn words() {
+    assert_eq!(object("iw", (4, 0), None), "main");
+    assert_eq!(object("aw", (4, 0), None), " main");
+    assert_eq!(object("aw", (0, 0), None), "fn ");
+    assert_eq!(object("iw", (1, 1), None), "    ");
+    assert_eq!(object("aw", (1, 1), None), "    let");
+    assert_eq!(object("iw", (4, 1), Some(3)), "let (");
+    assert_eq!(object("aw", (4, 1), Some(2)), "let (");
+    // Punctuation next to a word is a word of its own
+    assert_eq!(object("aw", (11, 4), None), "now");
+    assert_eq!(object("aw", (16, 2), None), " text");
+    assert_eq!(object("iw", (0, 3), None), "<none>");
+}
+
+#[test]
+fn brackets() {
+    assert_eq!(object("i(", (9, 1), None), "a, b");
+    assert_eq!(object("a(", (9, 1), None), "(a, b)");
+    assert_eq!(object("i)", (25, 1), None), "2");
+    assert_eq!(object("ib", (19, 1), None), "1, foo(2)");
+    assert_eq!(object("i(", (25, 1), Some(2)), "1, foo(2)");
+    // On a bracket itself
+    assert_eq!(object("a(", (27, 1), None), "(1, foo(2))");
+    assert_eq!(object("a(", (28, 1), None), "<none>");
+    assert_eq!(object("a{", (4, 2), None), &TEXT[10..TEXT.len() - 1]);
+    assert_eq!(object("i(", (4, 0), None), "<none>");
+    assert_eq!(object("i(", (0, 3), None), "<none>");
+}
+
+#[test]
+fn quotes() {
+    assert_eq!(object("i\"", (12, 2), None), "some text");
+    assert_eq!(object("a\"", (12, 2), None), "\"some text\"");
+    assert_eq!(object("i\"", (9, 2), None), "some text");
+    // Before the quotes, the first pair after the cursor
+    assert_eq!(object("i\"", (0, 2), None), "some text");
+    assert_eq!(object("i\"", (22, 2), None), "<none>");
+    assert_eq!(object("i'", (12, 2), None), "<none>");
+
+    let content = Rope::from_str("say \"a \\\"b\\\"\"  then\n");
+    let around = TextObject::from_keys('a', '"').unwrap();
+    assert_eq!(text_of(&content, around.span(&content, (6, 0), None)), "\"a \\\"b\\\"\"  ");
+}
+
+#[test]
+fn paragraphs() {
+    assert_eq!(object("ip", (0, 1), None), "fn main() {\n    let (a, b) = (1, foo(2));\n    call(\"some text\", x);\n");
+    assert_eq!(object("ap", (0, 1), None), "fn main() {\n    let (a, b) = (1, foo(2));\n    call(\"some text\", x);\n\n");
+    assert_eq!(object("ip", (0, 3), None), "\n");
+    assert_eq!(object("ap", (0, 3), None), "\n    done.now();\n}\n");
+    assert_eq!(object("ip", (0, 0), Some(2)), "fn main() {\n    let (a, b) = (1, foo(2));\n    call(\"some text\", x);\n\n");
+    // The last paragraph takes the blank lines before it
+    assert_eq!(object("ap", (0, 5), None), "\n    done.now();\n}\n");
+}
@@ -0,0 +1,43 @@
+use gof_lib::registers::{ self, Register, RegisterKind, Registers };
+
+fn text(registers: &Registers, name: char) -> Option<(&str, RegisterKind)> {
+    registers.get(Some(name)).map(|r| (r.text.as_str(), r.kind))
+}
+
+#[test]
+fn base64_padding() {
+    assert_eq!(registers::base64(b""), "");
+    // No padding, then two and one `=`
+    assert_eq!(registers::base64(b"abc"), "YWJj");
+    assert_eq!(registers::base64(b"abcd"), "YWJjZA==");
+    assert_eq!(registers::base64(b"abcde"), "YWJjZGU=");
+    assert_eq!(registers::base64("é\n".as_bytes()), "w6kK");
+    assert_eq!(registers::base64(&[ 0xff, 0xfe ]), "//4=");
+}
+
+#[test]
+fn uppercase_appends_to_lowercase() {
+    let mut registers = Registers::new();
+
+    registers.set(Some('a'), Register::new("one".to_string(), RegisterKind::Charwise));
+    registers.set(Some('A'), Register::new(" two".to_string(), RegisterKind::Charwise));
+
+    assert_eq!(text(&registers, 'a'), Some(("one two", RegisterKind::Charwise)));
+    // The unnamed register gets the whole of it
+    assert_eq!(registers.get(None).map(|r| r.text.as_str()), Some("one two"));
+
+    // Lines go on lines of their own, and make the whole register linewise
+    registers.set(Some('A'), Register::new("three\n".to_string(), RegisterKind::Linewise));
+    assert_eq!(text(&registers, 'a'), Some(("one two\nthree\n", RegisterKind::Linewise)));
+
+    registers.set(Some('A'), Register::new("four".to_string(), RegisterKind::Charwise));
+    assert_eq!(text(&registers, 'A'), Some(("one two\nthree\nfour\n", RegisterKind::Linewise)));
+
+    // Appending to an empty register fills it
+    registers.set(Some('B'), Register::new("b".to_string(), RegisterKind::Charwise));
+    assert_eq!(text(&registers, 'b'), Some(("b", RegisterKind::Charwise)));
+
+    // Setting the lowercase one starts over
+    registers.set(Some('a'), Register::new("new".to_string(), RegisterKind::Charwise));
+    assert_eq!(text(&registers, 'a'), Some(("new", RegisterKind::Charwise)));
+}
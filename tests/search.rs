@@ -0,0 +1,84 @@
+use ropey::Rope;
+
+use gof_lib::search::{ Search, SearchDirection, SearchMatch, SearchOptions };
+
+fn search(pattern: &str) -> Search {
+    Search::new(pattern, SearchDirection::Forward, SearchOptions::default()).unwrap()
+}
+
+fn literal(pattern: &str) -> Search {
+    let options = SearchOptions { regex: false, ..SearchOptions::default() };
+    Search::new(pattern, SearchDirection::Forward, options).unwrap()
+}
+
+fn found(search: &Search, text: &str) -> Vec<String> {
+    search.find_in_str(text).map(|range| text[range].to_string()).collect()
+}
+
+#[test]
+fn smart_case() {
+    assert_eq!(found(&search("foo"), "foo Foo FOO"), [ "foo", "Foo", "FOO" ]);
+    assert_eq!(found(&search("Foo"), "foo Foo FOO"), [ "Foo" ]);
+
+    let options = SearchOptions { smart_case: false, ..SearchOptions::default() };
+    let search = Search::new("foo", SearchDirection::Forward, options).unwrap();
+    assert_eq!(found(&search, "foo Foo"), [ "foo" ]);
+}
+
+#[test]
+fn regex_escapes_are_not_uppercase_text() {
+    assert_eq!(found(&search(r"a\S"), "ab AB"), [ "ab", "AB" ]);
+    assert_eq!(found(&search(r"\Bo\W"), "Foo. FOO!"), [ "o.", "O!" ]);
+    assert_eq!(found(&search(r"\D+x"), "ax1AX"), [ "ax", "AX" ]);
+    assert_eq!(found(&search(r"\p{Greek}a"), "αa ΑA"), [ "αa", "ΑA" ]);
+
+    // In literal text they are text
+    assert_eq!(found(&literal(r"a\S"), r"a\s a\S"), [ r"a\S" ]);
+}
+
+#[test]
+fn case_flags() {
+    assert_eq!(found(&search(r"\cFoo"), "foo Foo"), [ "foo", "Foo" ]);
+    assert_eq!(found(&search(r"foo\C"), "foo Foo"), [ "foo" ]);
+    // `\c` wins over `\C`
+    assert_eq!(found(&search(r"\Cfoo\c"), "foo Foo"), [ "foo", "Foo" ]);
+    assert_eq!(found(&literal(r"\ca.b"), "A.B axb"), [ "A.B" ]);
+}
+
+#[test]
+fn escaped_backslash_is_not_a_flag() {
+    assert_eq!(found(&search(r"a\\c"), r"a\c A\C"), [ r"a\c", r"A\C" ]);
+    assert_eq!(found(&search(r"a\\C"), r"a\c a\C A\C"), [ r"a\C" ]);
+    assert_eq!(search(r"a\\c").pattern(), r"a\\c");
+}
+
+#[test]
+fn literal_patterns() {
+    assert_eq!(found(&literal("a.b"), "axb a.b"), [ "a.b" ]);
+    assert_eq!(found(&literal("(x)"), "x (x)"), [ "(x)" ]);
+}
+
+#[test]
+fn matches_are_in_chars() {
+    let text = Rope::from_str("été, été\n");
+
+    assert_eq!(search("té").find_all(&text), [
+        SearchMatch { line: 0, start: 1, end: 3 },
+        SearchMatch { line: 0, start: 6, end: 8 },
+    ]);
+}
+
+#[test]
+fn find_next_wraps() {
+    let text = Rope::from_str("one\ntwo one\nthree\n");
+    let search = search("one");
+
+    let next = |from| search.find_next(&text, from, SearchDirection::Forward).map(|m| (m.start, m.line));
+    let previous = |from| search.find_next(&text, from, SearchDirection::Backward).map(|m| (m.start, m.line));
+
+    assert_eq!(next((0, 0)), Some((4, 1)));
+    assert_eq!(next((4, 1)), Some((0, 0)));
+    assert_eq!(previous((4, 1)), Some((0, 0)));
+    assert_eq!(previous((0, 0)), Some((4, 1)));
+    assert_eq!(literal("four").find_next(&text, (0, 0), SearchDirection::Forward), None);
+}
@@ -0,0 +1,153 @@
+use regex::Regex;
+use ropey::Rope;
+
+use gof_lib::command::{ self, Command };
+use gof_lib::history::History;
+use gof_lib::substitute::{ PendingReplacement, Substitution };
+use gof_lib::text;
+
+/// The substitution `:s` would start for `input` over `lines`.
+fn substitution(input: &str, lines: (usize, usize)) -> Substitution {
+    let Ok(Command::Substitute { pattern, replacement, flags, .. }) = command::parse(input) else {
+        panic!("not a substitution: {}", input);
+    };
+
+    Substitution::new(Regex::new(&pattern).unwrap(), replacement, flags.global, lines, (0, 0))
+}
+
+/// `text` after `input` is run over every line, without confirmation.
+fn substitute(input: &str, text: &str) -> String {
+    let mut text = Rope::from_str(text);
+    let last = text::line_count(&text) - 1;
+
+    substitution(input, (0, last)).replace_all(&mut text);
+    text.to_string()
+}
+
+/// Answers the confirmation prompt with each of `keys` like the buffer does,
+/// returning the text and how many replacements were made.
+fn confirm(input: &str, text: &str, keys: &str) -> (String, usize) {
+    let mut text = Rope::from_str(text);
+    let last = text::line_count(&text) - 1;
+    let mut substitution = substitution(input, (0, last));
+
+    for key in keys.chars() {
+        let Some(pending) = substitution.next_match(&text) else { break };
+
+        match key {
+            'y' => substitution.replace(&mut text, &pending),
+            'n' => substitution.skip(&text, &pending),
+            'a' => {
+                substitution.replace(&mut text, &pending);
+                substitution.replace_all(&mut text);
+                break;
+            },
+            'l' => {
+                substitution.replace(&mut text, &pending);
+                break;
+            },
+            'q' => break,
+            _ => unreachable!(),
+        }
+    }
+
+    (text.to_string(), substitution.replacements())
+}
+
+#[test]
+fn capture_groups() {
+    assert_eq!(substitute(r"s/(\w+)=(\w+)/\2=\1/", "a=b c=d\n"), "b=a c=d\n");
+    assert_eq!(substitute(r"s/(\w+)=(\w+)/\2=\1/g", "a=b c=d\n"), "b=a d=c\n");
+    assert_eq!(substitute(r"s/\w+/<&>/g", "ab cd\n"), "<ab> <cd>\n");
+    assert_eq!(substitute(r"s/a/\$1/", "a\n"), "$1\n");
+}
+
+#[test]
+fn global_flag() {
+    assert_eq!(substitute("s/o/0/", "foo\nboo\n"), "f0o\nb0o\n");
+    assert_eq!(substitute("s/o/0/g", "foo\nboo\n"), "f00\nb00\n");
+
+    let mut text = Rope::from_str("foo\nbar\nboo\n");
+    let mut substitution = substitution("s/o/0/g", (0, 2));
+    substitution.replace_all(&mut text);
+
+    assert_eq!((substitution.replacements(), substitution.lines_changed()), (4, 2));
+    // The cursor ends on the last replacement
+    assert_eq!(substitution.cursor(), Some((2, 2)));
+}
+
+#[test]
+fn only_lines_in_range() {
+    let mut text = Rope::from_str("a\na\na\na\n");
+    substitution("s/a/b/", (1, 2)).replace_all(&mut text);
+
+    assert_eq!(text, "a\nb\nb\na\n");
+}
+
+#[test]
+fn empty_matches() {
+    assert_eq!(substitute("s/$/x/g", "ab\ncd\n"), "abx\ncdx\n");
+    assert_eq!(substitute("s/^/> /", "ab\ncd\n"), "> ab\n> cd\n");
+    assert_eq!(substitute("s/x*/-/g", "abc\n"), "-a-b-c-\n");
+    // An empty match right where a match ended isn't a match of its own
+    assert_eq!(substitute("s/x*/-/g", "axxb\n"), "-a-b-\n");
+    assert_eq!(substitute("s/x*/-/g", "é\n"), "-é-\n");
+}
+
+#[test]
+fn newlines_in_the_replacement() {
+    assert_eq!(substitute(r"s/, /,\r/g", "a, b, c\nd, e\n"), "a,\nb,\nc\nd,\ne\n");
+    assert_eq!(substitute(r"s/-/\n/", "a-b-c\nd-e\n"), "a\nb-c\nd\ne\n");
+
+    // The lines pushed down are still searched, and the range grows with them
+    let mut text = Rope::from_str("a-b\nc-d\nleft-alone\n");
+    let mut substitution = substitution(r"s/-/\n/g", (0, 1));
+    substitution.replace_all(&mut text);
+
+    assert_eq!(text, "a\nb\nc\nd\nleft-alone\n");
+    assert_eq!((substitution.replacements(), substitution.lines_changed()), (2, 2));
+    assert_eq!(substitution.cursor(), Some((1, 2)));
+}
+
+#[test]
+fn confirmation() {
+    let text = "x x\nx\nx\n";
+
+    assert_eq!(confirm("s/x/y/g", text, "yyyy"), ("y y\ny\ny\n".to_string(), 4));
+    assert_eq!(confirm("s/x/y/g", text, "nyny"), ("x y\nx\ny\n".to_string(), 2));
+    assert_eq!(confirm("s/x/y/g", text, "nna"), ("x x\ny\ny\n".to_string(), 2));
+    assert_eq!(confirm("s/x/y/g", text, "yq"), ("y x\nx\nx\n".to_string(), 1));
+    assert_eq!(confirm("s/x/y/g", text, "nl"), ("x y\nx\nx\n".to_string(), 1));
+    // Without `g`, skipping a match moves on to the next line
+    assert_eq!(confirm("s/x/y/", text, "ny"), ("x x\ny\nx\n".to_string(), 1));
+}
+
+#[test]
+fn undone_in_one_step() {
+    let original = "one two\ntwo\nthree two\n";
+    let mut text = Rope::from_str(original);
+    let mut history = History::new();
+    let mut substitution = Substitution::new(Regex::new("two").unwrap(), "2\n".to_string(), true, (0, 2), (3, 1));
+
+    substitution.replace_all(&mut text);
+    history.commit(substitution.finish());
+    assert_eq!(text, "one 2\n\n2\n\nthree 2\n\n");
+
+    assert_eq!(history.undo(&mut text), Some((3, 1)));
+    assert_eq!(text, original);
+    assert_eq!(history.undo(&mut text), None);
+
+    history.redo(&mut text);
+    assert_eq!(text, "one 2\n\n2\n\nthree 2\n\n");
+}
+
+#[test]
+fn columns_of_a_match_the_text_changed_under() {
+    let pending = PendingReplacement { line: 1, range: 2..6, replacement: String::new() };
+
+    assert_eq!(pending.columns(&Rope::from_str("\nab éf gh\n")), (2, 5));
+    // Shorter since, or cut in the middle of a char
+    assert_eq!(pending.columns(&Rope::from_str("\nab é\n")), (2, 4));
+    assert_eq!(pending.columns(&Rope::from_str("\naé\n")), (1, 2));
+    assert_eq!(pending.columns(&Rope::from_str("one line\n")), (0, 0));
+}
@@ -0,0 +1,125 @@
+use ropey::Rope;
+
+use gof_lib::text::{ self, Decoder, Encoding, LineEnding };
+
+const TEXT: &str = "plain ascii\nLatin-1: café\nbeyond: € 🦀\n";
+
+/// `text` as a file in `encoding` would hold it, byte order mark included.
+fn file(encoding: Encoding, text: &str) -> Vec<u8> {
+    let mut bytes = encoding.bom().to_vec();
+    bytes.extend(encoding.encode(text).unwrap());
+    bytes
+}
+
+#[test]
+fn detecting_encodings() {
+    assert_eq!(Encoding::detect(b"plain"), Encoding::Utf8);
+    assert_eq!(Encoding::detect("café".as_bytes()), Encoding::Utf8);
+    assert_eq!(Encoding::detect(b"caf\xe9"), Encoding::Latin1);
+    assert_eq!(Encoding::detect(b"\xef\xbb\xbfplain"), Encoding::Utf8Bom);
+    assert_eq!(Encoding::detect(b"\xff\xfep\0"), Encoding::Utf16Le);
+    assert_eq!(Encoding::detect(b"\xfe\xff\0p"), Encoding::Utf16Be);
+    assert_eq!(Encoding::detect(b""), Encoding::Utf8);
+}
+
+#[test]
+fn round_trips() {
+    for encoding in [ Encoding::Utf8, Encoding::Utf8Bom, Encoding::Utf16Le, Encoding::Utf16Be ] {
+        let bytes = file(encoding, TEXT);
+
+        assert_eq!(Encoding::detect(&bytes), encoding);
+        assert_eq!(encoding.decode(&bytes).unwrap(), TEXT, "{}", encoding);
+    }
+
+    let latin1 = "Latin-1: café ÿ\n";
+    let bytes = file(Encoding::Latin1, latin1);
+
+    assert_eq!(bytes.len(), latin1.chars().count());
+    assert_eq!(Encoding::detect(&bytes), Encoding::Latin1);
+    assert_eq!(Encoding::Latin1.decode(&bytes).unwrap(), latin1);
+}
+
+#[test]
+fn byte_order_marks() {
+    assert_eq!(file(Encoding::Utf8Bom, "a"), b"\xef\xbb\xbfa");
+    assert_eq!(file(Encoding::Utf16Le, "a"), b"\xff\xfea\0");
+    assert_eq!(file(Encoding::Utf16Be, "a"), b"\xfe\xff\0a");
+    assert_eq!(file(Encoding::Utf8, "a"), b"a");
+
+    // Only skipped where they're expected
+    assert_eq!(Encoding::Utf8Bom.decode(b"\xef\xbb\xbfa").unwrap(), "a");
+    assert_eq!(Encoding::Utf8.decode(b"\xef\xbb\xbfa").unwrap(), "\u{feff}a");
+}
+
+#[test]
+fn unencodable_chars() {
+    let e = Encoding::Latin1.encode("5 €").unwrap_err();
+    assert!(e.to_string().contains("latin-1"), "{}", e);
+
+    assert!(Encoding::Latin1.encode("ÿ").is_ok());
+    assert!(Encoding::Latin1.encode("Ā").is_err());
+}
+
+#[test]
+fn invalid_input() {
+    assert!(Encoding::Utf8.decode(b"caf\xe9").is_err());
+    assert!(Encoding::Utf16Le.decode(b"\xff\xfea\0b").is_err());
+    // A lone surrogate
+    assert!(Encoding::Utf16Le.decode(b"\xff\xfe\x3d\xd8").is_err());
+}
+
+#[test]
+fn decoding_in_pieces() {
+    for encoding in [ Encoding::Utf8, Encoding::Utf8Bom, Encoding::Utf16Le, Encoding::Utf16Be ] {
+        let bytes = file(encoding, TEXT);
+
+        // Every split point, so BOMs, multi-byte chars and surrogate pairs
+        // are all cut somewhere
+        for split in 0..bytes.len() {
+            let mut decoder = Decoder::new(encoding);
+            let mut text = decoder.decode(&bytes[..split], false).unwrap();
+            text += &decoder.decode(&bytes[split..], false).unwrap();
+            text += &decoder.decode(&[], true).unwrap();
+
+            assert_eq!(text, TEXT, "{} split at {}", encoding, split);
+        }
+    }
+
+    let mut decoder = Decoder::new(Encoding::Utf8);
+    assert_eq!(decoder.decode(b"caf\xc3", false).unwrap(), "caf");
+    assert!(decoder.decode(b"", true).is_err());
+}
+
+#[test]
+fn detecting_line_endings() {
+    let detect = |text: &str| LineEnding::detect(&Rope::from_str(text));
+
+    assert_eq!(detect("a\nb\n"), LineEnding::Lf);
+    assert_eq!(detect("a\r\nb\r\n"), LineEnding::Crlf);
+    assert_eq!(detect("a\r\nb\n"), LineEnding::Mixed);
+    assert_eq!(detect("a\nb\r\nc"), LineEnding::Mixed);
+    // Without any line break, or with only the last one missing
+    assert_eq!(detect("a"), LineEnding::Lf);
+    assert_eq!(detect("a\r\nb"), LineEnding::Crlf);
+    // A lone CR isn't a line break of its own
+    assert_eq!(detect("a\rb\n"), LineEnding::Lf);
+}
+
+#[test]
+fn normalizing_line_endings() {
+    let text = "a\r\nb\nc";
+
+    assert_eq!(LineEnding::Lf.normalize(text), "a\nb\nc");
+    assert_eq!(LineEnding::Crlf.normalize(text), "a\r\nb\r\nc");
+    assert_eq!(LineEnding::Mixed.normalize(text), text);
+    assert_eq!(LineEnding::Crlf.normalize("a\r\n"), "a\r\n");
+}
+
+#[test]
+fn counting_lines() {
+    assert_eq!(text::line_count(&Rope::from_str("")), 1);
+    assert_eq!(text::line_count(&Rope::from_str("a")), 1);
+    assert_eq!(text::line_count(&Rope::from_str("a\n")), 1);
+    assert_eq!(text::line_count(&Rope::from_str("a\r\nb\r\n")), 2);
+    assert_eq!(text::line_count(&Rope::from_str("a\n\n")), 2);
+}
@@ -0,0 +1,209 @@
+use crossterm::style::{ Attribute, Color, ContentStyle };
+
+use gof_lib::vt::Screen;
+
+/// A `width` by `height` screen after `bytes` are printed to it.
+fn screen(width: usize, height: usize, bytes: &str) -> Screen {
+    let mut screen = Screen::new(width, height);
+    screen.process(bytes.as_bytes());
+    screen
+}
+
+/// Every row's text, without the blanks at the end.
+fn rows(screen: &Screen) -> Vec<String> {
+    screen.lines().iter()
+        .map(|line| line.iter_chunks().map(|(text, _)| text).collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+/// The style of the cell at `column` on `row`.
+fn style_at(screen: &Screen, column: usize, row: usize) -> ContentStyle {
+    let line = &screen.lines()[row];
+    let mut start = 0;
+
+    for (text, style) in line.iter_chunks() {
+        start += text.chars().count();
+
+        if column < start {
+            return style;
+        }
+    }
+
+    panic!("no cell at {}, {}", column, row);
+}
+
+fn cursor(screen: &Screen) -> (usize, usize) {
+    screen.cursor().expect("the cursor is hidden")
+}
+
+#[test]
+fn cursor_movement_is_clamped() {
+    let mut screen = screen(10, 5, "\x1b[3;4H");
+    assert_eq!(cursor(&screen), (3, 2));
+
+    let moves = [
+        ("\x1b[99;99H", (9, 4)),
+        ("\x1b[99A", (9, 0)),
+        ("\x1b[99D", (0, 0)),
+        ("\x1b[99B", (0, 4)),
+        ("\x1b[99C", (9, 4)),
+        // 0 or nothing counts as 1
+        ("\x1b[0A", (9, 3)),
+        ("\x1b[D", (8, 3)),
+        ("\x1b[0;0H", (0, 0)),
+        ("\x1b[A\x1b[D", (0, 0)),
+    ];
+
+    for (bytes, expected) in moves {
+        screen.process(bytes.as_bytes());
+        assert_eq!(cursor(&screen), expected, "{:?}", bytes);
+    }
+}
+
+#[test]
+fn scroll_regions() {
+    let mut screen = screen(5, 5, "1\r\n2\r\n3\r\n4\r\n5");
+
+    // Rows 2 to 4, which homes the cursor
+    screen.process(b"\x1b[2;4r");
+    assert_eq!(cursor(&screen), (0, 0));
+
+    screen.process(b"\x1b[4;1H\n");
+    assert_eq!(rows(&screen), [ "1", "3", "4", "", "5" ]);
+    assert_eq!(cursor(&screen), (0, 3));
+
+    // Reverse index at the top of the region scrolls it down
+    screen.process(b"\x1b[2;1H\x1bM");
+    assert_eq!(rows(&screen), [ "1", "", "3", "4", "5" ]);
+
+    // Regions without at least two rows are ignored, cursor and all
+    screen.process(b"\x1b[3;3r\x1b[4;2r");
+    assert_eq!(cursor(&screen), (0, 1));
+
+    screen.process(b"\x1b[4;1H\n");
+    assert_eq!(rows(&screen), [ "1", "3", "4", "", "5" ]);
+
+    // The whole screen again
+    screen.process(b"\x1b[r\x1b[5;1H\n");
+    assert_eq!(rows(&screen), [ "3", "4", "", "5", "" ]);
+}
+
+#[test]
+fn scroll_region_bottom_is_clamped() {
+    let mut screen = screen(5, 3, "1\r\n2\r\n3\x1b[2;99r\x1b[3;1H\n");
+    assert_eq!(rows(&screen), [ "1", "3", "" ]);
+
+    screen.process(b"\x1b[0;0r\x1b[3;1H\n");
+    assert_eq!(rows(&screen), [ "3", "", "" ]);
+}
+
+#[test]
+fn extended_colors() {
+    let screen = screen(10, 1, concat!(
+        "\x1b[38;5;196mA",
+        "\x1b[38:5:21mB",
+        "\x1b[48;2;1;2;3mC",
+        "\x1b[0;48:2:4:5:6mD",
+        // With the color space xterm allows before the components
+        "\x1b[0;38:2::7:8:9mE",
+        // Attributes before and after in the same sequence
+        "\x1b[0;1;38;5;10;4mF",
+        "\x1b[0;38;2;1;2;3;48;5;2mG",
+    ));
+
+    assert_eq!(style_at(&screen, 0, 0).foreground_color, Some(Color::AnsiValue(196)));
+    assert_eq!(style_at(&screen, 1, 0).foreground_color, Some(Color::AnsiValue(21)));
+    assert_eq!(style_at(&screen, 2, 0).background_color, Some(Color::Rgb { r: 1, g: 2, b: 3 }));
+    assert_eq!(style_at(&screen, 2, 0).foreground_color, Some(Color::AnsiValue(21)));
+    assert_eq!(style_at(&screen, 3, 0).background_color, Some(Color::Rgb { r: 4, g: 5, b: 6 }));
+    assert_eq!(style_at(&screen, 3, 0).foreground_color, None);
+    assert_eq!(style_at(&screen, 4, 0).foreground_color, Some(Color::Rgb { r: 7, g: 8, b: 9 }));
+
+    let bold = style_at(&screen, 5, 0);
+    assert_eq!(bold.foreground_color, Some(Color::AnsiValue(10)));
+    assert!(bold.attributes.has(Attribute::Bold) && bold.attributes.has(Attribute::Underlined));
+
+    let both = style_at(&screen, 6, 0);
+    assert_eq!((both.foreground_color, both.background_color), (Some(Color::Rgb { r: 1, g: 2, b: 3 }), Some(Color::AnsiValue(2))));
+}
+
+#[test]
+fn alternate_screen() {
+    let mut screen = screen(10, 3, "main\r\n\x1b[31mred");
+    let red = style_at(&screen, 0, 1);
+
+    screen.process(b"\x1b[?1049h");
+    assert_eq!(rows(&screen), [ "", "", "" ]);
+
+    screen.process(b"\x1b[0m\x1b[Halt\x1b[3;1Hbottom");
+    assert_eq!(rows(&screen), [ "alt", "", "bottom" ]);
+
+    // Asking again changes nothing
+    screen.process(b"\x1b[?1049h");
+    assert_eq!(rows(&screen), [ "alt", "", "bottom" ]);
+
+    screen.process(b"\x1b[?1049l");
+    assert_eq!(rows(&screen), [ "main", "red", "" ]);
+    assert_eq!(cursor(&screen), (3, 1));
+
+    // The style from before is back too
+    screen.process(b"!");
+    assert_eq!(style_at(&screen, 3, 1), red);
+}
+
+#[test]
+fn resizing_keeps_the_cursor_row() {
+    let mut screen = screen(10, 5, "1\r\n2\r\n3\r\n4\r\n5");
+    assert_eq!(cursor(&screen), (1, 4));
+
+    // Rows go from the top to keep the cursor's
+    screen.resize(10, 3);
+    assert_eq!(rows(&screen), [ "3", "4", "5" ]);
+    assert_eq!(cursor(&screen), (1, 2));
+
+    screen.process(b"\n");
+    assert_eq!(rows(&screen), [ "4", "5", "" ]);
+
+    screen.resize(4, 5);
+    assert_eq!(rows(&screen), [ "4", "5", "", "", "" ]);
+    assert_eq!(screen.size(), (4, 5));
+
+    // A cursor past the new width is brought back
+    screen.process(b"\x1b[1;4H");
+    screen.resize(2, 5);
+    assert_eq!(cursor(&screen), (1, 0));
+}
+
+#[test]
+fn wrapping_waits_for_the_next_char() {
+    let mut screen = screen(5, 2, "abcde");
+    assert_eq!(rows(&screen), [ "abcde", "" ]);
+    assert_eq!(cursor(&screen), (4, 0));
+
+    screen.process(b"f");
+    assert_eq!(rows(&screen), [ "abcde", "f" ]);
+    assert_eq!(cursor(&screen), (1, 1));
+
+    // A carriage return, or any move, cancels the wrap
+    let screen2 = self::screen(5, 2, "abcde\rx");
+    assert_eq!(rows(&screen2), [ "xbcde", "" ]);
+
+    let screen2 = self::screen(5, 2, "abcde\x1b[Dx");
+    assert_eq!(rows(&screen2), [ "abcxe", "" ]);
+
+    // Wrapping on the last row scrolls
+    screen.process(b"ghij");
+    assert_eq!(cursor(&screen), (4, 1));
+
+    screen.process(b"k");
+    assert_eq!(rows(&screen), [ "fghij", "k" ]);
+}
+
+#[test]
+fn hiding_the_cursor() {
+    let mut screen = screen(5, 2, "\x1b[?25l");
+    assert_eq!(screen.cursor(), None);
+
+    screen.process(b"\x1b[?25h");
+    assert_eq!(screen.cursor(), Some((0, 0)));
+}